@@ -1,4 +1,3 @@
-use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
@@ -41,7 +40,7 @@ impl Default for AIConfig {
 impl AIConfig {
     /// Get the config file path
     fn get_config_path() -> Option<PathBuf> {
-        ProjectDirs::from("", "", "vimgram").map(|p| p.config_dir().join("ai.json"))
+        Some(crate::paths::config_file("ai.json"))
     }
 
     /// Load config from file or environment
@@ -69,6 +68,7 @@ impl AIConfig {
     }
 
     /// Save config to file
+    #[allow(dead_code)] // Not wired up to the UI yet
     pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
         if let Some(path) = Self::get_config_path() {
             if let Some(parent) = path.parent() {
@@ -1,4 +1,15 @@
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::config::{Config, DeletedMessages, NotifyLevel};
+use crate::format::TextFormat;
+use crate::palette::{self, PaletteAction, PaletteEntry};
+use crate::telegram::session_crypto;
+use crate::ui::draw::LayoutCache;
+use crate::ui::glyphs::{glyphs, Glyphs};
 
 /// Application mode (Vim-style)
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -7,10 +18,74 @@ pub enum Mode {
     Insert,
     Search,
     AccountPicker,
-    Command,   // For : commands
-    FindUser,  // For :find username
-    AICommand, // For :ai natural language commands
-    Code,      // For :code coding assistant
+    Command,        // For : commands
+    FindUser,       // For :find username
+    AICommand,      // For :ai natural language commands
+    Code,           // For :code coding assistant
+    WhoAmI,         // For :whoami account details overlay
+    Stats,          // For :stats session metrics overlay
+    LinkPicker,     // Choosing which link of a message to open
+    PinList,        // Browsing the open chat's pinned messages (:pins)
+    Visual,         // Selecting a range of messages to copy (v)
+    Quote,          // Picking the words of a message to quote in a reply (q in visual)
+    TopicPicker,    // Choosing which topic of a forum to show and send to (T)
+    StickerPicker,  // Choosing a saved sticker or GIF to send (:sticker)
+    MessageView,    // Reading the selected message in full (K)
+    NumberJump,     // Typing the number shown next to a chat to open it (f)
+    Confirm,        // y/n prompt before a destructive action
+    Scratchpad,     // Editing local notes in the scratchpad overlay
+    CommandPalette, // Fuzzy-finding an action to run (Ctrl+P)
+}
+
+/// Destructive actions that wait for a y/n confirmation
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfirmAction {
+    ForgetCredentials,
+    LogOutAccount {
+        id: String,
+        name: String,
+    },
+    Disconnect,
+    Block {
+        id: i64,
+        name: String,
+    },
+    Send {
+        chat_id: i64,
+        name: String,
+        members: u32,
+    },
+}
+
+impl ConfirmAction {
+    /// Question shown in the confirmation overlay
+    pub fn prompt(&self) -> String {
+        match self {
+            ConfirmAction::ForgetCredentials => "Delete saved API ID/hash?".to_string(),
+            ConfirmAction::LogOutAccount { name, .. } => format!("Log out {}?", name),
+            ConfirmAction::Disconnect => "Delete session and log out?".to_string(),
+            ConfirmAction::Block { name, .. } => format!("Block {}?", name),
+            ConfirmAction::Send { name, members, .. } => {
+                format!("Send to {} ({} members)?", name, short_count(*members))
+            }
+        }
+    }
+}
+
+/// A count to a few characters: 950, 1.2k, 12k, 3.4M
+pub fn short_count(count: u32) -> String {
+    let scaled = |value: f64, unit: &str| {
+        if value < 9.95 && value.fract() >= 0.05 {
+            format!("{:.1}{}", value, unit)
+        } else {
+            format!("{}{}", value as u32, unit)
+        }
+    };
+    match count {
+        0..=999 => count.to_string(),
+        1_000..=999_999 => scaled(count as f64 / 1_000.0, "k"),
+        _ => scaled(count as f64 / 1_000_000.0, "M"),
+    }
 }
 
 /// Which panel is focused
@@ -20,49 +95,430 @@ pub enum Panel {
     Chats,
 }
 
+/// State of the connection to Telegram, shown next to the friends list title
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ConnectionState {
+    #[default]
+    Online,
+    Reconnecting, // Health check failed, a new connection is being opened
+    Offline,      // Reconnecting failed, retried after a backoff delay
+    Lost,         // Gave up after reconnect_attempts failures, r retries
+}
+
 /// A chat/contact in the friends list
 #[derive(Debug, Clone)]
 pub struct Chat {
     pub id: i64,
     pub name: String,
     pub last_message: Option<String>,
+    pub last_outgoing: bool, // The last message was sent by you
     pub unread: u32,
+    pub muted_until: Option<i64>, // Unix timestamp, MUTE_FOREVER for an open-ended mute
+    pub pinned: Vec<(i32, String)>, // Pinned message ids with their text, newest first
+    pub is_group: bool,           // Group or supergroup, where sender names are shown
+    pub username: Option<String>, // Public @username, matched by :goto
+    pub is_bot: bool,             // Private chat with a bot
+    pub is_channel: bool,         // Broadcast channel, where only admins post
+    pub mentions: u32,            // Unread messages that @-mention you
+    pub read_inbox_max_id: i32,   // Newest incoming message read on any device
+    pub is_blocked: bool,         // A user this account has blocked
+    pub slow_mode_secs: Option<u32>, // Slow mode delay between sends, None until checked
+    pub is_forum: bool,           // Supergroup split into topics
+    pub topics: Vec<(i32, String)>, // Forum topics as (topic id, title), once fetched
+    pub members: Option<u32>,     // Member count of groups and channels, once known
+}
+
+/// Kind of chat the friends list is narrowed to (F or :filter)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChatFilter {
+    Users,
+    Groups,
+    Channels,
+    Bots,
+}
+
+impl ChatFilter {
+    /// Filter `F` switches to from `current`: users, groups, channels, bots, then none
+    pub fn next(current: Option<Self>) -> Option<Self> {
+        match current {
+            None => Some(Self::Users),
+            Some(Self::Users) => Some(Self::Groups),
+            Some(Self::Groups) => Some(Self::Channels),
+            Some(Self::Channels) => Some(Self::Bots),
+            Some(Self::Bots) => None,
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "users" | "user" => Some(Self::Users),
+            "groups" | "group" => Some(Self::Groups),
+            "channels" | "channel" => Some(Self::Channels),
+            "bots" | "bot" => Some(Self::Bots),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Users => "users",
+            Self::Groups => "groups",
+            Self::Channels => "channels",
+            Self::Bots => "bots",
+        }
+    }
+
+    /// Whether a chat is of this kind (the Welcome chat is none of them)
+    pub fn matches(self, chat: &Chat) -> bool {
+        match self {
+            Self::Users => {
+                !chat.is_group && !chat.is_channel && !chat.is_bot && chat.id != WELCOME_CHAT_ID
+            }
+            Self::Groups => chat.is_group,
+            Self::Channels => chat.is_channel,
+            Self::Bots => chat.is_bot,
+        }
+    }
+}
+
+/// Id of the local Welcome chat, which has no Telegram chat behind it
+pub const WELCOME_CHAT_ID: i64 = 1;
+
+/// Id of a forum's General topic, whose messages carry no topic of their own
+pub const GENERAL_TOPIC_ID: i32 = 1;
+
+/// Telegram's `mute_until` value for "muted forever"
+pub const MUTE_FOREVER: i64 = i32::MAX as i64;
+
+impl Chat {
+    /// Whether the chat is muted at the given unix time
+    pub fn is_muted(&self, now: i64) -> bool {
+        self.muted_until.is_some_and(|until| until > now)
+    }
+}
+
+/// Counters for the :stats overlay, since this session started
+#[derive(Debug, Clone)]
+pub struct SessionStats {
+    pub started: Instant,
+    pub sent: u32,       // Messages sent
+    pub received: u32,   // Incoming messages from live updates
+    pub reconnects: u32, // Successful reconnects after the connection dropped
+}
+
+impl SessionStats {
+    fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            sent: 0,
+            received: 0,
+            reconnects: 0,
+        }
+    }
+}
+
+/// How long a status message stays up when no key dismisses it
+const STATUS_MESSAGE_TTL: Duration = Duration::from_secs(5);
+
+/// How many recently opened chats Tab / Alt+number can reach
+pub const MAX_RECENT_CHATS: usize = 9;
+
+/// How many sent messages Up/Down can recall in insert mode
+const MAX_SENT_HISTORY: usize = 100;
+
+/// Current unix time in seconds
+pub fn unix_now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Who a message comes from, for every path that loads or receives messages.
+/// `sender` is the message's (id, name) when it has one, `in_group` whether the
+/// chat is a group or supergroup (not a DM or broadcast channel):
+/// - channel posts carry no sender, so they're from the channel
+/// - anonymous group admins post as the group itself, shown as "Admin"
+/// - senders without a name are deleted accounts in groups
+/// - anything else without a name is from whoever the chat is with
+pub fn resolve_sender(
+    outgoing: bool,
+    post: bool,
+    sender: Option<(i64, &str)>,
+    chat_id: i64,
+    chat_name: &str,
+    in_group: bool,
+) -> String {
+    if outgoing {
+        return "You".to_string();
+    }
+    if post {
+        return chat_name.trim().to_string();
+    }
+    let name = sender.map(|(_, name)| name.trim()).unwrap_or("");
+    match sender {
+        Some((id, _)) if in_group && id == chat_id => "Admin".to_string(),
+        Some(_) if in_group && name.is_empty() => "Deleted Account".to_string(),
+        _ if name.is_empty() => chat_name.trim().to_string(),
+        _ => name.to_string(),
+    }
+}
+
+/// A `:set` boolean value
+fn parse_bool(value: &str) -> Result<bool, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid boolean: {}", value))
+}
+
+/// A `:set` number, `what` naming it in the error ("number", "width", ...)
+fn parse_num<T: std::str::FromStr>(value: &str, what: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid {}: {}", what, value))
+}
+
+/// A `:set` value naming one of a config enum's variants, `choices` listing them for the error
+fn parse_enum<T: serde::de::DeserializeOwned>(
+    key: &str,
+    value: &str,
+    choices: &str,
+) -> Result<T, String> {
+    serde_json::from_value(serde_json::Value::String(value.to_string()))
+        .map_err(|_| format!("Invalid {}: {} ({})", key, value, choices))
+}
+
+/// Name to show next to a message: only incoming group messages with a known
+/// sender get one (in DMs and channels the chat title already says who it is)
+fn sender_display_name(sender: &str, outgoing: bool, is_group: bool) -> Option<String> {
+    let sender = sender.trim();
+    if outgoing || !is_group || sender.is_empty() || sender == "Unknown" {
+        return None;
+    }
+    Some(sender.to_string())
+}
+
+/// Parse a mute duration like `30m`, `8h` or `1d` into seconds
+fn parse_duration(input: &str) -> Option<i64> {
+    let unit = input.chars().last()?;
+    let amount: i64 = input[..input.len() - unit.len_utf8()].parse().ok()?;
+    let secs = match unit {
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        _ => return None,
+    };
+    (amount > 0).then(|| amount.saturating_mul(secs))
+}
+
+/// Byte ranges of the whitespace-separated words of a text
+fn word_spans(text: &str) -> Vec<Range<usize>> {
+    let mut spans = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices() {
+        match (c.is_whitespace(), start) {
+            (true, Some(s)) => {
+                spans.push(s..i);
+                start = None;
+            }
+            (false, None) => start = Some(i),
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        spans.push(s..text.len());
+    }
+    spans
 }
 
 /// A message in a chat
 #[derive(Debug, Clone)]
 pub struct Message {
+    pub id: i32, // Telegram message id (unique per chat)
     pub sender: String,
+    pub display_name: Option<String>, // Name shown next to the message, None to hide it
+    pub forwarded_from: Option<String>, // Original author of a forward ("" if unknown)
+    pub media: Option<String>,        // Label of attached downloadable media, e.g. "Voice (0:14)"
+    pub media_kind: Option<MediaKind>, // Media or call, drawn as a glyph before the label or service text
+    pub kind: MessageKind,
     pub text: String,
+    pub formats: Vec<(Range<usize>, TextFormat)>, // Bold/italic/... spans, byte ranges into text
     pub outgoing: bool,
+    pub deleted: bool, // Deleted on the server, shown as a placeholder
+    pub edited: bool,
+    pub date: i64,          // Unix timestamp the message was sent
+    pub topic: Option<i32>, // Forum topic it was posted in, None for General
+}
+
+impl Message {
+    /// The media label as shown, after the glyph of its kind
+    pub fn media_text(&self, glyphs: &Glyphs) -> Option<String> {
+        let label = self.media.as_deref()?;
+        Some(glyphs.with_media(self.media_kind, label))
+    }
+
+    /// Whether the message belongs to a forum topic (the topic's first message included)
+    pub fn in_topic(&self, topic: i32) -> bool {
+        self.topic.unwrap_or(GENERAL_TOPIC_ID) == topic || self.id == topic
+    }
+}
+
+/// Kind of attached media or call, each drawn with its own glyph
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MediaKind {
+    Photo,
+    Sticker,
+    Gif,
+    Voice,
+    File,
+    Call,
+    VideoCall,
+}
+
+/// What a message is, so service messages get their own styling
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MessageKind {
+    Text,    // Normal message (text and/or media)
+    Service, // Join, pin, call, ...: its text describes the action
+}
+
+/// A Telegram chat folder, resolved to the loaded chats it holds
+#[derive(Debug, Clone)]
+pub struct ChatFolder {
+    pub title: String,
+    pub chat_ids: Vec<i64>,
+    pub exclude_muted: bool,
+    pub exclude_read: bool,
+}
+
+/// A typed message on its way out, with where it goes in the chat
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutgoingMessage {
+    pub chat_id: i64,
+    pub text: String,
+    pub reply_to: Option<i32>,
+    pub quote: Option<Range<usize>>, // Byte range of reply_to's text to quote
+    pub topic: Option<i32>,          // Forum topic it's posted in
+}
+
+/// Details of the logged-in user, cached from `get_me` at startup
+#[derive(Debug, Clone)]
+pub struct SelfInfo {
+    pub id: i64,
+    pub name: String,
+    pub username: Option<String>,
+    pub phone: Option<String>,
 }
 
 /// Main application state
 pub struct App {
+    pub config: Config,
     pub mode: Mode,
     pub panel: Panel,
     pub chats: Vec<Chat>,
     pub messages: HashMap<i64, Vec<Message>>,
+    pub connection: ConnectionState,
+    pub messages_revision: u64, // Bumped on every message change, invalidates layout_cache
+    pub layout_cache: RefCell<LayoutCache>,
     pub selected_chat: usize,
-    pub selected_message: usize,
-    pub scroll_offset: usize,
+    pub selected_message: usize, // Message cursor in the chat panel, 0 = newest
+    pub scroll_offset: Cell<usize>, // Lines scrolled up from the bottom, kept in sync by draw
+    pub new_below: usize,        // Messages that arrived below the view while scrolled up
     pub input: String,
+    pub sent_history: Vec<String>, // Sent messages, oldest first, for Up/Down recall
+    history_cursor: Option<usize>, // Index of the recalled entry, None while editing
+    history_prefix: String,        // What was typed before recalling, entries must start with it
+    pub last_failed_send: Option<OutgoingMessage>, // The last send that failed
+    pub retry_send_requested: bool, // . in normal mode: send last_failed_send again
     pub should_quit: bool,
     pub reload_requested: bool,
+    pub bell_requested: bool,  // Ring the terminal bell before the next draw
+    pub sound_requested: bool, // Play the configured sound cue before the next draw
+    pub reconnect_requested: bool, // r after the connection was lost: try again
+    pub manual_reconnect_requested: bool, // :reconnect, a fresh connection even if this one looks fine
     pub loading_status: Option<String>,
+    pub status_message: Option<String>, // One-shot feedback shown in the input box
+    status_seen: Option<(String, Instant)>, // Status message as of the last tick, and since when
+    last_input: Instant,                // Last key or paste, for lock_after_mins
+    pub locked: bool,                   // Idle lock screen is up, chats are hidden
+    pub lock_input: String,             // Passphrase typed on the lock screen
     pub needs_message_load: bool,
+    pub refresh_requested: bool, // Re-read the dialog list for :refresh
+    pub stats: SessionStats,
+    // Link opening state
+    pub link_choices: Vec<String>,
+    pub link_picker_selected: usize,
+    // Visual mode state
+    pub visual_anchor: Option<i32>, // Message id where v started, the other end is the cursor
+    pub quote_pick: Option<(i32, usize, usize)>, // (message id, first word, last word) being quoted
+    // Pinned message list state
+    pub pins_requested: Option<i64>, // Fetch this chat's pinned messages, then list them
+    pub pins_selected: usize,
+    // Forum topic state
+    pub topics_requested: Option<i64>, // Fetch this forum's topics, then pick one
+    pub topics_selected: usize,        // 0 is "All topics"
+    pub active_topic: HashMap<i64, (i32, String)>, // Topic shown and sent to, by forum chat
+    // Sticker picker state
+    pub sticker_requested: Option<String>, // :sticker query; fetch saved stickers, then pick one
+    pub sticker_choices: Vec<(i64, String)>, // Matching stickers/GIFs as (document id, label)
+    pub sticker_selected: usize,
+    pub sticker_send_requested: Option<(i64, i64)>, // Send (chat id, document id)
+    // Message view state
+    pub message_view: Option<(String, String)>, // (sender, full text) of the message shown with K
+    pub message_view_scroll: u16,
+    pub message_view_max_scroll: Cell<u16>, // Lines the text overflows the view by, kept in sync by draw
+    // Command palette state
+    pub palette_input: String,
+    pub palette_selected: usize,
+    pub open_url_requested: Option<String>,
+    pub letter_jump_pending: bool, // ' pressed, waiting for the letter to jump to
+    pub number_jump_input: String, // Digits typed so far in NumberJump mode
+    pub reply_to: Option<i32>,     // Message id the next sent message replies to
+    pub reply_quote: Option<Range<usize>>, // Byte range of reply_to's text to quote in the reply
     // Search mode state
     pub search_input: String,
     pub filtered_chat_indices: Vec<usize>,
     pub search_selected: usize,
     // Disconnect request
     pub disconnect_requested: bool,
+    pub session_expired: bool, // Server rejected our auth key, re-login on exit
+    pub forget_credentials_requested: bool,
+    // Confirmation state
+    pub pending_confirm: Option<ConfirmAction>,
     // Multi-account state
     pub current_account_id: String,
     pub account_names: Vec<(String, String)>, // (id, display_name)
+    pub account_accents: HashMap<String, (u8, u8, u8)>, // Accent color by account id
     pub account_picker_selected: usize,
     pub switch_account_requested: Option<String>,
     pub add_account_requested: bool,
+    pub log_out_account_requested: Option<String>, // Drop this account's session only
+    pub mute_requested: Option<(i64, i64)>,        // (chat_id, mute_until), 0 unmutes
+    pub block_requested: Option<(i64, bool)>,      // (chat_id, block), false unblocks
+    pub slow_mode_until: HashMap<i64, Instant>,    // When slow mode groups accept a message again
+    pub pin_requested: Option<(i64, i32, bool)>,   // (chat_id, message_id, pin)
+    pub download_requested: Option<(i64, i32, Option<PathBuf>)>, // (chat_id, message_id, save path)
+    pub copy_requested: Option<String>,            // Text to put on the clipboard
+    pub paste_requested: bool,                     // Read the clipboard into the input
+    pub invite_link_requested: Option<i64>,        // Chat to export an invite link for
+    pub send_to_id_requested: Option<(i64, String)>, // (chat_id as typed, text) from :msg
+    pub jump_requested: Option<(i64, i32, i32)>, // (chat_id, message_id, oldest loaded id) to fetch back to
+    pub load_more_requested: Option<(i64, i32, usize)>, // (chat_id, oldest loaded id, count) for L
+    history_start: HashSet<i64>, // Chats loaded back to their first message, nothing more for L
+    loading_more: HashSet<i64>,  // Chats with an L batch on its way, so L doesn't ask twice
+    newest_trimmed: HashSet<i64>, // Chats whose newest messages the cap dropped, reloaded back at the bottom
+    pub read_queue: Vec<i64>,     // Chats read locally, marked read on the server in batches
+    pub message_search_requested: Option<String>, // Query of a :search across all chats
+    pub message_search_results: Vec<(i64, i32)>, // (chat_id, message_id) of the last :search
+    pub message_search_query: Option<String>, // Last :search with results, highlighted in chats
+    message_search_index: usize,  // Result :next/:prev step from
+    pending_jump: Option<(i64, i32)>, // Message to select once its chat has loaded
+    pub pinned_index: usize,      // Which pinned message the banner shows
+    pub recent_chats: Vec<i64>,   // Recently opened chat ids, most recent first
+    pub folders: Vec<ChatFolder>,
+    pub active_folder: usize, // 0 is "All chats", n is folders[n - 1]
+    pub chat_filter: Option<ChatFilter>, // Only chats of this kind are listed
+    pub me: Option<SelfInfo>,
     // Async loading state
     pub pending_load: Option<i64>,
     // Command mode state
@@ -79,14 +535,16 @@ pub struct App {
     pub code_input: String,
     pub code_output: String,
     pub code_scroll: usize,
+    pub scratchpad: String, // Local notes, saved to scratchpad.txt when closed
 }
 
 /// AI request types
 #[derive(Debug, Clone)]
 pub enum AIRequest {
-    Command(String),       // Natural language command to parse
-    Reply(Option<String>), // Generate reply with optional tone
-    Code(String),          // Code assistance query
+    Command(String), // Natural language command to parse
+    #[allow(dead_code)]
+    Reply(Option<String>), // Generate reply with optional tone, not sent by the UI yet
+    Code(String),    // Code assistance query
 }
 
 /// Result of a global user search
@@ -101,30 +559,106 @@ pub enum FindResult {
 impl App {
     pub fn new() -> Self {
         Self {
+            config: Config::default(),
             mode: Mode::Normal,
             panel: Panel::Friends,
             chats: Vec::new(),
             messages: HashMap::new(),
+            connection: ConnectionState::Online,
+            messages_revision: 0,
+            layout_cache: RefCell::new(LayoutCache::default()),
             selected_chat: 0,
             selected_message: 0,
-            scroll_offset: 0,
+            scroll_offset: Cell::new(0),
+            new_below: 0,
             input: String::new(),
+            sent_history: Vec::new(),
+            history_cursor: None,
+            history_prefix: String::new(),
+            last_failed_send: None,
+            retry_send_requested: false,
             should_quit: false,
             reload_requested: false,
+            bell_requested: false,
+            sound_requested: false,
+            reconnect_requested: false,
+            manual_reconnect_requested: false,
             loading_status: None,
+            status_message: None,
+            status_seen: None,
+            last_input: Instant::now(),
+            locked: false,
+            stats: SessionStats::new(),
+            lock_input: String::new(),
             needs_message_load: true,
+            refresh_requested: false,
+            link_choices: Vec::new(),
+            link_picker_selected: 0,
+            visual_anchor: None,
+            quote_pick: None,
+            pins_requested: None,
+            pins_selected: 0,
+            topics_requested: None,
+            topics_selected: 0,
+            active_topic: HashMap::new(),
+            sticker_requested: None,
+            sticker_choices: Vec::new(),
+            sticker_selected: 0,
+            sticker_send_requested: None,
+            message_view: None,
+            message_view_scroll: 0,
+            message_view_max_scroll: Cell::new(0),
+            palette_input: String::new(),
+            palette_selected: 0,
+            open_url_requested: None,
+            letter_jump_pending: false,
+            number_jump_input: String::new(),
+            reply_to: None,
+            reply_quote: None,
             // Search mode state
             search_input: String::new(),
             filtered_chat_indices: Vec::new(),
             search_selected: 0,
             // Disconnect
             disconnect_requested: false,
+            session_expired: false,
+            forget_credentials_requested: false,
+            // Confirmation
+            pending_confirm: None,
             // Multi-account
             current_account_id: String::new(),
             account_names: Vec::new(),
+            account_accents: HashMap::new(),
             account_picker_selected: 0,
             switch_account_requested: None,
             add_account_requested: false,
+            log_out_account_requested: None,
+            mute_requested: None,
+            block_requested: None,
+            slow_mode_until: HashMap::new(),
+            pin_requested: None,
+            download_requested: None,
+            copy_requested: None,
+            paste_requested: false,
+            invite_link_requested: None,
+            send_to_id_requested: None,
+            jump_requested: None,
+            load_more_requested: None,
+            history_start: HashSet::new(),
+            loading_more: HashSet::new(),
+            newest_trimmed: HashSet::new(),
+            read_queue: Vec::new(),
+            message_search_requested: None,
+            message_search_results: Vec::new(),
+            message_search_query: None,
+            message_search_index: 0,
+            pending_jump: None,
+            pinned_index: 0,
+            recent_chats: Vec::new(),
+            folders: Vec::new(),
+            active_folder: 0,
+            chat_filter: None,
+            me: None,
             // Async loading
             pending_load: None,
             // Command mode
@@ -141,6 +675,7 @@ impl App {
             code_input: String::new(),
             code_output: String::new(),
             code_scroll: 0,
+            scratchpad: String::new(),
         }
     }
 
@@ -161,10 +696,39 @@ impl App {
         }
     }
 
-    /// Clear unread count for currently selected chat
+    /// Messages of the current chat that get a bubble (empty media-only ones are skipped)
+    pub fn shown_messages(&self) -> Vec<&Message> {
+        let topic = self.current_topic().map(|(id, _)| *id);
+        self.current_messages()
+            .into_iter()
+            .filter(|m| m.deleted || m.media.is_some() || !m.text.trim().is_empty())
+            .filter(|m| topic.is_none_or(|topic| m.in_topic(topic)))
+            .collect()
+    }
+
+    /// The message under the cursor, if any
+    pub fn selected_message(&self) -> Option<&Message> {
+        let shown = self.shown_messages();
+        let index = shown.len().checked_sub(self.selected_message + 1)?;
+        Some(shown[index])
+    }
+
+    /// Clear unread count for currently selected chat, queueing the server read mark
     pub fn clear_current_unread(&mut self) {
+        let newest = self
+            .current_messages()
+            .iter()
+            .filter(|m| !m.outgoing)
+            .map(|m| m.id)
+            .max();
         if let Some(chat) = self.chats.get_mut(self.selected_chat) {
+            chat.read_inbox_max_id = chat.read_inbox_max_id.max(newest.unwrap_or(0));
+            if chat.unread > 0 && chat.id != WELCOME_CHAT_ID && !self.read_queue.contains(&chat.id)
+            {
+                self.read_queue.push(chat.id);
+            }
             chat.unread = 0;
+            chat.mentions = 0;
         }
     }
 
@@ -172,17 +736,19 @@ impl App {
     pub fn move_up(&mut self) {
         match self.panel {
             Panel::Friends => {
-                if self.selected_chat > 0 {
-                    self.selected_chat -= 1;
-                    self.clear_current_unread();
-                    self.scroll_offset = 0; // Reset scroll when switching chats
-                    self.needs_message_load = true; // Trigger lazy loading
+                let visible = self.folder_chat_indices();
+                match visible.iter().position(|&i| i == self.selected_chat) {
+                    Some(pos) if pos > 0 => self.show_chat(visible[pos - 1]),
+                    Some(_) if self.config.wrap_navigation && visible.len() > 1 => {
+                        // Wrap from the top to the bottom
+                        self.show_chat(visible[visible.len() - 1]);
+                    }
+                    Some(_) => {}
+                    None => self.jump_to_edge(false),
                 }
             }
-            Panel::Chats => {
-                // Scroll up (back in history)
-                self.scroll_offset = self.scroll_offset.saturating_add(1);
-            }
+            Panel::Chats if self.config.natural_scroll => self.select_newer_message(),
+            Panel::Chats => self.select_older_message(),
         }
     }
 
@@ -190,207 +756,1711 @@ impl App {
     pub fn move_down(&mut self) {
         match self.panel {
             Panel::Friends => {
-                if self.selected_chat < self.chats.len().saturating_sub(1) {
-                    self.selected_chat += 1;
-                    self.clear_current_unread();
-                    self.scroll_offset = 0; // Reset scroll when switching chats
-                    self.needs_message_load = true; // Trigger lazy loading
+                let visible = self.folder_chat_indices();
+                match visible.iter().position(|&i| i == self.selected_chat) {
+                    Some(pos) if pos + 1 < visible.len() => self.show_chat(visible[pos + 1]),
+                    Some(_) if self.config.wrap_navigation && visible.len() > 1 => {
+                        // Wrap from the bottom to the top
+                        self.show_chat(visible[0]);
+                    }
+                    Some(_) => {}
+                    None => self.jump_to_edge(false),
                 }
             }
-            Panel::Chats => {
-                // Scroll down (forward in history)
-                self.scroll_offset = self.scroll_offset.saturating_sub(1);
-            }
+            Panel::Chats if self.config.natural_scroll => self.select_older_message(),
+            Panel::Chats => self.select_newer_message(),
         }
     }
 
-    /// Switch between panels
-    pub fn switch_panel(&mut self) {
-        self.panel = match self.panel {
-            Panel::Friends => Panel::Chats,
-            Panel::Chats => Panel::Friends,
-        };
+    /// Select the message above the current one
+    fn select_older_message(&mut self) {
+        let count = self.shown_messages().len();
+        if self.selected_message + 1 < count {
+            self.selected_message += 1;
+        }
     }
 
-    /// Enter insert mode
-    pub fn enter_insert(&mut self) {
-        self.mode = Mode::Insert;
+    /// Select the message below the current one
+    fn select_newer_message(&mut self) {
+        self.selected_message = self.selected_message.saturating_sub(1);
+        if self.selected_message == 0 {
+            self.new_below = 0;
+            self.reload_if_trimmed();
+        }
     }
 
-    /// Exit insert mode
-    pub fn exit_insert(&mut self) {
-        self.mode = Mode::Normal;
+    /// Jump back to the newest message and follow new ones again
+    pub fn jump_to_latest(&mut self) {
+        self.selected_message = 0;
+        self.scroll_offset.set(0);
+        self.new_below = 0;
+        self.reload_if_trimmed();
     }
 
-    /// Add a chat to the list
-    pub fn add_chat(&mut self, id: i64, name: String) {
-        if !self.chats.iter().any(|c| c.id == id) {
-            self.chats.push(Chat {
-                id,
-                name,
-                last_message: None,
-                unread: 0,
-            });
+    /// Back at the bottom of a chat whose newest messages the cap dropped:
+    /// reload them, so what's shown isn't missing everything after the gap
+    fn reload_if_trimmed(&mut self) {
+        if let Some(chat_id) = self.current_chat_id() {
+            if self.newest_trimmed.remove(&chat_id) {
+                self.reload_requested = true;
+            }
         }
     }
 
-    /// Add a message to a chat
-    pub fn add_message(&mut self, chat_id: i64, sender: String, text: String, outgoing: bool) {
-        let messages = self.messages.entry(chat_id).or_insert_with(Vec::new);
-        messages.push(Message {
-            sender,
-            text: text.clone(),
-            outgoing,
-        });
+    /// Select the oldest loaded message of the open chat
+    pub fn jump_to_oldest(&mut self) {
+        self.selected_message = self.shown_messages().len().saturating_sub(1);
+    }
 
-        // Update last message preview
-        if let Some(chat) = self.chats.iter_mut().find(|c| c.id == chat_id) {
-            chat.last_message = Some(text);
-            if !outgoing {
-                chat.unread += 1;
-            }
+    /// Fetch the load_more_batch messages before the oldest loaded one (L)
+    pub fn request_load_more(&mut self) {
+        let Some(chat_id) = self.current_real_chat_id() else {
+            return;
+        };
+        if self.history_start.contains(&chat_id) {
+            self.status_message = Some("Already at the start of the chat".to_string());
+            return;
+        }
+        // Not loaded yet: the first page is still on its way
+        let Some(oldest) = self
+            .messages
+            .get(&chat_id)
+            .and_then(|m| m.first())
+            .map(|m| m.id)
+        else {
+            return;
+        };
+        if !self.loading_more.insert(chat_id) {
+            return;
         }
+        let count = self.config.load_more_batch;
+        self.status_message = Some(format!("Loading {} older messages…", count));
+        self.load_more_requested = Some((chat_id, oldest, count));
     }
 
-    /// Enter search mode
-    pub fn enter_search(&mut self) {
-        self.mode = Mode::Search;
-        self.search_input.clear();
-        self.search_selected = 0;
-        self.update_search_filter();
+    /// An L batch that failed, or came back for history that was reloaded
+    /// meanwhile (`error` is None) and so was dropped
+    pub fn note_load_more_failed(&mut self, chat_id: i64, error: Option<&str>) {
+        self.loading_more.remove(&chat_id);
+        self.status_message = error.map(|e| format!("Loading older messages failed: {}", e));
     }
 
-    /// Exit search mode without jumping
-    pub fn exit_search(&mut self) {
-        self.mode = Mode::Normal;
-        self.search_input.clear();
-        self.filtered_chat_indices.clear();
+    /// An L batch came back with `loaded` of the `requested` messages; fewer
+    /// means it reached the start of the chat, and L stops asking
+    pub fn note_loaded_more(&mut self, chat_id: i64, requested: usize, loaded: usize) {
+        self.loading_more.remove(&chat_id);
+        let at_start = loaded < requested;
+        if at_start {
+            self.history_start.insert(chat_id);
+        }
+        self.status_message = Some(match loaded {
+            0 => "Already at the start of the chat".to_string(),
+            n if at_start => format!("Loaded {} older messages, back to the start of the chat", n),
+            n => format!("Loaded {} older messages", n),
+        });
     }
 
-    /// Update filtered chat indices based on search input
-    pub fn update_search_filter(&mut self) {
-        let query = self.search_input.to_lowercase();
-        self.filtered_chat_indices = self
-            .chats
-            .iter()
-            .enumerate()
-            .filter(|(_, chat)| {
-                if query.is_empty() {
-                    true // Show all when empty
-                } else {
-                    chat.name.to_lowercase().contains(&query)
-                }
-            })
-            .map(|(i, _)| i)
-            .collect();
-
-        // Reset selection if it's out of bounds
-        if self.search_selected >= self.filtered_chat_indices.len() {
-            self.search_selected = 0;
+    /// After a live message arrived: stay pinned to the bottom if we're there
+    /// (and auto_scroll is on), otherwise keep the view and count it as new below
+    pub fn note_new_message(&mut self, chat_id: i64) {
+        if self.current_chat_id() != Some(chat_id) {
+            return;
+        }
+        let at_bottom = self.selected_message == 0 && self.scroll_offset.get() == 0;
+        if at_bottom && self.config.auto_scroll {
+            return;
         }
+        if self.selected_message == 0 {
+            // Keep the cursor on what was the newest message
+            self.selected_message = 1;
+        }
+        self.new_below += 1;
     }
 
-    /// Jump to the selected search result
-    pub fn jump_to_selected_search_result(&mut self) {
-        if let Some(&chat_index) = self.filtered_chat_indices.get(self.search_selected) {
-            self.selected_chat = chat_index;
-            self.scroll_offset = 0;
-            self.needs_message_load = true;
-            self.clear_current_unread();
+    /// Take a chat's unread state from the server (when dialogs load), so counts
+    /// match other devices instead of starting from zero every session
+    pub fn set_read_state(&mut self, chat_id: i64, read_max_id: i32, unread: u32, mentions: u32) {
+        if let Some(chat) = self.chats.iter_mut().find(|c| c.id == chat_id) {
+            chat.read_inbox_max_id = read_max_id;
+            chat.unread = unread;
+            chat.mentions = mentions;
         }
-        self.exit_search();
     }
 
-    /// Move selection up in search results
-    pub fn search_move_up(&mut self) {
-        if self.search_selected > 0 {
-            self.search_selected -= 1;
+    /// Count a new message that @-mentions the user, ringing the bell if enabled
+    pub fn note_mention(&mut self, chat_id: i64) {
+        if let Some(chat) = self.chats.iter_mut().find(|c| c.id == chat_id) {
+            chat.mentions += 1;
         }
+        self.bell_requested |=
+            self.config.mention_bell && self.notify_level(chat_id) != NotifyLevel::None;
     }
 
-    /// Move selection down in search results
-    pub fn search_move_down(&mut self) {
-        if self.search_selected < self.filtered_chat_indices.len().saturating_sub(1) {
-            self.search_selected += 1;
+    /// Select the first (or last) chat of the active folder
+    pub fn jump_to_edge(&mut self, last: bool) {
+        let visible = self.folder_chat_indices();
+        let edge = if last {
+            visible.last()
+        } else {
+            visible.first()
+        };
+        if let Some(&index) = edge {
+            self.show_chat(index);
         }
     }
 
-    // ==================== Account Picker Methods ====================
+    // ==================== Folder Methods ====================
 
-    /// Enter account picker mode
-    pub fn enter_account_picker(&mut self) {
-        self.mode = Mode::AccountPicker;
-        self.account_picker_selected = 0;
-        // Find current account index
-        for (i, (id, _)) in self.account_names.iter().enumerate() {
-            if *id == self.current_account_id {
-                self.account_picker_selected = i;
-                break;
-            }
+    /// Whether a chat belongs in the active folder (and the chat filter)
+    fn in_active_folder(&self, chat: &Chat) -> bool {
+        if self.chat_filter.is_some_and(|filter| !filter.matches(chat)) {
+            return false;
         }
+        let Some(folder) = self
+            .active_folder
+            .checked_sub(1)
+            .and_then(|i| self.folders.get(i))
+        else {
+            return true;
+        };
+        folder.chat_ids.contains(&chat.id)
+            && !(folder.exclude_muted && chat.is_muted(unix_now()))
+            && !(folder.exclude_read && chat.unread == 0)
     }
 
-    /// Exit account picker mode
-    pub fn exit_account_picker(&mut self) {
-        self.mode = Mode::Normal;
+    /// Indices into `chats` of the chats in the active folder, in list order
+    pub fn folder_chat_indices(&self) -> Vec<usize> {
+        (0..self.chats.len())
+            .filter(|&i| self.in_active_folder(&self.chats[i]))
+            .collect()
     }
 
-    /// Move up in account picker
-    pub fn account_picker_move_up(&mut self) {
-        if self.account_picker_selected > 0 {
-            self.account_picker_selected -= 1;
-        }
+    /// Replace the folder list (keeps "All chats" active)
+    pub fn set_folders(&mut self, folders: Vec<ChatFolder>) {
+        self.folders = folders;
+        self.active_folder = 0;
     }
 
-    /// Move down in account picker (includes "+ Add Account" option)
-    pub fn account_picker_move_down(&mut self) {
-        // +1 for the "Add Account" option
-        let max_index = self.account_names.len();
-        if self.account_picker_selected < max_index {
-            self.account_picker_selected += 1;
+    /// Switch to the next (or previous) folder tab, opening its first chat
+    /// if the current one isn't in it
+    pub fn cycle_folder(&mut self, forward: bool) {
+        if self.folders.is_empty() {
+            self.status_message = Some("No chat folders".to_string());
+            return;
+        }
+        let count = self.folders.len() + 1;
+        self.active_folder = if forward {
+            (self.active_folder + 1) % count
+        } else {
+            (self.active_folder + count - 1) % count
+        };
+        let visible = self.folder_chat_indices();
+        if !visible.contains(&self.selected_chat) {
+            match visible.first() {
+                Some(&index) => self.show_chat(index),
+                None => self.status_message = Some("This folder is empty".to_string()),
+            }
         }
     }
 
-    /// Select the current account in picker
-    pub fn select_account(&mut self) {
-        if self.account_picker_selected < self.account_names.len() {
-            // Switch to selected account
-            let (account_id, _) = &self.account_names[self.account_picker_selected];
-            if *account_id != self.current_account_id {
-                self.switch_account_requested = Some(account_id.clone());
+    /// List only chats of one kind (None lists them all), opening the first
+    /// one if the current chat is filtered out
+    pub fn set_chat_filter(&mut self, filter: Option<ChatFilter>) {
+        self.chat_filter = filter;
+        if self.mode == Mode::Search {
+            self.update_search_filter();
+        }
+        let visible = self.folder_chat_indices();
+        let shown = match filter {
+            Some(filter) => format!("Showing {}", filter.label()),
+            None => "Showing all chats".to_string(),
+        };
+        self.status_message = Some(shown);
+        if !visible.contains(&self.selected_chat) {
+            match visible.first() {
+                Some(&index) => self.show_chat(index),
+                None => {
+                    self.status_message =
+                        Some(format!("No {}", filter.map_or("chats", ChatFilter::label)))
+                }
             }
-            self.exit_account_picker();
-        } else {
-            // "Add Account" selected
-            self.add_account_requested = true;
-            self.exit_account_picker();
         }
     }
 
-    /// Set the current account info
-    pub fn set_account_info(&mut self, account_id: String, accounts: Vec<(String, String)>) {
-        self.current_account_id = account_id;
-        self.account_names = accounts;
+    /// Step to the next chat filter (F)
+    pub fn cycle_chat_filter(&mut self) {
+        self.set_chat_filter(ChatFilter::next(self.chat_filter));
     }
 
-    // ==================== Command Mode Methods ====================
-
-    /// Enter command mode (after pressing :)
-    pub fn enter_command(&mut self) {
-        self.mode = Mode::Command;
-        self.command_input.clear();
+    /// Select a chat by index, resetting scroll and triggering a lazy load
+    pub fn select_chat(&mut self, index: usize) {
+        self.show_chat(index);
+        self.remember_chat();
     }
 
-    /// Exit command mode
-    pub fn exit_command(&mut self) {
-        self.mode = Mode::Normal;
-        self.command_input.clear();
+    /// Switch to a chat without touching the recent-chats order
+    fn show_chat(&mut self, index: usize) {
+        self.selected_chat = index;
+        self.clear_current_unread();
+        self.selected_message = 0;
+        self.scroll_offset.set(0);
+        self.new_below = 0;
+        self.needs_message_load = true;
     }
 
-    /// Execute the current command
-    pub fn execute_command(&mut self) {
-        let cmd = self.command_input.trim();
-        let cmd_lower = cmd.to_lowercase();
+    /// Move the current chat to the front of the recent-chats list
+    pub fn remember_chat(&mut self) {
+        let Some(chat_id) = self.current_chat_id().filter(|&id| id != WELCOME_CHAT_ID) else {
+            return;
+        };
+        self.recent_chats.retain(|&id| id != chat_id);
+        self.recent_chats.insert(0, chat_id);
+        self.recent_chats.truncate(MAX_RECENT_CHATS);
+    }
+
+    /// Open the chat in a recent-chats slot (0 = most recent)
+    pub fn jump_to_recent(&mut self, slot: usize) {
+        let index = self
+            .recent_chats
+            .get(slot)
+            .and_then(|id| self.chats.iter().position(|c| c.id == *id));
+        match index {
+            Some(index) => self.show_chat(index),
+            None => self.status_message = Some(format!("No recent chat {}", slot + 1)),
+        }
+    }
+
+    /// Cycle through recent chats like tabs (forward = towards older ones)
+    pub fn cycle_recent(&mut self, forward: bool) {
+        let len = self.recent_chats.len();
+        if len < 2 {
+            self.status_message = Some("No other recent chats".to_string());
+            return;
+        }
+        let current = self
+            .current_chat_id()
+            .and_then(|id| self.recent_chats.iter().position(|&r| r == id));
+        let slot = match (current, forward) {
+            (Some(pos), true) => (pos + 1) % len,
+            (Some(pos), false) => (pos + len - 1) % len,
+            (None, _) => 0,
+        };
+        self.jump_to_recent(slot);
+    }
+
+    /// Jump to the next chat (after the current one, wrapping) whose name starts with `letter`
+    pub fn jump_to_letter(&mut self, letter: char) {
+        let letter: String = letter.to_lowercase().collect();
+        let visible = self.folder_chat_indices();
+        let len = visible.len();
+        let start = visible
+            .iter()
+            .position(|&i| i == self.selected_chat)
+            .unwrap_or(len.saturating_sub(1));
+        let found = (1..=len)
+            .map(|offset| visible[(start + offset) % len])
+            .find(|&i| {
+                let chat = &self.chats[i];
+                chat.id != WELCOME_CHAT_ID && chat.name.to_lowercase().starts_with(&letter)
+            });
+
+        match found {
+            Some(index) => self.select_chat(index),
+            None => self.status_message = Some(format!("No chat starting with '{}'", letter)),
+        }
+    }
+
+    /// Number the chats of the friends list and wait for one to be typed (f)
+    pub fn enter_number_jump(&mut self) {
+        if self.folder_chat_indices().is_empty() {
+            return;
+        }
+        self.number_jump_input.clear();
+        self.mode = Mode::NumberJump;
+    }
+
+    /// Add a typed digit. Opens the chat right away once no more digits
+    /// could make a number on screen (e.g. 4 with 30 chats, 31 or 5 with 40)
+    pub fn number_jump_push(&mut self, digit: char) {
+        let count = self.folder_chat_indices().len();
+        let typed = format!("{}{}", self.number_jump_input, digit);
+        match typed.parse::<usize>() {
+            Ok(number) if number >= 1 && number <= count => {
+                self.number_jump_input = typed;
+                if number * 10 > count {
+                    self.confirm_number_jump();
+                }
+            }
+            _ => {} // Leading zero or past the last chat
+        }
+    }
+
+    /// Remove the last typed digit
+    pub fn number_jump_backspace(&mut self) {
+        self.number_jump_input.pop();
+    }
+
+    /// Open the chat with the typed number (Enter)
+    pub fn confirm_number_jump(&mut self) {
+        let visible = self.folder_chat_indices();
+        let chosen = self
+            .number_jump_input
+            .parse::<usize>()
+            .ok()
+            .and_then(|number| visible.get(number.checked_sub(1)?).copied());
+        self.exit_number_jump();
+        if let Some(index) = chosen {
+            self.select_chat(index);
+        }
+    }
+
+    /// Leave NumberJump mode without opening anything
+    pub fn exit_number_jump(&mut self) {
+        self.number_jump_input.clear();
+        self.mode = Mode::Normal;
+    }
+
+    /// Switch between panels
+    pub fn switch_panel(&mut self) {
+        self.panel = match self.panel {
+            Panel::Friends => {
+                self.remember_chat();
+                Panel::Chats
+            }
+            Panel::Chats => Panel::Friends,
+        };
+    }
+
+    /// Enter insert mode
+    pub fn enter_insert(&mut self) {
+        if self.current_real_chat_id().is_none() {
+            return;
+        }
+        if self.chats[self.selected_chat].is_blocked {
+            self.status_message = Some("You blocked this contact (:unblock)".to_string());
+            return;
+        }
+        self.mode = Mode::Insert;
+    }
+
+    /// Exit insert mode
+    pub fn exit_insert(&mut self) {
+        self.mode = Mode::Normal;
+        self.history_cursor = None;
+        self.reply_to = None;
+        self.reply_quote = None;
+    }
+
+    /// After a message went out: show it at the bottom and/or stop typing, as configured
+    pub fn note_sent(&mut self) {
+        if self.config.scroll_on_send {
+            self.jump_to_latest();
+        }
+        if self.config.exit_insert_on_send {
+            self.exit_insert();
+        }
+        self.sound_requested |= self.config.sound_on_send;
+        self.stats.sent += 1;
+    }
+
+    /// A send failed: keep the message so `.` can send it again without retyping
+    pub fn note_send_failed(&mut self, outgoing: OutgoingMessage, error: &str) {
+        self.last_failed_send = Some(outgoing);
+        self.status_message = Some(format!("Failed to send: {} (. retries)", error));
+    }
+
+    /// A message went out; if it was the failed one, there's nothing left to retry
+    pub fn note_send_succeeded(&mut self, outgoing: &OutgoingMessage) {
+        if self.last_failed_send.as_ref() == Some(outgoing) {
+            self.last_failed_send = None;
+        }
+    }
+
+    /// Send the last failed message again, to the chat it was meant for (.)
+    pub fn request_retry_send(&mut self) {
+        if self.last_failed_send.is_some() {
+            self.retry_send_requested = true;
+        } else {
+            self.status_message = Some("No failed message to retry".to_string());
+        }
+    }
+
+    /// An incoming message arrived: play the receive sound unless the chat is muted
+    /// or its notification level leaves this message out
+    pub fn note_received(&mut self, chat_id: i64, mentioned: bool) {
+        let now = unix_now();
+        let muted = self
+            .chats
+            .iter()
+            .any(|c| c.id == chat_id && c.is_muted(now));
+        let notify = match self.notify_level(chat_id) {
+            NotifyLevel::All => true,
+            NotifyLevel::Mentions => mentioned,
+            NotifyLevel::None => false,
+        };
+        self.sound_requested |= self.config.sound_on_receive && !muted && notify;
+        self.stats.received += 1;
+    }
+
+    /// Reply to the latest incoming message of the open chat: select it and start typing
+    pub fn reply_to_latest(&mut self) {
+        let Some(chat_id) = self.current_real_chat_id() else {
+            return;
+        };
+        let latest = self.messages.get(&chat_id).and_then(|messages| {
+            messages
+                .iter()
+                .rev()
+                .find(|m| !m.outgoing && !m.deleted && m.kind == MessageKind::Text)
+                .map(|m| m.id)
+        });
+        let Some(message_id) = latest else {
+            self.status_message = Some("No incoming messages to reply to".to_string());
+            return;
+        };
+        self.jump_to_message(message_id);
+        self.reply_to = Some(message_id);
+        self.reply_quote = None;
+        self.enter_insert();
+    }
+
+    /// The message being replied to, if it's still loaded
+    pub fn reply_target(&self) -> Option<&Message> {
+        let id = self.reply_to?;
+        self.current_messages().into_iter().find(|m| m.id == id)
+    }
+
+    /// Text quoted from a message and where it starts in UTF-16 units, as
+    /// Telegram counts offsets. None if the message or range is gone
+    pub fn quote_for(
+        &self,
+        chat_id: i64,
+        message_id: i32,
+        range: &Range<usize>,
+    ) -> Option<(String, i32)> {
+        let message = self
+            .messages
+            .get(&chat_id)?
+            .iter()
+            .find(|m| m.id == message_id)?;
+        let quote = message.text.get(range.clone())?;
+        let offset = message.text.get(..range.start)?.encode_utf16().count();
+        Some((quote.to_string(), offset as i32))
+    }
+
+    /// Address a message to the open chat: it replies (and quotes) as set up,
+    /// in the replied-to message's topic, or the active one if it's no reply
+    pub fn prepare_send(&mut self, chat_id: i64, text: String) -> OutgoingMessage {
+        let topic = match self.reply_target() {
+            Some(target) => target.topic,
+            None => self.send_topic(),
+        };
+        OutgoingMessage {
+            chat_id,
+            text,
+            reply_to: self.reply_to.take(),
+            quote: self.reply_quote.take(),
+            topic,
+        }
+    }
+
+    /// Take the typed message for sending, remembering it for Up/Down recall
+    pub fn take_outgoing(&mut self) -> String {
+        let message = std::mem::take(&mut self.input);
+        self.record_sent(&message);
+        message
+    }
+
+    /// Ask before sending to a chat with more members than `confirm_send_members`
+    /// (None when it can go straight out)
+    pub fn send_confirmation(&self) -> Option<ConfirmAction> {
+        let threshold = self.config.confirm_send_members;
+        let chat = self.chats.get(self.selected_chat)?;
+        let members = chat.members.filter(|&n| threshold > 0 && n > threshold)?;
+        Some(ConfirmAction::Send {
+            chat_id: chat.id,
+            name: chat.name.clone(),
+            members,
+        })
+    }
+
+    /// Remember a sent message for Up/Down recall (consecutive repeats count once)
+    pub fn record_sent(&mut self, message: &str) {
+        self.history_cursor = None;
+        if self.sent_history.last().map(String::as_str) != Some(message) {
+            self.sent_history.push(message.to_string());
+            if self.sent_history.len() > MAX_SENT_HISTORY {
+                self.sent_history.remove(0);
+            }
+        }
+    }
+
+    /// Recall the previous sent message that starts with what was typed, like a shell
+    pub fn history_up(&mut self) {
+        let cursor = match self.history_cursor {
+            Some(cursor) => cursor,
+            None => {
+                self.history_prefix = self.input.clone();
+                self.sent_history.len()
+            }
+        };
+        let prefix = &self.history_prefix;
+        if let Some(index) = self.sent_history[..cursor]
+            .iter()
+            .rposition(|m| m.starts_with(prefix.as_str()))
+        {
+            self.history_cursor = Some(index);
+            self.input = self.sent_history[index].clone();
+        }
+    }
+
+    /// Step back towards newer messages, ending on what was typed before recalling
+    pub fn history_down(&mut self) {
+        let Some(cursor) = self.history_cursor else {
+            return;
+        };
+        let prefix = &self.history_prefix;
+        match self.sent_history[cursor + 1..]
+            .iter()
+            .position(|m| m.starts_with(prefix.as_str()))
+        {
+            Some(offset) => {
+                self.history_cursor = Some(cursor + 1 + offset);
+                self.input = self.sent_history[cursor + 1 + offset].clone();
+            }
+            None => {
+                self.history_cursor = None;
+                self.input = std::mem::take(&mut self.history_prefix);
+            }
+        }
+    }
+
+    /// Editing a recalled message makes it the new draft
+    pub fn stop_history_recall(&mut self) {
+        self.history_cursor = None;
+    }
+
+    /// Ask for the clipboard to be pasted into the input, starting to type if needed
+    pub fn request_paste(&mut self) {
+        self.enter_insert();
+        self.paste_requested = self.mode == Mode::Insert;
+    }
+
+    /// Append pasted text to the input, line breaks kept as newlines rather than sends
+    pub fn insert_pasted(&mut self, text: &str) {
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        let text = text.trim_end_matches('\n');
+        if text.is_empty() {
+            self.status_message = Some("Nothing to paste".to_string());
+            return;
+        }
+        self.input.push_str(text);
+        self.stop_history_recall();
+    }
+
+    /// Add a chat to the list
+    pub fn add_chat(&mut self, id: i64, name: String) {
+        if !self.chats.iter().any(|c| c.id == id) {
+            self.chats.push(Chat {
+                id,
+                name,
+                last_message: None,
+                last_outgoing: false,
+                unread: 0,
+                muted_until: None,
+                pinned: Vec::new(),
+                is_group: false,
+                is_bot: false,
+                is_channel: false,
+                mentions: 0,
+                read_inbox_max_id: 0,
+                is_blocked: false,
+                slow_mode_secs: None,
+                is_forum: false,
+                topics: Vec::new(),
+                username: None,
+                members: None,
+            });
+        }
+    }
+
+    /// Whether a chat already has a message with this id loaded
+    pub fn has_message(&self, chat_id: i64, id: i32) -> bool {
+        self.messages
+            .get(&chat_id)
+            .is_some_and(|messages| messages.iter().rev().any(|m| m.id == id))
+    }
+
+    /// Add a message to a chat. One that's already there (e.g. the update
+    /// echoing a message we just sent and added) is ignored
+    pub fn add_message(
+        &mut self,
+        chat_id: i64,
+        id: i32,
+        sender: String,
+        text: String,
+        outgoing: bool,
+        date: i64,
+    ) {
+        if self.has_message(chat_id, id) {
+            return;
+        }
+        self.messages_revision += 1;
+        let is_group = self.chats.iter().any(|c| c.id == chat_id && c.is_group);
+        let display_name = sender_display_name(&sender, outgoing, is_group);
+        let viewing = self.current_chat_id() == Some(chat_id) && self.selected_message > 0;
+        let selected_id = viewing
+            .then(|| self.selected_message().map(|m| m.id))
+            .flatten();
+
+        // Keep the cursor on the same message when it isn't on the newest one
+        if self.selected_message > 0
+            && self.current_chat_id() == Some(chat_id)
+            && !text.trim().is_empty()
+        {
+            self.selected_message += 1;
+        }
+
+        let messages = self.messages.entry(chat_id).or_default();
+        messages.push(Message {
+            id,
+            sender,
+            display_name,
+            forwarded_from: None,
+            media: None,
+            media_kind: None,
+            kind: MessageKind::Text,
+            text: text.clone(),
+            formats: Vec::new(),
+            outgoing,
+            deleted: false,
+            edited: false,
+            date,
+            topic: None,
+        });
+
+        // Over the cap, forget the oldest messages, but never the one under the
+        // cursor (e.g. after a :jump far back). The first one left is where :jump
+        // starts fetching older history from, so dropped ones can still be reached
+        let cap = self.config.max_messages_per_chat;
+        if cap > 0 && messages.len() > cap {
+            let mut excess = messages.len() - cap;
+            if let Some(index) = selected_id.and_then(|id| messages.iter().position(|m| m.id == id))
+            {
+                excess = excess.min(index);
+            }
+            messages.drain(..excess);
+            if excess > 0 {
+                self.history_start.remove(&chat_id);
+            }
+        }
+
+        // Update last message preview
+        if let Some(chat) = self.chats.iter_mut().find(|c| c.id == chat_id) {
+            chat.last_message = Some(text);
+            chat.last_outgoing = outgoing;
+            // Messages up to the server's read mark were read on some device
+            if !outgoing && id > chat.read_inbox_max_id {
+                chat.unread += 1;
+            }
+        }
+    }
+
+    /// Insert a message fetched from further back in history, in id order.
+    /// Unlike add_message it leaves the unread count and chat preview alone
+    pub fn add_older_message(
+        &mut self,
+        chat_id: i64,
+        id: i32,
+        sender: String,
+        text: String,
+        outgoing: bool,
+        date: i64,
+    ) {
+        let is_group = self.chats.iter().any(|c| c.id == chat_id && c.is_group);
+        let display_name = sender_display_name(&sender, outgoing, is_group);
+        let selected_id = (self.current_chat_id() == Some(chat_id) && self.selected_message > 0)
+            .then(|| self.selected_message().map(|m| m.id))
+            .flatten();
+        let messages = self.messages.entry(chat_id).or_default();
+        if messages.iter().any(|m| m.id == id) {
+            return;
+        }
+        let index = messages.partition_point(|m| m.id < id);
+        messages.insert(
+            index,
+            Message {
+                id,
+                sender,
+                display_name,
+                forwarded_from: None,
+                media: None,
+                media_kind: None,
+                kind: MessageKind::Text,
+                text,
+                formats: Vec::new(),
+                outgoing,
+                deleted: false,
+                edited: false,
+                date,
+                topic: None,
+            },
+        );
+        self.messages_revision += 1;
+
+        // Over the cap, forget messages from the end farthest from the cursor:
+        // the newest ones past it (or past this one, with the cursor on the
+        // newest), reloaded on the way back down, then the oldest if needed
+        let cap = self.config.max_messages_per_chat;
+        if cap == 0 || messages.len() <= cap {
+            return;
+        }
+        let anchor = selected_id
+            .and_then(|id| messages.iter().position(|m| m.id == id))
+            .unwrap_or(index);
+        let keep = cap.max(anchor + 1);
+        if messages.len() > keep {
+            messages.truncate(keep);
+            self.newest_trimmed.insert(chat_id);
+        }
+        let excess = messages.len().saturating_sub(cap);
+        if excess > 0 {
+            messages.drain(..excess);
+            self.history_start.remove(&chat_id);
+        }
+        // The cursor counts from the newest shown message, which may have changed
+        if let Some(id) = selected_id {
+            let shown = self.shown_messages();
+            if let Some(position) = shown.iter().position(|m| m.id == id) {
+                self.selected_message = shown.len() - 1 - position;
+            }
+        }
+    }
+
+    /// Mark a loaded message as forwarded, naming the original author when we can
+    pub fn set_forwarded(
+        &mut self,
+        chat_id: i64,
+        message_id: i32,
+        (peer_id, name): (Option<i64>, Option<String>),
+    ) {
+        // Fall back to a chat we know by that id; hidden authors stay unnamed
+        let name = name
+            .filter(|n| !n.trim().is_empty())
+            .or_else(|| {
+                let peer_id = peer_id?;
+                self.chats
+                    .iter()
+                    .find(|c| c.id == peer_id)
+                    .map(|c| c.name.clone())
+            })
+            .unwrap_or_default();
+        if let Some(msg) = self
+            .messages
+            .get_mut(&chat_id)
+            .and_then(|msgs| msgs.iter_mut().find(|m| m.id == message_id))
+        {
+            msg.forwarded_from = Some(name);
+            self.messages_revision += 1;
+        }
+    }
+
+    /// Attach a media label to a loaded message so media-only messages show up too
+    pub fn set_media(&mut self, chat_id: i64, message_id: i32, label: String) {
+        let Some(messages) = self.messages.get_mut(&chat_id) else {
+            return;
+        };
+        let is_last = messages.last().is_some_and(|m| m.id == message_id);
+        let Some(msg) = messages.iter_mut().find(|m| m.id == message_id) else {
+            return;
+        };
+        // A media-only message wasn't shown before, so it shifts the cursor now
+        let newly_shown = msg.media.is_none() && msg.text.trim().is_empty();
+        msg.media = Some(label.clone());
+        self.messages_revision += 1;
+        if newly_shown && self.selected_message > 0 && self.current_chat_id() == Some(chat_id) {
+            self.selected_message += 1;
+        }
+        if is_last {
+            if let Some(chat) = self.chats.iter_mut().find(|c| c.id == chat_id) {
+                if chat
+                    .last_message
+                    .as_deref()
+                    .is_none_or(|t| t.trim().is_empty())
+                {
+                    chat.last_message = Some(label);
+                }
+            }
+        }
+    }
+
+    /// Record the forum topic a message was posted in (None for General)
+    pub fn set_topic(&mut self, chat_id: i64, message_id: i32, topic: Option<i32>) {
+        let active = self.active_topic.get(&chat_id).map(|(id, _)| *id);
+        let Some(messages) = self.messages.get_mut(&chat_id) else {
+            return;
+        };
+        let is_last = messages.last().is_some_and(|m| m.id == message_id);
+        let Some(msg) = messages.iter_mut().find(|m| m.id == message_id) else {
+            return;
+        };
+        msg.topic = topic;
+        self.messages_revision += 1;
+        // A new message in another topic than the one shown isn't shown, so it
+        // doesn't shift the cursor after all
+        let hidden = active.is_some_and(|active| !msg.in_topic(active));
+        if hidden && is_last && self.selected_message > 0 && self.current_chat_id() == Some(chat_id)
+        {
+            self.selected_message -= 1;
+        }
+    }
+
+    /// Record what kind of media or call a loaded message has, for its glyph
+    /// (and in the chat preview, where set_media put its bare label)
+    pub fn set_media_kind(&mut self, chat_id: i64, message_id: i32, kind: MediaKind) {
+        let Some(messages) = self.messages.get_mut(&chat_id) else {
+            return;
+        };
+        let is_last = messages.last().is_some_and(|m| m.id == message_id);
+        let Some(msg) = messages.iter_mut().find(|m| m.id == message_id) else {
+            return;
+        };
+        msg.media_kind = Some(kind);
+        self.messages_revision += 1;
+        if !is_last {
+            return;
+        }
+        if let Some(chat) = self.chats.iter_mut().find(|c| c.id == chat_id) {
+            if msg.media.is_some() && chat.last_message == msg.media {
+                chat.last_message = msg.media_text(glyphs(&self.config));
+            }
+        }
+    }
+
+    /// Mark a loaded message as a service message (its text describes the action)
+    pub fn set_service(&mut self, chat_id: i64, message_id: i32) {
+        if let Some(msg) = self
+            .messages
+            .get_mut(&chat_id)
+            .and_then(|msgs| msgs.iter_mut().find(|m| m.id == message_id))
+        {
+            msg.kind = MessageKind::Service;
+            self.messages_revision += 1;
+        }
+    }
+
+    /// Attach the formatting entities of a loaded message
+    pub fn set_formats(
+        &mut self,
+        chat_id: i64,
+        message_id: i32,
+        formats: Vec<(Range<usize>, TextFormat)>,
+    ) {
+        if let Some(msg) = self
+            .messages
+            .get_mut(&chat_id)
+            .and_then(|msgs| msgs.iter_mut().find(|m| m.id == message_id))
+        {
+            msg.formats = formats;
+            self.messages_revision += 1;
+        }
+    }
+
+    /// Record that a chat's history finished loading, even if it had no messages
+    pub fn mark_loaded(&mut self, chat_id: i64) {
+        self.messages.entry(chat_id).or_default();
+        self.messages_revision += 1;
+        // Whatever loaded in the open chat is being read now
+        if self.current_chat_id() == Some(chat_id) {
+            self.clear_current_unread();
+        }
+        if self.pending_jump.is_some_and(|(id, _)| id == chat_id) {
+            self.apply_pending_jump();
+        }
+    }
+
+    /// Drop all loaded messages of a chat (before a reload)
+    pub fn clear_messages(&mut self, chat_id: i64) {
+        self.messages.remove(&chat_id);
+        self.newest_trimmed.remove(&chat_id);
+        self.messages_revision += 1;
+        if self.current_chat_id() == Some(chat_id) {
+            self.selected_message = 0;
+            self.new_below = 0;
+        }
+    }
+
+    /// Replace the text of a loaded message after an edit (ignored if not loaded)
+    pub fn edit_message(&mut self, chat_id: i64, message_id: i32, text: String) {
+        let Some(messages) = self.messages.get_mut(&chat_id) else {
+            return;
+        };
+        let Some(msg) = messages.iter_mut().find(|m| m.id == message_id) else {
+            return;
+        };
+        msg.text = text.clone();
+        msg.formats.clear(); // Offsets of the old text, set_formats applies the new ones
+        msg.edited = true;
+        self.messages_revision += 1;
+
+        // Keep the preview in sync if the newest message was edited
+        let is_latest = messages.last().is_some_and(|m| m.id == message_id);
+        if is_latest {
+            if let Some(chat) = self.chats.iter_mut().find(|c| c.id == chat_id) {
+                chat.last_message = Some(text);
+            }
+        }
+    }
+
+    /// Apply a server-side deletion to the given chats' loaded messages
+    pub fn delete_messages(&mut self, chat_ids: &[i64], message_ids: &[i32]) {
+        for chat_id in chat_ids {
+            // The cursor counts from the newest shown message, so removing newer
+            // ones would move it onto another message
+            let viewing = self.current_chat_id() == Some(*chat_id);
+            let removed_below = if viewing {
+                let shown = self.shown_messages();
+                let cursor = shown.len().saturating_sub(self.selected_message + 1);
+                shown
+                    .iter()
+                    .skip(cursor + 1)
+                    .filter(|m| message_ids.contains(&m.id))
+                    .count()
+            } else {
+                0
+            };
+            let Some(messages) = self.messages.get_mut(chat_id) else {
+                continue;
+            };
+            match self.config.deleted_messages {
+                DeletedMessages::Remove => {
+                    let before = messages.len();
+                    messages.retain(|m| !message_ids.contains(&m.id));
+                    if messages.len() == before {
+                        continue;
+                    }
+                    if viewing {
+                        self.selected_message = self.selected_message.saturating_sub(removed_below);
+                    }
+                }
+                DeletedMessages::Placeholder => {
+                    let mut changed = false;
+                    for msg in messages.iter_mut().filter(|m| message_ids.contains(&m.id)) {
+                        msg.deleted = true;
+                        changed = true;
+                    }
+                    if !changed {
+                        continue;
+                    }
+                }
+            }
+            self.messages_revision += 1;
+
+            // Preview falls back to the newest message that still exists
+            let latest = messages.iter().rev().find(|m| !m.deleted);
+            let preview = latest.map(|m| match m.media_text(glyphs(&self.config)) {
+                Some(label) if m.text.trim().is_empty() => label,
+                _ => m.text.clone(),
+            });
+            let outgoing = latest.is_some_and(|m| m.outgoing);
+            if let Some(chat) = self.chats.iter_mut().find(|c| c.id == *chat_id) {
+                chat.last_message = preview;
+                chat.last_outgoing = outgoing;
+            }
+            if viewing {
+                let last = self.shown_messages().len().saturating_sub(1);
+                self.selected_message = self.selected_message.min(last);
+            }
+        }
+    }
+
+    /// Enter search mode
+    pub fn enter_search(&mut self) {
+        self.mode = Mode::Search;
+        self.search_input.clear();
+        self.search_selected = 0;
+        self.update_search_filter();
+    }
+
+    /// Exit search mode without jumping
+    pub fn exit_search(&mut self) {
+        self.mode = Mode::Normal;
+        self.search_input.clear();
+        self.filtered_chat_indices.clear();
+    }
+
+    /// Update filtered chat indices based on search input
+    pub fn update_search_filter(&mut self) {
+        let query = self.search_input.to_lowercase();
+        self.filtered_chat_indices = self
+            .chats
+            .iter()
+            .enumerate()
+            .filter(|(_, chat)| self.chat_filter.is_none_or(|filter| filter.matches(chat)))
+            .filter(|(_, chat)| {
+                if query.is_empty() {
+                    true // Show all when empty
+                } else {
+                    chat.name.to_lowercase().contains(&query)
+                }
+            })
+            .map(|(i, _)| i)
+            .collect();
+
+        // Reset selection if it's out of bounds
+        if self.search_selected >= self.filtered_chat_indices.len() {
+            self.search_selected = 0;
+        }
+    }
+
+    /// Jump to the selected search result
+    pub fn jump_to_selected_search_result(&mut self) {
+        if let Some(&chat_index) = self.filtered_chat_indices.get(self.search_selected) {
+            self.select_chat(chat_index);
+        }
+        self.exit_search();
+    }
+
+    /// Move selection up in search results
+    pub fn search_move_up(&mut self) {
+        if self.search_selected > 0 {
+            self.search_selected -= 1;
+        }
+    }
+
+    /// Move selection down in search results
+    pub fn search_move_down(&mut self) {
+        if self.search_selected < self.filtered_chat_indices.len().saturating_sub(1) {
+            self.search_selected += 1;
+        }
+    }
+
+    // ==================== Tick Methods ====================
+
+    /// Periodic idle housekeeping, called on every tick of the main loop
+    pub fn tick(&mut self) {
+        // Expire a status message once it has been up for a while
+        match (&self.status_message, &self.status_seen) {
+            (Some(text), Some((seen, since))) if text == seen => {
+                if since.elapsed() >= STATUS_MESSAGE_TTL {
+                    self.status_message = None;
+                    self.status_seen = None;
+                }
+            }
+            (Some(text), _) => self.status_seen = Some((text.clone(), Instant::now())),
+            (None, _) => self.status_seen = None,
+        }
+
+        // Forget slow mode cooldowns that are over
+        let now = Instant::now();
+        self.slow_mode_until.retain(|_, until| *until > now);
+
+        // Lock once nothing was typed for lock_after_mins
+        let lock_after = Duration::from_secs(self.config.lock_after_mins * 60);
+        if !lock_after.is_zero() && self.last_input.elapsed() >= lock_after {
+            self.lock();
+        }
+    }
+
+    // ==================== Lock Methods ====================
+
+    /// Note a key or paste, which keeps the idle lock away
+    pub fn note_input(&mut self) {
+        self.last_input = Instant::now();
+    }
+
+    /// Hide everything behind the lock screen (idle timeout or :lock)
+    pub fn lock(&mut self) {
+        self.locked = true;
+        self.lock_input.clear();
+    }
+
+    /// Whether unlocking needs the passphrase: it's configured and this run knows
+    /// one to check against, otherwise any key unlocks
+    pub fn lock_needs_passphrase(&self) -> bool {
+        self.config.lock_passphrase && session_crypto::knows_passphrase()
+    }
+
+    /// Unlock with the typed passphrase, or clear it and say so if it's wrong
+    pub fn submit_unlock(&mut self) {
+        let typed = std::mem::take(&mut self.lock_input);
+        if !self.lock_needs_passphrase() || session_crypto::check_passphrase(&typed) {
+            self.locked = false;
+            self.status_message = None;
+        } else {
+            self.status_message = Some("Wrong passphrase".to_string());
+        }
+    }
+
+    // ==================== Link Methods ====================
+
+    /// Open the links of the selected message, or of the newest message that
+    /// has any when the chat panel isn't focused (picker if several)
+    pub fn open_link(&mut self) {
+        let candidates = match self.panel {
+            Panel::Chats => self.selected_message().into_iter().collect(),
+            Panel::Friends => self.current_messages(),
+        };
+        let urls: Vec<String> = candidates
+            .iter()
+            .rev()
+            .filter(|m| !m.deleted)
+            .map(|m| {
+                crate::links::find_urls(&m.text)
+                    .into_iter()
+                    .map(|r| crate::links::normalize_url(&m.text[r]))
+                    .collect::<Vec<_>>()
+            })
+            .find(|urls| !urls.is_empty())
+            .unwrap_or_default();
+
+        match urls.len() {
+            0 if self.panel == Panel::Chats => {
+                self.status_message = Some("No links in this message".to_string())
+            }
+            0 => self.status_message = Some("No links in this chat".to_string()),
+            1 => self.open_url_requested = urls.into_iter().next(),
+            _ => {
+                self.link_choices = urls;
+                self.link_picker_selected = 0;
+                self.mode = Mode::LinkPicker;
+            }
+        }
+    }
+
+    /// Move up in the link picker
+    pub fn link_picker_move_up(&mut self) {
+        if self.link_picker_selected > 0 {
+            self.link_picker_selected -= 1;
+        }
+    }
+
+    /// Move down in the link picker
+    pub fn link_picker_move_down(&mut self) {
+        if self.link_picker_selected < self.link_choices.len().saturating_sub(1) {
+            self.link_picker_selected += 1;
+        }
+    }
+
+    /// Open the highlighted link and close the picker
+    pub fn select_link(&mut self) {
+        self.open_url_requested = self.link_choices.get(self.link_picker_selected).cloned();
+        self.exit_link_picker();
+    }
+
+    /// Close the link picker
+    pub fn exit_link_picker(&mut self) {
+        self.mode = Mode::Normal;
+        self.link_choices.clear();
+    }
+
+    // ==================== Visual Mode Methods ====================
+
+    /// Start selecting a range of messages at the cursor (v in the chat panel)
+    pub fn enter_visual(&mut self) {
+        if self.panel != Panel::Chats {
+            return;
+        }
+        let Some(id) = self.selected_message().map(|m| m.id) else {
+            return;
+        };
+        self.visual_anchor = Some(id);
+        self.mode = Mode::Visual;
+    }
+
+    /// Leave visual mode without copying
+    pub fn exit_visual(&mut self) {
+        self.visual_anchor = None;
+        self.mode = Mode::Normal;
+    }
+
+    /// First and last index into shown_messages of the visual selection, oldest first.
+    /// An anchor that disappeared (deleted, trimmed) collapses onto the cursor
+    pub fn visual_range(&self) -> Option<(usize, usize)> {
+        if self.mode != Mode::Visual {
+            return None;
+        }
+        let shown = self.shown_messages();
+        let cursor = shown.len().checked_sub(self.selected_message + 1)?;
+        let anchor = self
+            .visual_anchor
+            .and_then(|id| shown.iter().position(|m| m.id == id))
+            .unwrap_or(cursor);
+        Some((anchor.min(cursor), anchor.max(cursor)))
+    }
+
+    /// Copy the selected messages, each prefixed with its time and sender
+    pub fn yank_visual(&mut self) {
+        let Some((first, last)) = self.visual_range() else {
+            return;
+        };
+        let chat_name = self
+            .chats
+            .get(self.selected_chat)
+            .map(|c| c.name.clone())
+            .unwrap_or_default();
+        let text = self.shown_messages()[first..=last]
+            .iter()
+            .map(|m| {
+                let sender = if m.sender.trim().is_empty() {
+                    chat_name.as_str()
+                } else {
+                    m.sender.as_str()
+                };
+                let body = if m.deleted {
+                    "(deleted)"
+                } else if m.text.trim().is_empty() {
+                    m.media.as_deref().unwrap_or("")
+                } else {
+                    m.text.as_str()
+                };
+                format!(
+                    "[{}] {}: {}",
+                    self.config.format_time_with(m.date, "%Y-%m-%d %H:%M"),
+                    sender,
+                    body
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.copy_requested = Some(text);
+        self.exit_visual();
+    }
+
+    /// Pick words of the one selected message to quote in a reply (q in visual
+    /// mode), starting with all of them
+    pub fn start_quote(&mut self) {
+        let Some((first, last)) = self.visual_range() else {
+            return;
+        };
+        if first != last {
+            self.status_message = Some("Select a single message to quote".to_string());
+            return;
+        }
+        let message = self.shown_messages()[first];
+        let words = word_spans(&message.text);
+        if message.deleted || message.kind != MessageKind::Text || words.is_empty() {
+            self.status_message = Some("No text to quote".to_string());
+            return;
+        }
+        self.quote_pick = Some((message.id, 0, words.len() - 1));
+        self.mode = Mode::Quote;
+    }
+
+    /// The message being quoted and the byte range of its picked words
+    pub fn quote_selection(&self) -> Option<(&Message, Range<usize>)> {
+        let (id, first, last) = self.quote_pick?;
+        let message = self.current_messages().into_iter().find(|m| m.id == id)?;
+        let words = word_spans(&message.text);
+        let start = words.get(first)?.start;
+        let end = words.get(last)?.end;
+        Some((message, start..end))
+    }
+
+    /// Move the first (`start`) or last quoted word by one, keeping at least one word
+    pub fn move_quote(&mut self, start: bool, forward: bool) {
+        let Some(count) = self
+            .quote_selection()
+            .map(|(m, _)| word_spans(&m.text).len())
+        else {
+            return;
+        };
+        let Some((_, first, last)) = self.quote_pick.as_mut() else {
+            return;
+        };
+        match (start, forward) {
+            (true, true) if *first < *last => *first += 1,
+            (true, false) => *first = first.saturating_sub(1),
+            (false, true) if *last + 1 < count => *last += 1,
+            (false, false) if *last > *first => *last -= 1,
+            _ => {}
+        }
+    }
+
+    /// Reply to the quoted message with the picked words as its quote
+    pub fn confirm_quote(&mut self) {
+        let picked = self.quote_selection().map(|(m, range)| (m.id, range));
+        self.quote_pick = None;
+        self.visual_anchor = None;
+        self.mode = Mode::Normal;
+        let Some((id, range)) = picked else {
+            return;
+        };
+        self.enter_insert();
+        if self.mode == Mode::Insert {
+            self.reply_to = Some(id);
+            self.reply_quote = Some(range);
+        }
+    }
+
+    /// Back to the message selection without quoting
+    pub fn cancel_quote(&mut self) {
+        self.quote_pick = None;
+        self.mode = Mode::Visual;
+    }
+
+    // ==================== Pinned List Methods ====================
+
+    /// Pinned messages of the open chat, newest first
+    pub fn current_pins(&self) -> &[(i32, String)] {
+        self.chats
+            .get(self.selected_chat)
+            .map(|chat| chat.pinned.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// List the open chat's pinned messages once they're fetched
+    pub fn open_pins(&mut self, chat_id: i64) {
+        if self.current_chat_id() != Some(chat_id) {
+            return;
+        }
+        if self.current_pins().is_empty() {
+            self.status_message = Some("No pinned messages".to_string());
+            return;
+        }
+        self.pins_selected = 0;
+        self.mode = Mode::PinList;
+    }
+
+    /// Move up in the pinned list
+    pub fn pins_move_up(&mut self) {
+        self.pins_selected = self.pins_selected.saturating_sub(1);
+    }
+
+    /// Move down in the pinned list
+    pub fn pins_move_down(&mut self) {
+        if self.pins_selected + 1 < self.current_pins().len() {
+            self.pins_selected += 1;
+        }
+    }
+
+    /// Jump to the highlighted pinned message and close the list
+    pub fn select_pin(&mut self) {
+        let target = self
+            .current_pins()
+            .get(self.pins_selected)
+            .map(|(id, _)| *id);
+        self.exit_pins();
+        if let Some(message_id) = target {
+            self.jump_to_id(message_id);
+        }
+    }
+
+    /// Close the pinned list
+    pub fn exit_pins(&mut self) {
+        self.mode = Mode::Normal;
+    }
+
+    // ==================== Forum Topic Methods ====================
+
+    /// Topic the open forum is narrowed to, as (topic id, title)
+    pub fn current_topic(&self) -> Option<&(i32, String)> {
+        self.active_topic.get(&self.current_chat_id()?)
+    }
+
+    /// Topic new messages go to: the active one, unless it's General (which
+    /// takes messages without a topic)
+    pub fn send_topic(&self) -> Option<i32> {
+        self.current_topic()
+            .map(|(id, _)| *id)
+            .filter(|&id| id != GENERAL_TOPIC_ID)
+    }
+
+    /// Fetch the open forum's topics to pick one (T / :topics)
+    pub fn request_topics(&mut self) {
+        let Some(chat_id) = self.current_real_chat_id() else {
+            return;
+        };
+        if self.chats[self.selected_chat].is_forum {
+            self.topics_requested = Some(chat_id);
+        } else {
+            self.status_message = Some("This chat has no topics".to_string());
+        }
+    }
+
+    /// Show the topic picker once a forum's topics are fetched, on the active topic
+    pub fn open_topics(&mut self, chat_id: i64) {
+        if self.current_chat_id() != Some(chat_id) {
+            return;
+        }
+        let active = self.current_topic().map(|(id, _)| *id);
+        self.topics_selected = self.chats[self.selected_chat]
+            .topics
+            .iter()
+            .position(|(id, _)| Some(*id) == active)
+            .map_or(0, |i| i + 1);
+        self.mode = Mode::TopicPicker;
+    }
+
+    /// Move up in the topic picker
+    pub fn topics_move_up(&mut self) {
+        self.topics_selected = self.topics_selected.saturating_sub(1);
+    }
+
+    /// Move down in the topic picker (past "All topics")
+    pub fn topics_move_down(&mut self) {
+        let count = self
+            .chats
+            .get(self.selected_chat)
+            .map_or(0, |c| c.topics.len());
+        if self.topics_selected < count {
+            self.topics_selected += 1;
+        }
+    }
+
+    /// Narrow the open forum to the highlighted topic, or show all of them again
+    pub fn select_topic(&mut self) {
+        self.mode = Mode::Normal;
+        let Some(chat) = self.chats.get(self.selected_chat) else {
+            return;
+        };
+        let chat_id = chat.id;
+        match self.topics_selected.checked_sub(1) {
+            Some(i) => {
+                if let Some(topic) = chat.topics.get(i).cloned() {
+                    self.active_topic.insert(chat_id, topic);
+                }
+            }
+            None => {
+                self.active_topic.remove(&chat_id);
+            }
+        }
+        // A different set of messages is shown, start again from the newest
+        self.selected_message = 0;
+        self.scroll_offset.set(0);
+        self.new_below = 0;
+        self.messages_revision += 1;
+    }
+
+    /// Close the topic picker without changing topic
+    pub fn exit_topics(&mut self) {
+        self.mode = Mode::Normal;
+    }
+
+    // ==================== Sticker Picker Methods ====================
+
+    /// Look through the saved stickers and GIFs for one to send (:sticker)
+    pub fn request_stickers(&mut self, query: &str) {
+        let Some(chat) = self
+            .current_real_chat_id()
+            .and_then(|id| self.chats.iter().find(|c| c.id == id))
+        else {
+            return;
+        };
+        if chat.is_blocked {
+            self.status_message = Some("You blocked this contact (:unblock)".to_string());
+            return;
+        }
+        self.sticker_requested = Some(query.to_string());
+    }
+
+    /// Show the saved stickers whose label contains the query (all of them for
+    /// an empty one), or say why there's nothing to pick
+    pub fn set_sticker_results(&mut self, query: &str, saved: Vec<(i64, String)>) {
+        if saved.is_empty() {
+            self.status_message = Some("No saved stickers or GIFs".to_string());
+            return;
+        }
+        let query = query.to_lowercase();
+        self.sticker_choices = saved
+            .into_iter()
+            .filter(|(_, label)| label.to_lowercase().contains(&query))
+            .collect();
+        if self.sticker_choices.is_empty() {
+            self.status_message = Some(format!("No stickers match {}", query));
+            return;
+        }
+        self.sticker_selected = 0;
+        self.mode = Mode::StickerPicker;
+    }
+
+    /// Move up in the sticker picker
+    pub fn stickers_move_up(&mut self) {
+        self.sticker_selected = self.sticker_selected.saturating_sub(1);
+    }
+
+    /// Move down in the sticker picker
+    pub fn stickers_move_down(&mut self) {
+        if self.sticker_selected + 1 < self.sticker_choices.len() {
+            self.sticker_selected += 1;
+        }
+    }
+
+    /// Send the highlighted sticker to the open chat
+    pub fn select_sticker(&mut self) {
+        let chosen = self
+            .sticker_choices
+            .get(self.sticker_selected)
+            .map(|(id, _)| *id);
+        self.exit_stickers();
+        if let (Some(chat_id), Some(sticker_id)) = (self.current_real_chat_id(), chosen) {
+            self.sticker_send_requested = Some((chat_id, sticker_id));
+        }
+    }
+
+    /// Close the sticker picker without sending
+    pub fn exit_stickers(&mut self) {
+        self.mode = Mode::Normal;
+    }
+
+    // ==================== Message View Methods ====================
+
+    /// Show the selected message in full (K), for text cut short or split
+    /// across many lines in the chat
+    pub fn view_message(&mut self) {
+        let Some(msg) = self.target_message() else {
+            return;
+        };
+        let sender = if msg.outgoing {
+            "You".to_string()
+        } else if msg.sender.trim().is_empty() {
+            self.chats
+                .get(self.selected_chat)
+                .map(|c| c.name.clone())
+                .unwrap_or_default()
+        } else {
+            msg.sender.clone()
+        };
+        let text = match (msg.media_text(glyphs(&self.config)), msg.text.trim()) {
+            (Some(label), "") => label,
+            (Some(label), caption) => format!("{}\n{}", label, caption),
+            (None, text) => text.to_string(),
+        };
+        self.message_view = Some((sender, text));
+        self.message_view_scroll = 0;
+        self.mode = Mode::MessageView;
+    }
+
+    /// Scroll the message view by a line, no further than its last line
+    pub fn scroll_message_view(&mut self, down: bool) {
+        self.message_view_scroll = if down {
+            (self.message_view_scroll + 1).min(self.message_view_max_scroll.get())
+        } else {
+            self.message_view_scroll.saturating_sub(1)
+        };
+    }
+
+    /// Close the message view
+    pub fn exit_message_view(&mut self) {
+        self.message_view = None;
+        self.mode = Mode::Normal;
+    }
+
+    // ==================== Account Picker Methods ====================
+
+    /// Enter account picker mode
+    pub fn enter_account_picker(&mut self) {
+        self.mode = Mode::AccountPicker;
+        self.account_picker_selected = 0;
+        // Find current account index
+        for (i, (id, _)) in self.account_names.iter().enumerate() {
+            if *id == self.current_account_id {
+                self.account_picker_selected = i;
+                break;
+            }
+        }
+    }
+
+    /// Exit account picker mode
+    pub fn exit_account_picker(&mut self) {
+        self.mode = Mode::Normal;
+    }
+
+    /// Move up in account picker
+    pub fn account_picker_move_up(&mut self) {
+        if self.account_picker_selected > 0 {
+            self.account_picker_selected -= 1;
+        }
+    }
+
+    /// Move down in account picker (includes "+ Add Account" option)
+    pub fn account_picker_move_down(&mut self) {
+        // +1 for the "Add Account" option
+        let max_index = self.account_names.len();
+        if self.account_picker_selected < max_index {
+            self.account_picker_selected += 1;
+        }
+    }
+
+    /// Select the current account in picker
+    pub fn select_account(&mut self) {
+        if self.account_picker_selected < self.account_names.len() {
+            // Switch to selected account
+            let (account_id, _) = &self.account_names[self.account_picker_selected];
+            if *account_id != self.current_account_id {
+                self.switch_account_requested = Some(account_id.clone());
+            }
+            self.exit_account_picker();
+        } else {
+            // "Add Account" selected
+            self.add_account_requested = true;
+            self.exit_account_picker();
+        }
+    }
+
+    /// Ask to drop the highlighted account's session (keeps it in the registry)
+    pub fn log_out_selected_account(&mut self) {
+        let Some((id, name)) = self.account_names.get(self.account_picker_selected) else {
+            return;
+        };
+        if *id == self.current_account_id {
+            self.status_message = Some("Can't log out the active account here (use D)".to_string());
+            self.exit_account_picker();
+            return;
+        }
+        let action = ConfirmAction::LogOutAccount {
+            id: id.clone(),
+            name: name.clone(),
+        };
+        self.ask_confirm(action);
+    }
+
+    /// Set the current account info
+    pub fn set_account_info(&mut self, account_id: String, accounts: Vec<(String, String)>) {
+        self.current_account_id = account_id;
+        self.account_names = accounts;
+    }
+
+    /// Accent color of an account, steel blue if it has none
+    pub fn account_accent(&self, account_id: &str) -> (u8, u8, u8) {
+        self.account_accents
+            .get(account_id)
+            .copied()
+            .unwrap_or((70, 130, 180))
+    }
+
+    // ==================== Command Mode Methods ====================
+
+    /// Enter command mode (after pressing :)
+    pub fn enter_command(&mut self) {
+        self.mode = Mode::Command;
+        self.command_input.clear();
+    }
+
+    /// Open command mode with `command` typed, waiting for its arguments
+    fn prompt_command(&mut self, command: &str) {
+        self.mode = Mode::Command;
+        self.command_input = command.to_string();
+    }
+
+    /// Run a command as if it was typed after :
+    fn run_command(&mut self, command: &str) {
+        self.command_input = command.to_string();
+        self.execute_command();
+    }
+
+    /// Exit command mode
+    pub fn exit_command(&mut self) {
+        self.mode = Mode::Normal;
+        self.command_input.clear();
+    }
+
+    /// Execute the current command
+    pub fn execute_command(&mut self) {
+        let cmd = self.command_input.trim().to_string();
+        let cmd_lower = cmd.to_lowercase();
+        // Commands that open an overlay switch mode again below
+        self.mode = Mode::Normal;
 
         if cmd_lower.starts_with("find ") || cmd_lower.starts_with("f ") {
             // Extract username (strip leading @ if present)
@@ -405,44 +2475,924 @@ impl App {
                 self.find_requested = Some(username.to_string());
                 self.mode = Mode::FindUser;
             }
-        } else if cmd_lower.starts_with("ai ") {
-            // Enter AI command mode with the command text
-            let ai_cmd = cmd
-                .strip_prefix("ai ")
-                .or_else(|| cmd.strip_prefix("AI "))
-                .unwrap_or("");
-            self.ai_input = ai_cmd.to_string();
-            self.mode = Mode::AICommand;
-            if !ai_cmd.is_empty() {
-                // Auto-submit if command provided
-                self.submit_ai_command();
+        } else if let Some(target) = cmd
+            .strip_prefix("goto ")
+            .or_else(|| cmd.strip_prefix("GOTO "))
+        {
+            self.goto_chat(target.trim().trim_start_matches('@'));
+        } else if cmd_lower.starts_with("ai ") {
+            // Enter AI command mode with the command text
+            let ai_cmd = cmd
+                .strip_prefix("ai ")
+                .or_else(|| cmd.strip_prefix("AI "))
+                .unwrap_or("");
+            self.ai_input = ai_cmd.to_string();
+            self.mode = Mode::AICommand;
+            if !ai_cmd.is_empty() {
+                // Auto-submit if command provided
+                self.submit_ai_command();
+            }
+        } else if cmd_lower == "ai" {
+            // Enter AI command mode empty
+            self.enter_ai_command();
+        } else if cmd_lower.starts_with("code ") {
+            // Enter code mode with query
+            let query = cmd
+                .strip_prefix("code ")
+                .or_else(|| cmd.strip_prefix("CODE "))
+                .unwrap_or("");
+            self.code_input = query.to_string();
+            self.mode = Mode::Code;
+            if !query.is_empty() {
+                self.submit_code_query();
+            }
+        } else if cmd_lower == "code" {
+            // Enter code mode empty
+            self.enter_code_mode();
+        } else if cmd_lower.starts_with("set ") {
+            self.set_option(cmd["set ".len()..].trim());
+        } else if cmd_lower == "mute" || cmd_lower.starts_with("mute ") {
+            let duration = cmd_lower["mute".len()..].trim().to_string();
+            self.request_mute(&duration);
+        } else if cmd_lower == "unmute" {
+            if let Some(chat_id) = self.current_real_chat_id() {
+                self.mute_requested = Some((chat_id, 0));
+            }
+        } else if cmd_lower == "block" {
+            self.request_block(true);
+        } else if cmd_lower == "unblock" {
+            self.request_block(false);
+        } else if cmd_lower == "msg" || cmd_lower.starts_with("msg ") {
+            self.send_to_id(cmd["msg".len()..].trim());
+        } else if cmd_lower == "search" || cmd_lower.starts_with("search ") {
+            self.search_messages(cmd["search".len()..].trim());
+        } else if cmd_lower == "next" {
+            self.step_search_result(true);
+        } else if cmd_lower == "prev" {
+            self.step_search_result(false);
+        } else if cmd_lower == "nohl" || cmd_lower == "noh" {
+            self.message_search_results.clear();
+            self.message_search_query = None;
+            self.status_message = Some("Search results cleared".to_string());
+        } else if cmd_lower == "filter" || cmd_lower.starts_with("filter ") {
+            match cmd_lower["filter".len()..].trim() {
+                "" | "all" | "none" => self.set_chat_filter(None),
+                name => match ChatFilter::parse(name) {
+                    Some(filter) => self.set_chat_filter(Some(filter)),
+                    None => {
+                        self.status_message = Some(format!(
+                            "Unknown filter: {} (users, groups, channels, bots, all)",
+                            name
+                        ))
+                    }
+                },
+            }
+        } else if cmd_lower == "jump" || cmd_lower.starts_with("jump ") {
+            self.jump_command(cmd["jump".len()..].trim());
+        } else if cmd_lower == "sticker" || cmd_lower.starts_with("sticker ") {
+            self.request_stickers(cmd["sticker".len()..].trim());
+        } else if cmd_lower == "topics" {
+            self.request_topics();
+        } else if cmd_lower == "lock" {
+            self.lock();
+        } else if cmd_lower == "reconnect" {
+            self.manual_reconnect_requested = true;
+            self.status_message = Some("Reconnecting…".to_string());
+        } else if cmd_lower == "refresh" {
+            self.refresh_requested = true;
+            self.status_message = Some("Refreshing chats…".to_string());
+        } else if cmd_lower == "pins" {
+            if let Some(chat_id) = self.current_real_chat_id() {
+                self.pins_requested = Some(chat_id);
+            }
+        } else if cmd_lower == "download" || cmd_lower.starts_with("download ") {
+            self.download_target(cmd["download".len()..].trim());
+        } else if cmd_lower == "export" || cmd_lower.starts_with("export ") {
+            self.export_current_chat(cmd["export".len()..].trim());
+        } else if cmd_lower == "forget-credentials" {
+            self.ask_confirm(ConfirmAction::ForgetCredentials);
+        } else if cmd_lower == "stats" {
+            self.mode = Mode::Stats;
+        } else if cmd_lower == "whoami" {
+            self.mode = Mode::WhoAmI;
+        } else if cmd_lower == "q" || cmd_lower == "quit" {
+            self.should_quit = true;
+        } else if !cmd.is_empty() {
+            self.status_message = Some(format!("Unknown command: {}", cmd));
+        }
+        // Clear command input after execution
+        self.command_input.clear();
+    }
+
+    // ==================== Command Palette Methods ====================
+
+    /// Open the command palette with every action listed
+    pub fn enter_palette(&mut self) {
+        self.mode = Mode::CommandPalette;
+        self.palette_input.clear();
+        self.palette_selected = 0;
+    }
+
+    /// Close the palette without running anything
+    pub fn exit_palette(&mut self) {
+        self.mode = Mode::Normal;
+        self.palette_input.clear();
+    }
+
+    /// Palette entries matching what's typed, best first
+    pub fn palette_matches(&self) -> Vec<&'static PaletteEntry> {
+        palette::matching(&self.palette_input)
+    }
+
+    pub fn palette_move_up(&mut self) {
+        self.palette_selected = self.palette_selected.saturating_sub(1);
+    }
+
+    pub fn palette_move_down(&mut self) {
+        if self.palette_selected + 1 < self.palette_matches().len() {
+            self.palette_selected += 1;
+        }
+    }
+
+    /// Run the highlighted palette entry
+    pub fn run_palette_selection(&mut self) {
+        let action = self
+            .palette_matches()
+            .get(self.palette_selected)
+            .map(|entry| entry.action);
+        self.exit_palette();
+        if let Some(action) = action {
+            self.run_palette_action(action);
+        }
+    }
+
+    /// Do what a palette entry stands for
+    fn run_palette_action(&mut self, action: PaletteAction) {
+        match action {
+            PaletteAction::SearchChats => self.enter_search(),
+            PaletteAction::JumpToChatNumber => self.enter_number_jump(),
+            PaletteAction::FindUser => self.prompt_command("find "),
+            PaletteAction::SearchMessages => self.prompt_command("search "),
+            PaletteAction::JumpToMessage => self.prompt_command("jump "),
+            PaletteAction::LoadMore => self.request_load_more(),
+            PaletteAction::FilterChats => self.cycle_chat_filter(),
+            PaletteAction::RetrySend => self.request_retry_send(),
+            PaletteAction::ReplyToLatest => self.reply_to_latest(),
+            PaletteAction::PasteClipboard => self.request_paste(),
+            PaletteAction::OpenLink => self.open_link(),
+            PaletteAction::TogglePin => self.toggle_pin(),
+            PaletteAction::NextPinned => self.cycle_pinned(),
+            PaletteAction::PinnedList => self.run_command("pins"),
+            PaletteAction::Topics => self.request_topics(),
+            PaletteAction::Sticker => self.run_command("sticker"),
+            PaletteAction::CopyChatLink => self.copy_chat_link(),
+            PaletteAction::Mute => self.prompt_command("mute "),
+            PaletteAction::Unmute => self.run_command("unmute"),
+            PaletteAction::NotifyLevel => self.cycle_notify_level(),
+            PaletteAction::Download => self.run_command("download"),
+            PaletteAction::Export => self.prompt_command("export "),
+            PaletteAction::Reload => self.request_reload(),
+            PaletteAction::RefreshChats => self.run_command("refresh"),
+            PaletteAction::Reconnect => self.run_command("reconnect"),
+            PaletteAction::Scratchpad => self.enter_scratchpad(),
+            PaletteAction::AiCommand => self.enter_ai_command(),
+            PaletteAction::CodeAssistant => self.enter_code_mode(),
+            PaletteAction::SwitchAccount => self.enter_account_picker(),
+            PaletteAction::WhoAmI => self.mode = Mode::WhoAmI,
+            PaletteAction::Stats => self.mode = Mode::Stats,
+            PaletteAction::Lock => self.lock(),
+            PaletteAction::SetOption => self.prompt_command("set "),
+            PaletteAction::ForgetCredentials => self.ask_confirm(ConfirmAction::ForgetCredentials),
+            PaletteAction::Disconnect => self.request_disconnect(),
+            PaletteAction::Quit => self.should_quit = true,
+        }
+    }
+
+    // ==================== Confirmation Methods ====================
+
+    /// Ask the user to confirm a destructive action
+    pub fn ask_confirm(&mut self, action: ConfirmAction) {
+        self.pending_confirm = Some(action);
+        self.mode = Mode::Confirm;
+    }
+
+    /// Run the pending action after the user pressed y, returning a message
+    /// that was waiting on it to be sent
+    pub fn confirm(&mut self) -> Option<String> {
+        match self.pending_confirm.take() {
+            Some(ConfirmAction::ForgetCredentials) => self.forget_credentials_requested = true,
+            Some(ConfirmAction::LogOutAccount { id, .. }) => {
+                self.log_out_account_requested = Some(id)
+            }
+            Some(ConfirmAction::Disconnect) => self.disconnect_requested = true,
+            Some(ConfirmAction::Block { id, .. }) => self.block_requested = Some((id, true)),
+            Some(ConfirmAction::Send { chat_id, .. }) => {
+                // Back to typing, the message goes out from there
+                self.mode = Mode::Insert;
+                if self.current_chat_id() != Some(chat_id) {
+                    self.status_message = Some("Chat changed, message not sent".to_string());
+                    return None;
+                }
+                return Some(self.take_outgoing());
+            }
+            None => {}
+        }
+        self.mode = Mode::Normal;
+        None
+    }
+
+    /// Log out and delete the session (D), asking first unless turned off in the config
+    pub fn request_disconnect(&mut self) {
+        if self.config.confirm_disconnect {
+            self.ask_confirm(ConfirmAction::Disconnect);
+        } else {
+            self.disconnect_requested = true;
+        }
+    }
+
+    /// Drop the pending action
+    pub fn cancel_confirm(&mut self) {
+        // A held-back message stays typed in for editing
+        self.mode = match self.pending_confirm.take() {
+            Some(ConfirmAction::Send { .. }) => Mode::Insert,
+            _ => Mode::Normal,
+        };
+    }
+
+    /// Whether the selected chat is a real Telegram chat, not the Welcome chat
+    /// (or nothing at all). Every action that would call the API checks this
+    pub fn is_real_chat(&self) -> bool {
+        self.current_chat_id()
+            .is_some_and(|id| id != WELCOME_CHAT_ID)
+    }
+
+    /// Current chat id, with a status message if it's the Welcome chat
+    fn current_real_chat_id(&mut self) -> Option<i64> {
+        if self.is_real_chat() {
+            self.current_chat_id()
+        } else {
+            self.status_message = Some("Open a chat first".to_string());
+            None
+        }
+    }
+
+    /// Reload the open chat's messages (r), or reconnect once the connection is lost
+    pub fn request_reload(&mut self) {
+        if self.connection == ConnectionState::Lost {
+            self.reconnect_requested = true;
+            self.connection = ConnectionState::Reconnecting;
+        } else if self.current_real_chat_id().is_some() {
+            self.reload_requested = true;
+        }
+    }
+
+    /// Copy the selected chat's t.me link, or ask for an invite link when it has no username
+    pub fn copy_chat_link(&mut self) {
+        let Some(chat_id) = self.current_real_chat_id() else {
+            return;
+        };
+        match self.chats[self.selected_chat].username.as_deref() {
+            Some(username) => self.copy_requested = Some(format!("https://t.me/{}", username)),
+            None => self.invite_link_requested = Some(chat_id),
+        }
+    }
+
+    /// Handle `:block` / `:unblock` for the current private chat, asking before blocking
+    fn request_block(&mut self, block: bool) {
+        if self.current_real_chat_id().is_none() {
+            return;
+        }
+        let chat = &self.chats[self.selected_chat];
+        if chat.is_group {
+            self.status_message = Some("Only users can be blocked".to_string());
+        } else if chat.is_blocked == block {
+            self.status_message = Some(format!(
+                "{} is {}",
+                chat.name,
+                if block {
+                    "already blocked"
+                } else {
+                    "not blocked"
+                }
+            ));
+        } else if block {
+            let action = ConfirmAction::Block {
+                id: chat.id,
+                name: chat.name.clone(),
+            };
+            self.ask_confirm(action);
+        } else {
+            self.block_requested = Some((chat.id, false));
+        }
+    }
+
+    /// Handle `:mute [duration]` for the current chat (no duration mutes forever)
+    fn request_mute(&mut self, duration: &str) {
+        let until = if duration.is_empty() {
+            MUTE_FOREVER
+        } else {
+            match parse_duration(duration) {
+                Some(secs) => (unix_now() + secs).min(MUTE_FOREVER),
+                None => {
+                    self.status_message = Some(format!(
+                        "Invalid duration: {} (use e.g. 30m, 8h, 1d)",
+                        duration
+                    ));
+                    return;
+                }
+            }
+        };
+        if let Some(chat_id) = self.current_real_chat_id() {
+            self.mute_requested = Some((chat_id, until));
+        }
+    }
+
+    /// Which incoming messages of a chat notify
+    pub fn notify_level(&self, chat_id: i64) -> NotifyLevel {
+        self.config
+            .notify_levels
+            .get(&chat_id)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Step the current chat to its next notification level and save it. None
+    /// also mutes the chat on Telegram, so other devices stay quiet too, and
+    /// leaving None lifts that mute; mentions only has no Telegram equivalent
+    pub fn cycle_notify_level(&mut self) {
+        let Some(chat_id) = self.current_real_chat_id() else {
+            return;
+        };
+        let old = self.notify_level(chat_id);
+        let level = old.next();
+        if level == NotifyLevel::All {
+            self.config.notify_levels.remove(&chat_id);
+        } else {
+            self.config.notify_levels.insert(chat_id, level);
+        }
+        if level == NotifyLevel::None {
+            self.mute_requested = Some((chat_id, MUTE_FOREVER));
+        } else if old == NotifyLevel::None {
+            self.mute_requested = Some((chat_id, 0));
+        }
+
+        let status = format!("Notifications: {}", level.label());
+        self.status_message = Some(match self.config.save() {
+            Ok(()) => status,
+            Err(e) => format!("{} (not saved: {})", status, e),
+        });
+    }
+
+    /// The selected message if it's still live, the target of per-message actions
+    pub fn target_message(&self) -> Option<&Message> {
+        self.selected_message().filter(|m| !m.deleted)
+    }
+
+    /// Pin the target message, or unpin it if it's already pinned
+    pub fn toggle_pin(&mut self) {
+        let Some(chat_id) = self.current_real_chat_id() else {
+            return;
+        };
+        let Some(message_id) = self.target_message().map(|m| m.id) else {
+            self.status_message = Some("No message to pin".to_string());
+            return;
+        };
+        let is_pinned = self.chats[self.selected_chat]
+            .pinned
+            .iter()
+            .any(|(id, _)| *id == message_id);
+        self.pin_requested = Some((chat_id, message_id, !is_pinned));
+    }
+
+    /// Handle `:msg <chat_id> <text>`: send to a chat by id, loaded or not
+    fn send_to_id(&mut self, args: &str) {
+        let usage = || Some("Usage: :msg <chat_id> <text>".to_string());
+        let Some((id, text)) = args.split_once(char::is_whitespace) else {
+            self.status_message = usage();
+            return;
+        };
+        let Ok(id) = id.parse::<i64>() else {
+            self.status_message = Some(format!("Invalid chat id: {}", id));
+            return;
+        };
+        if text.trim().is_empty() {
+            self.status_message = usage();
+            return;
+        }
+        self.send_to_id_requested = Some((id, text.trim().to_string()));
+    }
+
+    /// Select a loaded message of the open chat by id and scroll it into view.
+    /// Returns false if it isn't loaded (or has nothing to show)
+    pub fn jump_to_message(&mut self, message_id: i32) -> bool {
+        let shown = self.shown_messages();
+        let Some(index) = shown.iter().position(|m| m.id == message_id) else {
+            return false;
+        };
+        self.selected_message = shown.len() - 1 - index;
+        if self.selected_message == 0 {
+            self.new_below = 0;
+        }
+        self.panel = Panel::Chats;
+        true
+    }
+
+    /// Handle `:jump <message_id>`: select the message, fetching older history
+    /// back to it when it isn't loaded yet
+    fn jump_command(&mut self, args: &str) {
+        let Ok(message_id) = args.parse::<i32>() else {
+            self.status_message = Some("Usage: :jump <message_id>".to_string());
+            return;
+        };
+        self.jump_to_id(message_id);
+    }
+
+    /// Select a message of the open chat by id, fetching older history back to
+    /// it when it isn't loaded yet
+    fn jump_to_id(&mut self, message_id: i32) {
+        let Some(chat_id) = self.current_real_chat_id() else {
+            return;
+        };
+        if self.jump_to_message(message_id) {
+            return;
+        }
+        let Some(messages) = self.messages.get(&chat_id) else {
+            self.status_message = Some("Chat is still loading".to_string());
+            return;
+        };
+        match messages.first() {
+            // Older than everything loaded: worth asking the server
+            Some(oldest) if message_id < oldest.id => {
+                self.jump_requested = Some((chat_id, message_id, oldest.id));
+            }
+            _ => self.status_message = Some("Message not found".to_string()),
+        }
+    }
+
+    /// Select the pending jump target if its chat is open and loaded: directly when
+    /// loaded, by fetching older history when it's further back
+    fn apply_pending_jump(&mut self) {
+        let Some((chat_id, message_id)) = self.pending_jump else {
+            return;
+        };
+        if self.current_chat_id() != Some(chat_id) {
+            self.pending_jump = None;
+            return;
+        }
+        let Some(messages) = self.messages.get(&chat_id) else {
+            return; // Still loading, mark_loaded comes back here
+        };
+        let oldest = messages.first().map(|m| m.id);
+        self.pending_jump = None;
+        if self.jump_to_message(message_id) {
+            return;
+        }
+        if let Some(oldest) = oldest.filter(|&oldest| message_id < oldest) {
+            self.jump_requested = Some((chat_id, message_id, oldest));
+        }
+    }
+
+    /// Handle `:search <query>`: search messages in all chats
+    fn search_messages(&mut self, query: &str) {
+        if query.is_empty() {
+            self.status_message = Some("Usage: :search <text>".to_string());
+            return;
+        }
+        self.message_search_results.clear();
+        self.status_message = Some(format!("Searching for \"{}\"...", query));
+        self.message_search_requested = Some(query.to_string());
+    }
+
+    /// Keep the results of a :search (newest first) and open the first one
+    pub fn set_message_search_results(&mut self, query: &str, results: Vec<(i64, i32)>) {
+        self.message_search_results = results;
+        self.message_search_index = 0;
+        if self.message_search_results.is_empty() {
+            self.status_message = Some(format!("No messages found for \"{}\"", query));
+        } else {
+            self.message_search_query = Some(query.to_string());
+            self.open_search_result();
+        }
+    }
+
+    /// Step to the next (older) or previous (newer) :search result (:next/:prev, n/N)
+    pub fn step_search_result(&mut self, forward: bool) {
+        let count = self.message_search_results.len();
+        if count == 0 {
+            self.status_message = Some("No search results (use :search <text>)".to_string());
+            return;
+        }
+        // Wraps around at either end, like vim's search
+        self.message_search_index = if forward {
+            (self.message_search_index + 1) % count
+        } else {
+            (self.message_search_index + count - 1) % count
+        };
+        self.open_search_result();
+    }
+
+    /// Open the chat of the current :search result and select the message
+    fn open_search_result(&mut self) {
+        let (chat_id, message_id) = self.message_search_results[self.message_search_index];
+        let status = format!(
+            "result {}/{}",
+            self.message_search_index + 1,
+            self.message_search_results.len()
+        );
+        let Some(index) = self.chats.iter().position(|c| c.id == chat_id) else {
+            self.status_message = Some(format!("{} (chat not in your list)", status));
+            return;
+        };
+        if self.current_chat_id() != Some(chat_id) {
+            self.select_chat(index);
+        }
+        self.status_message = Some(status);
+        self.pending_jump = Some((chat_id, message_id));
+        self.apply_pending_jump();
+    }
+
+    /// Loaded messages containing `query` (case-insensitive), newest first
+    pub fn search_loaded(&self, query: &str) -> Vec<(i64, i32)> {
+        let query = query.to_lowercase();
+        let mut results: Vec<(i64, i32, i64)> = self
+            .messages
+            .iter()
+            .flat_map(|(chat_id, msgs)| {
+                msgs.iter()
+                    .filter(|m| !m.deleted && m.text.to_lowercase().contains(&query))
+                    .map(move |m| (*chat_id, m.id, m.date))
+            })
+            .collect();
+        results.sort_by_key(|&(_, _, date)| std::cmp::Reverse(date));
+        results
+            .into_iter()
+            .map(|(chat, id, _)| (chat, id))
+            .collect()
+    }
+
+    /// Handle `:download [path]`: save the target message's media, by default
+    /// into the downloads folder under its own name
+    fn download_target(&mut self, path: &str) {
+        let Some(chat_id) = self.current_real_chat_id() else {
+            return;
+        };
+        let Some(msg) = self.target_message() else {
+            self.status_message = Some("No message selected".to_string());
+            return;
+        };
+        if msg.media.is_none() {
+            self.status_message = Some("This message has no media to download".to_string());
+            return;
+        }
+        let path = (!path.is_empty()).then(|| crate::export::expand_path(path));
+        self.download_requested = Some((chat_id, msg.id, path));
+    }
+
+    /// Replace a chat's pinned messages with what the server reported
+    pub fn set_pinned(&mut self, chat_id: i64, pinned: Vec<(i32, String)>) {
+        if let Some(chat) = self.chats.iter_mut().find(|c| c.id == chat_id) {
+            chat.pinned = pinned;
+            self.messages_revision += 1;
+        }
+    }
+
+    /// Reflect a successful pin/unpin in the cached pinned list
+    pub fn pin_applied(&mut self, chat_id: i64, message_id: i32, pinned: bool) {
+        let text = self
+            .messages
+            .get(&chat_id)
+            .and_then(|msgs| msgs.iter().find(|m| m.id == message_id))
+            .map(|m| m.text.clone())
+            .unwrap_or_default();
+        if let Some(chat) = self.chats.iter_mut().find(|c| c.id == chat_id) {
+            chat.pinned.retain(|(id, _)| *id != message_id);
+            if pinned {
+                chat.pinned.insert(0, (message_id, text));
+                self.pinned_index = 0;
+            }
+            self.messages_revision += 1;
+        }
+    }
+
+    /// Show the next pinned message in the banner
+    pub fn cycle_pinned(&mut self) {
+        self.pinned_index = self.pinned_index.wrapping_add(1);
+    }
+
+    /// Handle `:export <path> [--json]` for the loaded messages of the current chat
+    fn export_current_chat(&mut self, args: &str) {
+        let json = args.split_whitespace().any(|a| a == "--json");
+        let path: Vec<&str> = args.split_whitespace().filter(|a| *a != "--json").collect();
+        if path.is_empty() {
+            self.status_message = Some("Usage: :export <path> [--json]".to_string());
+            return;
+        }
+        if self.current_real_chat_id().is_none() {
+            return;
+        }
+
+        let path = crate::export::expand_path(&path.join(" "));
+        let chat_name = self.chats[self.selected_chat].name.clone();
+        let messages: Vec<Message> = self.current_messages().into_iter().cloned().collect();
+        self.status_message = Some(
+            match crate::export::export_chat(&path, &chat_name, &messages, json, &self.config) {
+                Ok(()) => format!("Exported {} messages to {}", messages.len(), path.display()),
+                Err(e) => format!("Export failed: {}", e),
+            },
+        );
+    }
+
+    /// Record a chat's mute expiry (None or a past time means unmuted)
+    pub fn set_chat_muted(&mut self, chat_id: i64, until: Option<i64>) {
+        if let Some(chat) = self.chats.iter_mut().find(|c| c.id == chat_id) {
+            chat.muted_until = until.filter(|&t| t > unix_now());
+        }
+    }
+
+    /// Remember a chat's public username for :goto
+    pub fn set_chat_username(&mut self, chat_id: i64, username: Option<&str>) {
+        if let Some(chat) = self.chats.iter_mut().find(|c| c.id == chat_id) {
+            chat.username = username.map(str::to_string);
+        }
+    }
+
+    /// Remember how many members a group or channel has
+    pub fn set_chat_members(&mut self, chat_id: i64, members: u32) {
+        if let Some(chat) = self.chats.iter_mut().find(|c| c.id == chat_id) {
+            chat.members = Some(members);
+        }
+    }
+
+    /// Member count of a chat, if it's a group or channel and known
+    pub fn chat_members(&self, chat_id: i64) -> Option<u32> {
+        self.chats
+            .iter()
+            .find(|c| c.id == chat_id)
+            .and_then(|c| c.members)
+    }
+
+    /// Flag a chat as a group, so incoming messages show their sender's name
+    pub fn set_chat_group(&mut self, chat_id: i64, group: bool) {
+        if let Some(chat) = self.chats.iter_mut().find(|c| c.id == chat_id) {
+            chat.is_group = group;
+        }
+    }
+
+    /// Flag a chat as a broadcast channel
+    pub fn set_chat_channel(&mut self, chat_id: i64, channel: bool) {
+        if let Some(chat) = self.chats.iter_mut().find(|c| c.id == chat_id) {
+            chat.is_channel = channel;
+        }
+    }
+
+    /// Flag a chat as a private chat with a bot
+    pub fn set_chat_bot(&mut self, chat_id: i64, bot: bool) {
+        if let Some(chat) = self.chats.iter_mut().find(|c| c.id == chat_id) {
+            chat.is_bot = bot;
+        }
+    }
+
+    /// Update a chat's name after it was renamed (blank names are ignored)
+    pub fn rename_chat(&mut self, chat_id: i64, name: &str) {
+        if name.trim().is_empty() {
+            return;
+        }
+        if let Some(chat) = self.chats.iter_mut().find(|c| c.id == chat_id) {
+            chat.name = name.to_string();
+        }
+    }
+
+    /// Set a chat's preview from the dialog list, unless its messages are
+    /// loaded (add_message keeps the preview of those up to date)
+    pub fn set_chat_preview(&mut self, chat_id: i64, text: String, outgoing: bool) {
+        if self.messages.get(&chat_id).is_some_and(|m| !m.is_empty()) {
+            return;
+        }
+        if let Some(chat) = self.chats.iter_mut().find(|c| c.id == chat_id) {
+            chat.last_message = Some(text);
+            chat.last_outgoing = outgoing;
+        }
+    }
+
+    /// Flag a chat as a user this account has blocked
+    pub fn set_chat_blocked(&mut self, chat_id: i64, blocked: bool) {
+        if let Some(chat) = self.chats.iter_mut().find(|c| c.id == chat_id) {
+            chat.is_blocked = blocked;
+        }
+    }
+
+    /// Record a chat's slow mode delay (0 when it has none)
+    pub fn set_slow_mode(&mut self, chat_id: i64, secs: u32) {
+        if let Some(chat) = self.chats.iter_mut().find(|c| c.id == chat_id) {
+            chat.slow_mode_secs = Some(secs);
+        }
+    }
+
+    /// Slow mode delay of a chat, None if it hasn't been checked yet
+    pub fn slow_mode_secs(&self, chat_id: i64) -> Option<u32> {
+        self.chats
+            .iter()
+            .find(|c| c.id == chat_id)
+            .and_then(|c| c.slow_mode_secs)
+    }
+
+    /// Start a chat's slow mode cooldown, `secs` from now
+    pub fn start_slow_mode(&mut self, chat_id: i64, secs: u32) {
+        if secs > 0 {
+            let until = Instant::now() + Duration::from_secs(secs as u64);
+            self.slow_mode_until.insert(chat_id, until);
+        }
+    }
+
+    /// Whole seconds left before the open chat accepts another message
+    pub fn slow_mode_remaining(&self) -> Option<u64> {
+        let until = self.slow_mode_until.get(&self.current_chat_id()?)?;
+        let left = until.saturating_duration_since(Instant::now());
+        (!left.is_zero()).then(|| left.as_secs() + u64::from(left.subsec_nanos() > 0))
+    }
+
+    /// Flag a chat as a forum (a supergroup split into topics)
+    pub fn set_chat_forum(&mut self, chat_id: i64, forum: bool) {
+        if let Some(chat) = self.chats.iter_mut().find(|c| c.id == chat_id) {
+            chat.is_forum = forum;
+        }
+    }
+
+    /// Replace a forum's topics with what the server reported
+    pub fn set_topics(&mut self, chat_id: i64, topics: Vec<(i32, String)>) {
+        if let Some(chat) = self.chats.iter_mut().find(|c| c.id == chat_id) {
+            chat.topics = topics;
+        }
+    }
+
+    /// Close the :whoami overlay
+    pub fn exit_whoami(&mut self) {
+        self.mode = Mode::Normal;
+    }
+
+    /// Messages held in memory across all chats
+    pub fn cached_message_count(&self) -> usize {
+        self.messages.values().map(Vec::len).sum()
+    }
+
+    /// Display name of the active account, if it's in the registry
+    pub fn current_account_name(&self) -> Option<&str> {
+        self.account_names
+            .iter()
+            .find(|(id, _)| *id == self.current_account_id)
+            .map(|(_, name)| name.as_str())
+    }
+
+    /// Open the scratchpad overlay
+    pub fn enter_scratchpad(&mut self) {
+        self.mode = Mode::Scratchpad;
+    }
+
+    /// Close the scratchpad, saving the notes to disk
+    pub fn exit_scratchpad(&mut self) {
+        self.mode = Mode::Normal;
+        if let Err(e) = crate::scratchpad::save(&self.scratchpad) {
+            self.status_message = Some(format!("Scratchpad not saved: {}", e));
+        }
+    }
+
+    /// Apply a `:set key=value` option live and persist it to config.json
+    fn set_option(&mut self, option: &str) {
+        let Some((key, raw_value)) = option.split_once('=') else {
+            self.status_message = Some("Usage: :set <option>=<value>".to_string());
+            return;
+        };
+        let key = key.trim().to_lowercase();
+        let raw_value = raw_value.trim();
+        if let Err(e) = self.apply_option(&key, &raw_value.to_lowercase(), raw_value) {
+            self.status_message = Some(e);
+            return;
+        }
+
+        self.status_message = Some(match self.config.save() {
+            Ok(()) => format!("{} set", key),
+            Err(e) => format!("{} set (not saved: {})", key, e),
+        });
+    }
+
+    /// Set one config option from its lowercased `value` (`raw_value` where case matters)
+    fn apply_option(&mut self, key: &str, value: &str, raw_value: &str) -> Result<(), String> {
+        let config = &mut self.config;
+        match key {
+            "bubble_width_pct" => {
+                config.bubble_width_pct = parse_num::<u16>(value, "percentage")?.clamp(40, 90)
+            }
+            "unread_style" => {
+                config.unread_style = parse_enum(key, value, "count, badge, bullet, hidden")?
             }
-        } else if cmd_lower == "ai" {
-            // Enter AI command mode empty
-            self.enter_ai_command();
-        } else if cmd_lower.starts_with("code ") {
-            // Enter code mode with query
-            let query = cmd
-                .strip_prefix("code ")
-                .or_else(|| cmd.strip_prefix("CODE "))
-                .unwrap_or("");
-            self.code_input = query.to_string();
-            self.mode = Mode::Code;
-            if !query.is_empty() {
-                self.submit_code_query();
+            // Case matters here, %M and %m differ
+            "time_format" => {
+                crate::config::validate_time_format(raw_value)?;
+                config.time_format = raw_value.to_string();
             }
-        } else if cmd_lower == "code" {
-            // Enter code mode empty
-            self.enter_code_mode();
-        } else if cmd_lower == "q" || cmd_lower == "quit" {
-            self.should_quit = true;
+            "time_zone" => config.time_zone = parse_enum(key, value, "local, utc")?,
+            "auto_scroll" => config.auto_scroll = parse_bool(value)?,
+            "group_window_secs" => {
+                config.group_window_secs = parse_num(value, "number of seconds")?
+            }
+            "refresh_on_focus" => config.refresh_on_focus = parse_bool(value)?,
+            "load_more_batch" => {
+                config.load_more_batch = parse_num::<usize>(value, "number")?.clamp(1, 1000)
+            }
+            "max_messages_per_chat" => config.max_messages_per_chat = parse_num(value, "number")?,
+            "scroll_on_send" => config.scroll_on_send = parse_bool(value)?,
+            "exit_insert_on_send" => config.exit_insert_on_send = parse_bool(value)?,
+            "reconnect_attempts" => config.reconnect_attempts = parse_num(value, "number")?,
+            "natural_scroll" => config.natural_scroll = parse_bool(value)?,
+            "wrap_navigation" => config.wrap_navigation = parse_bool(value)?,
+            "send_on" => config.send_on = parse_enum(key, value, "enter, ctrl-enter")?,
+            "border_style" => {
+                config.border_style = parse_enum(key, value, "rounded, plain, double, thick")?
+            }
+            "ascii_only" => {
+                config.ascii_only = match value {
+                    "auto" => None,
+                    other => Some(other.parse().map_err(|_| {
+                        format!("Invalid ascii_only: {} (true, false, auto)", value)
+                    })?),
+                }
+            }
+            "cursor_shapes" => config.cursor_shapes = parse_bool(value)?,
+            "normal_cursor" | "insert_cursor" => {
+                let shape = parse_enum(key, value, "block, bar, underline, blinking-block, ...")?;
+                if key == "normal_cursor" {
+                    config.normal_cursor = shape;
+                } else {
+                    config.insert_cursor = shape;
+                }
+            }
+            "max_name_width" => config.max_name_width = parse_num(value, "width")?,
+            "compact_width" => config.compact_width = parse_num(value, "width")?,
+            "outer_frame" => config.outer_frame = parse_bool(value)?,
+            "full_width_input" => config.full_width_input = parse_bool(value)?,
+            "truncate_long_words" => config.truncate_long_words = parse_bool(value)?,
+            "mention_bell" => config.mention_bell = parse_bool(value)?,
+            "sound_on_send" => config.sound_on_send = parse_bool(value)?,
+            "sound_on_receive" => config.sound_on_receive = parse_bool(value)?,
+            // Case matters in paths
+            "sound_file" => config.sound_file = raw_value.to_string(),
+            "markdown" => config.markdown = parse_bool(value)?,
+            "lock_after_mins" => config.lock_after_mins = parse_num(value, "number of minutes")?,
+            "lock_passphrase" => config.lock_passphrase = parse_bool(value)?,
+            "confirm_disconnect" => config.confirm_disconnect = parse_bool(value)?,
+            "confirm_send_members" => config.confirm_send_members = parse_num(value, "number")?,
+            "compact" => config.compact = parse_bool(value)?,
+            "chat_preview" => config.chat_preview = parse_bool(value)?,
+            "unread_bold" => config.unread_bold = parse_bool(value)?,
+            _ => return Err(format!("Unknown option: {}", key)),
         }
-        // Clear command input after execution
-        self.command_input.clear();
+        Ok(())
     }
 
     // ==================== FindUser Mode Methods ====================
 
+    /// Open a known chat by username or name (case-insensitive) without a
+    /// network call, falling back to :find when nothing in the list matches
+    fn goto_chat(&mut self, target: &str) {
+        if target.is_empty() {
+            self.status_message = Some("Usage: :goto @username or :goto name".to_string());
+            return;
+        }
+        let target_lower = target.to_lowercase();
+        let real_chats = || {
+            self.chats
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| c.id != WELCOME_CHAT_ID)
+        };
+        let found = real_chats()
+            .find(|(_, c)| {
+                c.username
+                    .as_deref()
+                    .is_some_and(|u| u.to_lowercase() == target_lower)
+            })
+            .or_else(|| real_chats().find(|(_, c)| c.name.to_lowercase() == target_lower))
+            .or_else(|| real_chats().find(|(_, c)| c.name.to_lowercase().contains(&target_lower)))
+            .map(|(index, _)| index);
+
+        match found {
+            Some(index) => {
+                // Show it even if the active folder or filter doesn't hold it
+                if !self.folder_chat_indices().contains(&index) {
+                    self.active_folder = 0;
+                    self.chat_filter = None;
+                }
+                self.select_chat(index);
+            }
+            None => {
+                self.find_input = target.to_string();
+                self.find_result = Some(FindResult::Searching);
+                self.find_requested = Some(target.to_string());
+                self.mode = Mode::FindUser;
+            }
+        }
+    }
+
     /// Exit find user mode
     pub fn exit_find(&mut self) {
         self.mode = Mode::Normal;
@@ -462,7 +3412,9 @@ impl App {
             // Find the chat in our list and navigate to it
             if let Some(index) = self.chats.iter().position(|c| c.id == *id) {
                 self.selected_chat = index;
-                self.scroll_offset = 0;
+                self.selected_message = 0;
+                self.scroll_offset.set(0);
+                self.new_below = 0;
                 self.needs_message_load = true;
                 self.clear_current_unread();
             }
@@ -510,6 +3462,7 @@ impl App {
     }
 
     /// Request smart reply generation
+    #[allow(dead_code)] // Not wired up to the UI yet
     pub fn request_smart_reply(&mut self, tone: Option<String>) {
         self.ai_request = Some(AIRequest::Reply(tone));
         self.ai_status = Some("✍️ Generating reply...".to_string());
@@ -546,6 +3499,7 @@ impl App {
     }
 
     /// Get chat context for smart reply (last N messages)
+    #[allow(dead_code)] // Not wired up to the UI yet
     pub fn get_chat_context(&self, max_messages: usize) -> String {
         let messages = self.current_messages();
         let start = messages.len().saturating_sub(max_messages);
@@ -562,3 +3516,743 @@ impl App {
             .join("\n")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app_with_chat(id: i64, name: &str, group: bool) -> App {
+        let mut app = App::new();
+        app.add_chat(id, name.to_string());
+        app.set_chat_group(id, group);
+        app
+    }
+
+    fn last_display_name(app: &App, chat_id: i64) -> Option<String> {
+        app.messages[&chat_id].last().unwrap().display_name.clone()
+    }
+
+    #[test]
+    fn dm_hides_sender_name() {
+        let mut app = app_with_chat(10, "Alice", false);
+        app.add_message(10, 1, "Alice".to_string(), "hi".to_string(), false, 0);
+        assert_eq!(last_display_name(&app, 10), None);
+    }
+
+    #[test]
+    fn group_shows_sender_name() {
+        let mut app = app_with_chat(20, "Rustaceans", true);
+        app.add_message(20, 1, "Bob".to_string(), "hi".to_string(), false, 0);
+        assert_eq!(last_display_name(&app, 20), Some("Bob".to_string()));
+    }
+
+    #[test]
+    fn group_member_named_like_the_group_keeps_their_name() {
+        let mut app = app_with_chat(30, "Book Club", true);
+        app.add_message(30, 1, "Book Club".to_string(), "hi".to_string(), false, 0);
+        assert_eq!(last_display_name(&app, 30), Some("Book Club".to_string()));
+    }
+
+    #[test]
+    fn sent_messages_are_from_you() {
+        let sender = Some((7, "Alice"));
+        assert_eq!(
+            resolve_sender(true, false, sender, 30, "Book Club", true),
+            "You"
+        );
+    }
+
+    #[test]
+    fn channel_posts_are_from_the_channel() {
+        assert_eq!(
+            resolve_sender(false, true, None, 50, "Release Notes", false),
+            "Release Notes"
+        );
+        // Signed posts too
+        let admin = Some((7, "Alice"));
+        assert_eq!(
+            resolve_sender(false, true, admin, 50, "Release Notes", false),
+            "Release Notes"
+        );
+    }
+
+    #[test]
+    fn anonymous_group_admins_show_as_admin() {
+        let as_group = Some((30, "Book Club"));
+        assert_eq!(
+            resolve_sender(false, false, as_group, 30, "Book Club", true),
+            "Admin"
+        );
+    }
+
+    #[test]
+    fn nameless_group_senders_are_deleted_accounts() {
+        let deleted = Some((7, " "));
+        assert_eq!(
+            resolve_sender(false, false, deleted, 30, "Book Club", true),
+            "Deleted Account"
+        );
+        let member = Some((8, "Bob"));
+        assert_eq!(
+            resolve_sender(false, false, member, 30, "Book Club", true),
+            "Bob"
+        );
+    }
+
+    #[test]
+    fn direct_messages_without_a_sender_are_from_the_chat() {
+        assert_eq!(
+            resolve_sender(false, false, None, 10, "Alice", false),
+            "Alice"
+        );
+        let nameless = Some((10, ""));
+        assert_eq!(
+            resolve_sender(false, false, nameless, 10, "Alice", false),
+            "Alice"
+        );
+        // The DM partner's id is the chat's, and that's no anonymous admin
+        let alice = Some((10, "Alice"));
+        assert_eq!(
+            resolve_sender(false, false, alice, 10, "Alice", false),
+            "Alice"
+        );
+    }
+
+    #[test]
+    fn long_names_are_not_truncated() {
+        let name = "Maximilian Alexander von Hohenberg";
+        let mut app = app_with_chat(40, "Family", true);
+        app.add_message(40, 1, name.to_string(), "hi".to_string(), false, 0);
+        assert_eq!(last_display_name(&app, 40), Some(name.to_string()));
+    }
+
+    #[test]
+    fn adding_a_chat_twice_keeps_one_entry() {
+        let mut app = app_with_chat(10, "Alice", false);
+        app.add_chat(10, "Alice (renamed)".to_string());
+        assert_eq!(app.chats.len(), 1);
+        assert_eq!(app.chats[0].name, "Alice");
+    }
+
+    #[test]
+    fn only_incoming_messages_count_as_unread() {
+        let mut app = app_with_chat(10, "Alice", false);
+        app.add_message(10, 1, "Alice".to_string(), "hi".to_string(), false, 0);
+        app.add_message(10, 2, "You".to_string(), "hey".to_string(), true, 0);
+        app.add_message(
+            10,
+            3,
+            "Alice".to_string(),
+            "how are you".to_string(),
+            false,
+            0,
+        );
+        assert_eq!(app.chats[0].unread, 2);
+        assert_eq!(app.chats[0].last_message.as_deref(), Some("how are you"));
+    }
+
+    #[test]
+    fn messages_over_the_cap_drop_the_oldest() {
+        let mut app = app_with_chat(10, "Alice", false);
+        app.config.max_messages_per_chat = 3;
+        app.select_chat(0);
+        app.panel = Panel::Chats;
+        for id in 1..=3 {
+            app.add_message(10, id, "Alice".to_string(), format!("m{}", id), false, 0);
+        }
+        app.jump_to_oldest();
+        assert_eq!(app.selected_message, 2);
+
+        // The message under the cursor is kept while it's being read
+        let ids = |app: &App| app.messages[&10].iter().map(|m| m.id).collect::<Vec<_>>();
+        app.add_message(10, 4, "Alice".to_string(), "m4".to_string(), false, 0);
+        app.add_message(10, 5, "Alice".to_string(), "m5".to_string(), false, 0);
+        assert_eq!(ids(&app), [1, 2, 3, 4, 5]);
+        assert_eq!(app.selected_message().map(|m| m.id), Some(1));
+
+        app.jump_to_latest();
+        app.add_message(10, 6, "Alice".to_string(), "m6".to_string(), false, 0);
+        assert_eq!(ids(&app), [4, 5, 6]);
+        assert_eq!(app.chats[0].unread, 6);
+    }
+
+    #[test]
+    fn removing_newer_messages_keeps_the_cursor_and_preview() {
+        let mut app = app_with_chat(10, "Alice", false);
+        app.config.deleted_messages = DeletedMessages::Remove;
+        app.select_chat(0);
+        app.panel = Panel::Chats;
+        for id in 1..=5 {
+            app.add_message(10, id, "Alice".to_string(), format!("m{}", id), false, 0);
+        }
+        app.add_message(10, 6, "Alice".to_string(), String::new(), false, 0);
+        app.set_media(10, 6, "Photo".to_string());
+        app.set_media_kind(10, 6, MediaKind::Photo);
+        app.selected_message = 3; // On m3
+        assert_eq!(app.selected_message().map(|m| m.id), Some(3));
+
+        app.delete_messages(&[10], &[4, 5]);
+        assert_eq!(app.selected_message().map(|m| m.id), Some(3));
+        assert_eq!(app.chats[0].last_message.as_deref(), Some("🖼 Photo"));
+
+        // Deleting what's under the cursor moves it to the next older one
+        app.delete_messages(&[10], &[3]);
+        assert_eq!(app.selected_message().map(|m| m.id), Some(2));
+        app.delete_messages(&[10], &[6]);
+        assert_eq!(app.chats[0].last_message.as_deref(), Some("m2"));
+    }
+
+    #[test]
+    fn older_messages_over_the_cap_drop_the_far_end() {
+        let mut app = app_with_chat(10, "Alice", false);
+        app.config.max_messages_per_chat = 3;
+        app.select_chat(0);
+        app.panel = Panel::Chats;
+        for id in 5..=7 {
+            app.add_message(10, id, "Alice".to_string(), format!("m{}", id), false, 0);
+        }
+        let ids = |app: &App| app.messages[&10].iter().map(|m| m.id).collect::<Vec<_>>();
+
+        // Reading back from the oldest, the newest ones go
+        app.jump_to_oldest();
+        app.add_older_message(10, 4, "Alice".to_string(), "m4".to_string(), false, 0);
+        app.add_older_message(10, 3, "Alice".to_string(), "m3".to_string(), false, 0);
+        assert_eq!(ids(&app), [3, 4, 5]);
+        assert_eq!(app.selected_message().map(|m| m.id), Some(5));
+
+        // and come back with a reload once the cursor is at the bottom again
+        assert!(!app.reload_requested);
+        app.jump_to_latest();
+        assert!(app.reload_requested);
+
+        // With the cursor on the newest message, the oldest ones go
+        app.clear_messages(10);
+        for id in 5..=7 {
+            app.add_message(10, id, "Alice".to_string(), format!("m{}", id), false, 0);
+        }
+        app.add_older_message(10, 8, "Alice".to_string(), "m8".to_string(), false, 0);
+        assert_eq!(ids(&app), [6, 7, 8]);
+    }
+
+    #[test]
+    fn server_read_mark_decides_what_is_unread() {
+        let mut app = App::new();
+        app.add_chat(WELCOME_CHAT_ID, "Welcome".to_string());
+        app.add_chat(10, "Alice".to_string());
+        app.set_read_state(10, 2, 1, 0);
+        assert_eq!(app.chats[1].unread, 1);
+
+        // History already read on another device doesn't count again
+        app.clear_messages(10);
+        app.chats[1].unread = 0;
+        for id in 1..=4 {
+            app.add_message(10, id, "Alice".to_string(), format!("m{}", id), false, 0);
+        }
+        assert_eq!(app.chats[1].unread, 2);
+
+        // Opening the chat reads everything loaded, so a reload counts nothing
+        app.select_chat(1);
+        app.mark_loaded(10);
+        assert_eq!(app.chats[1].read_inbox_max_id, 4);
+        app.select_chat(0);
+        app.clear_messages(10);
+        for id in 1..=4 {
+            app.add_message(10, id, "Alice".to_string(), format!("m{}", id), false, 0);
+        }
+        assert_eq!(app.chats[1].unread, 0);
+    }
+
+    #[test]
+    fn search_filter_matches_names_case_insensitively() {
+        let mut app = App::new();
+        for (id, name) in [(1, "Alice"), (2, "Bob"), (3, "Malice Group")] {
+            app.add_chat(id, name.to_string());
+        }
+        app.enter_search();
+        app.search_input = "ALI".to_string();
+        app.update_search_filter();
+        assert_eq!(app.filtered_chat_indices, vec![0, 2]);
+
+        app.search_input = "nobody".to_string();
+        app.update_search_filter();
+        assert!(app.filtered_chat_indices.is_empty());
+
+        app.search_input.clear();
+        app.update_search_filter();
+        assert_eq!(app.filtered_chat_indices, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn account_picker_reaches_the_add_account_slot() {
+        let mut app = App::new();
+        app.set_account_info(
+            "b".to_string(),
+            vec![
+                ("a".to_string(), "Work".to_string()),
+                ("b".to_string(), "Personal".to_string()),
+            ],
+        );
+        app.enter_account_picker();
+        assert_eq!(app.account_picker_selected, 1); // Starts on the active account
+
+        // One past the accounts is "+ Add Account", and it stops there
+        app.account_picker_move_down();
+        app.account_picker_move_down();
+        assert_eq!(app.account_picker_selected, 2);
+        app.select_account();
+        assert!(app.add_account_requested);
+        assert_eq!(app.switch_account_requested, None);
+
+        app.enter_account_picker();
+        for _ in 0..3 {
+            app.account_picker_move_up();
+        }
+        assert_eq!(app.account_picker_selected, 0);
+        app.select_account();
+        assert_eq!(app.switch_account_requested.as_deref(), Some("a"));
+    }
+
+    #[test]
+    fn welcome_chat_is_not_a_real_chat() {
+        let mut app = App::new();
+        assert!(!app.is_real_chat()); // No chats at all
+
+        app.add_chat(WELCOME_CHAT_ID, "Welcome".to_string());
+        app.add_chat(10, "Alice".to_string());
+        assert!(!app.is_real_chat());
+        app.select_chat(1);
+        assert!(app.is_real_chat());
+    }
+
+    #[test]
+    fn load_more_stops_at_the_start_of_the_chat() {
+        let mut app = app_with_chat(10, "Alice", false);
+        app.add_message(10, 40, "Alice".to_string(), "hi".to_string(), false, 0);
+        app.add_message(10, 41, "Alice".to_string(), "again".to_string(), false, 0);
+        app.config.load_more_batch = 20;
+
+        app.request_load_more();
+        assert_eq!(app.load_more_requested, Some((10, 40, 20)));
+
+        // Not again while that batch is on its way
+        app.load_more_requested = None;
+        app.request_load_more();
+        assert_eq!(app.load_more_requested, None);
+        app.note_load_more_failed(10, None);
+        assert_eq!(app.status_message, None);
+        app.request_load_more();
+        assert!(app.load_more_requested.is_some());
+
+        // A full batch may have more behind it, a short one is the last
+        app.note_loaded_more(10, 20, 20);
+        app.load_more_requested = None;
+        app.request_load_more();
+        assert!(app.load_more_requested.is_some());
+        app.note_loaded_more(10, 20, 7);
+        app.load_more_requested = None;
+        app.request_load_more();
+        assert_eq!(app.load_more_requested, None);
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("Already at the start of the chat")
+        );
+    }
+
+    #[test]
+    fn number_jump_opens_once_the_number_is_complete() {
+        let mut app = App::new();
+        for id in 1..=12 {
+            app.add_chat(100 + id, format!("Chat {}", id));
+        }
+
+        // 1 could still become 10-12, so it waits for another digit
+        app.enter_number_jump();
+        app.number_jump_push('1');
+        assert_eq!(app.mode, Mode::NumberJump);
+        app.number_jump_push('1');
+        assert_eq!(app.mode, Mode::Normal);
+        assert_eq!(app.current_chat_id(), Some(111));
+
+        // 3 can't be the start of another number on screen
+        app.enter_number_jump();
+        app.number_jump_push('0');
+        app.number_jump_push('3');
+        assert_eq!(app.current_chat_id(), Some(103));
+
+        // Enter takes the number as typed, Esc leaves the chat alone
+        app.enter_number_jump();
+        app.number_jump_push('1');
+        app.confirm_number_jump();
+        assert_eq!(app.current_chat_id(), Some(101));
+        app.enter_number_jump();
+        app.number_jump_push('1');
+        app.exit_number_jump();
+        assert_eq!(app.current_chat_id(), Some(101));
+    }
+
+    #[test]
+    fn notify_level_decides_which_messages_sound() {
+        let mut app = app_with_chat(10, "Team", true);
+        app.config.sound_on_receive = true;
+
+        let sounds = |app: &mut App, mentioned: bool| {
+            app.sound_requested = false;
+            app.bell_requested = false;
+            if mentioned {
+                app.note_mention(10);
+            }
+            app.note_received(10, mentioned);
+            (app.sound_requested, app.bell_requested)
+        };
+        assert_eq!(sounds(&mut app, false), (true, false));
+
+        app.config.notify_levels.insert(10, NotifyLevel::Mentions);
+        assert_eq!(sounds(&mut app, false), (false, false));
+        assert_eq!(sounds(&mut app, true), (true, true));
+
+        app.config.notify_levels.insert(10, NotifyLevel::None);
+        assert_eq!(sounds(&mut app, true), (false, false));
+        assert_eq!(app.notify_level(11), NotifyLevel::All);
+    }
+
+    #[test]
+    fn per_message_actions_no_op_on_the_welcome_chat() {
+        let mut app = app_with_chat(WELCOME_CHAT_ID, "Welcome", false);
+        app.enter_insert();
+        app.reply_to_latest();
+        app.request_paste();
+        app.request_reload();
+        app.toggle_pin();
+        app.copy_chat_link();
+        app.run_command("mute 1h");
+        app.run_command("download");
+        app.run_command("jump 5");
+
+        assert_eq!(app.mode, Mode::Normal);
+        assert_eq!(app.reply_to, None);
+        assert!(!app.paste_requested);
+        assert!(!app.reload_requested);
+        assert_eq!(app.pin_requested, None);
+        assert_eq!(app.copy_requested, None);
+        assert_eq!(app.invite_link_requested, None);
+        assert_eq!(app.mute_requested, None);
+        assert_eq!(app.download_requested, None);
+        assert_eq!(app.jump_requested, None);
+        assert_eq!(app.status_message.as_deref(), Some("Open a chat first"));
+    }
+
+    #[test]
+    fn pinned_list_jumps_to_the_chosen_pin() {
+        let mut app = app_with_chat(10, "Alice", false);
+        app.open_pins(10);
+        assert_eq!(app.mode, Mode::Normal);
+        assert_eq!(app.status_message.as_deref(), Some("No pinned messages"));
+
+        for id in 5..=8 {
+            app.add_message(10, id, "Alice".to_string(), format!("m{}", id), false, 0);
+        }
+        app.set_pinned(10, vec![(7, "m7".to_string()), (3, "m3".to_string())]);
+        app.open_pins(10);
+        assert_eq!(app.mode, Mode::PinList);
+
+        // Loaded: selected right away
+        app.select_pin();
+        assert_eq!(app.mode, Mode::Normal);
+        assert_eq!(app.selected_message().map(|m| m.id), Some(7));
+
+        // Older than what's loaded: fetched back to it
+        app.open_pins(10);
+        app.pins_move_down();
+        app.pins_move_down();
+        app.select_pin();
+        assert_eq!(app.jump_requested, Some((10, 3, 5)));
+    }
+
+    #[test]
+    fn visual_mode_copies_the_selected_range() {
+        let mut app = app_with_chat(10, "Alice", false);
+        for id in 1..=4 {
+            let outgoing = id == 2;
+            let sender = if outgoing { "You" } else { "Alice" };
+            app.add_message(10, id, sender.to_string(), format!("m{}", id), outgoing, 0);
+        }
+        app.enter_visual();
+        assert_eq!(app.mode, Mode::Normal, "only from the chat panel");
+
+        app.panel = Panel::Chats;
+        app.move_up();
+        app.enter_visual();
+        app.move_up();
+        app.move_up();
+        assert_eq!(app.visual_range(), Some((0, 2)));
+
+        app.yank_visual();
+        assert_eq!(app.mode, Mode::Normal);
+        let copied = app.copy_requested.take().unwrap();
+        let lines: Vec<_> = copied.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].ends_with("] Alice: m1"), "{}", lines[0]);
+        assert!(lines[1].ends_with("] You: m2"), "{}", lines[1]);
+        assert!(lines[2].ends_with("] Alice: m3"), "{}", lines[2]);
+    }
+
+    #[test]
+    fn quoting_picks_words_of_one_message_for_the_reply() {
+        let mut app = app_with_chat(10, "Alice", false);
+        app.add_message(
+            10,
+            1,
+            "Alice".to_string(),
+            "👋 hi there friend".to_string(),
+            false,
+            0,
+        );
+        app.add_message(10, 2, "Alice".to_string(), "later".to_string(), false, 0);
+        app.panel = Panel::Chats;
+        app.enter_visual();
+        app.move_up();
+        app.start_quote();
+        assert_eq!(app.mode, Mode::Visual, "two messages selected");
+
+        app.move_down();
+        app.move_up();
+        app.exit_visual();
+        app.enter_visual();
+        app.start_quote();
+        assert_eq!(app.mode, Mode::Quote);
+        app.move_quote(true, true);
+        app.move_quote(false, false);
+        app.move_quote(false, false);
+        app.move_quote(false, false); // Stays on one word
+        let (_, range) = app.quote_selection().unwrap();
+        assert_eq!(&app.messages[&10][0].text[range], "hi");
+        app.move_quote(false, true);
+
+        app.confirm_quote();
+        assert_eq!(app.mode, Mode::Insert);
+        assert_eq!(app.reply_to, Some(1));
+        let range = app.reply_quote.clone().unwrap();
+        // The wave is two UTF-16 units, then a space
+        assert_eq!(
+            app.quote_for(10, 1, &range),
+            Some(("hi there".to_string(), 3))
+        );
+
+        app.exit_insert();
+        assert_eq!(app.reply_quote, None);
+    }
+
+    #[test]
+    fn outgoing_echo_is_not_added_twice() {
+        let mut app = app_with_chat(10, "Alice", false);
+        app.add_message(10, 1, "Alice".to_string(), "hi".to_string(), false, 0);
+        app.clear_current_unread();
+        app.add_message(10, 2, "You".to_string(), "hello".to_string(), true, 0);
+
+        // The same message coming back through an update, not flagged outgoing
+        assert!(app.has_message(10, 2));
+        app.add_message(10, 2, "Alice".to_string(), "hello".to_string(), false, 0);
+        let ids: Vec<i32> = app.messages[&10].iter().map(|m| m.id).collect();
+        assert_eq!(ids, [1, 2]);
+        assert!(app.messages[&10][1].outgoing);
+        assert_eq!(app.chats[0].unread, 0);
+        assert!(app.chats[0].last_outgoing);
+    }
+
+    #[test]
+    fn sticker_query_filters_saved_stickers() {
+        let mut app = app_with_chat(10, "Alice", false);
+        app.set_sticker_results("", Vec::new());
+        assert_eq!(app.mode, Mode::Normal);
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("No saved stickers or GIFs")
+        );
+
+        let saved = vec![
+            (1, "[sticker: 👍] (favorite)".to_string()),
+            (2, "[GIF: Cat.mp4]".to_string()),
+        ];
+        app.set_sticker_results("dog", saved.clone());
+        assert_eq!(app.status_message.as_deref(), Some("No stickers match dog"));
+
+        app.set_sticker_results("cat", saved);
+        assert_eq!(app.mode, Mode::StickerPicker);
+        assert_eq!(app.sticker_choices.len(), 1);
+        app.select_sticker();
+        assert_eq!(app.sticker_send_requested, Some((10, 2)));
+    }
+
+    #[test]
+    fn idle_timeout_locks_until_a_key() {
+        let mut app = App::new();
+        app.tick();
+        assert!(!app.locked, "locking is off by default");
+
+        app.config.lock_after_mins = 5;
+        app.tick();
+        assert!(!app.locked);
+        app.last_input = Instant::now() - Duration::from_secs(5 * 60);
+        app.tick();
+        assert!(app.locked);
+
+        // No passphrase known in tests, so any key unlocks
+        app.submit_unlock();
+        assert!(!app.locked);
+    }
+
+    #[test]
+    fn slow_mode_counts_down_per_chat() {
+        let mut app = app_with_chat(10, "Rust Learners", true);
+        app.add_chat(11, "Bob".to_string());
+        app.start_slow_mode(10, 0);
+        assert_eq!(app.slow_mode_remaining(), None);
+
+        app.start_slow_mode(10, 30);
+        assert_eq!(app.slow_mode_remaining(), Some(30));
+        app.select_chat(1);
+        assert_eq!(app.slow_mode_remaining(), None);
+    }
+
+    #[test]
+    fn chat_filter_narrows_the_list_and_search() {
+        let mut app = app_with_chat(10, "Alice", false);
+        app.add_chat(11, "Rust Learners".to_string());
+        app.set_chat_group(11, true);
+        app.add_chat(12, "Rust News".to_string());
+        app.set_chat_channel(12, true);
+        app.add_chat(13, "Rust Bot".to_string());
+        app.set_chat_bot(13, true);
+        app.select_chat(0);
+
+        app.cycle_chat_filter();
+        assert_eq!(app.chat_filter, Some(ChatFilter::Users));
+        assert_eq!(app.folder_chat_indices(), [0]);
+
+        // The open chat is filtered out: the first listed one opens instead
+        app.run_command("filter groups");
+        assert_eq!(app.folder_chat_indices(), [1]);
+        assert_eq!(app.selected_chat, 1);
+
+        app.enter_search();
+        app.search_input = "rust".to_string();
+        app.update_search_filter();
+        assert_eq!(app.filtered_chat_indices, [1]);
+        app.cycle_chat_filter();
+        assert_eq!(app.filtered_chat_indices, [2]);
+        app.exit_search();
+
+        app.cycle_chat_filter();
+        assert_eq!(app.folder_chat_indices(), [3]);
+        app.cycle_chat_filter();
+        assert_eq!(app.chat_filter, None);
+        assert_eq!(app.folder_chat_indices(), [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn failed_send_stays_retryable_until_it_goes_out() {
+        let mut app = app_with_chat(10, "Alice", false);
+        app.request_retry_send();
+        assert!(!app.retry_send_requested);
+
+        app.add_message(10, 1, "Alice".to_string(), "hi".to_string(), false, 0);
+        app.reply_to = Some(1);
+        let failed = app.prepare_send(10, "hello".to_string());
+        assert_eq!(app.reply_to, None);
+        app.note_send_failed(failed.clone(), "timed out");
+        app.request_retry_send();
+        assert!(app.retry_send_requested);
+
+        // Other messages going out don't drop it
+        let other = app.prepare_send(10, "something else".to_string());
+        app.note_send_succeeded(&other);
+        assert_eq!(app.last_failed_send.as_ref(), Some(&failed));
+        // The retry goes out as the same reply
+        assert_eq!(failed.reply_to, Some(1));
+        app.note_send_succeeded(&failed);
+        assert_eq!(app.last_failed_send, None);
+    }
+
+    #[test]
+    fn large_chats_ask_before_sending() {
+        use crate::ui::input::handle_key;
+        use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+        let press = |app: &mut App, code| handle_key(app, KeyEvent::new(code, KeyModifiers::NONE));
+
+        let mut app = app_with_chat(10, "Rust Learners", true);
+        app.set_chat_members(10, 12_400);
+        app.mode = Mode::Insert;
+        app.input = "hello all".to_string();
+
+        // Enter asks, Esc goes back to typing with the message kept
+        assert_eq!(press(&mut app, KeyCode::Enter), None);
+        assert_eq!(app.mode, Mode::Confirm);
+        assert_eq!(
+            app.pending_confirm.as_ref().map(|a| a.prompt()),
+            Some("Send to Rust Learners (12k members)?".to_string())
+        );
+        press(&mut app, KeyCode::Esc);
+        assert_eq!((app.mode, app.input.as_str()), (Mode::Insert, "hello all"));
+
+        // y sends it
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(
+            press(&mut app, KeyCode::Char('y')),
+            Some("hello all".to_string())
+        );
+        assert_eq!((app.mode, app.input.as_str()), (Mode::Insert, ""));
+
+        // Under the threshold, or with it off, nothing is asked
+        app.config.confirm_send_members = 20_000;
+        app.input = "hi".to_string();
+        assert_eq!(press(&mut app, KeyCode::Enter), Some("hi".to_string()));
+        app.config.confirm_send_members = 0;
+        app.set_chat_members(10, 1_000_000);
+        app.input = "hi".to_string();
+        assert_eq!(press(&mut app, KeyCode::Enter), Some("hi".to_string()));
+
+        assert_eq!(short_count(950), "950");
+        assert_eq!(short_count(1_250), "1.2k");
+        assert_eq!(short_count(3_400_000), "3.4M");
+    }
+
+    #[test]
+    fn forum_topic_narrows_messages_and_sends() {
+        let mut app = app_with_chat(10, "Rust Learners", true);
+        app.set_chat_forum(10, true);
+        for id in 1..=4 {
+            app.add_message(10, id, "Carol".to_string(), format!("m{}", id), false, 0);
+        }
+        app.set_topic(10, 3, Some(2));
+        app.set_topics(
+            10,
+            vec![(1, "General".to_string()), (2, "Async".to_string())],
+        );
+
+        app.request_topics();
+        assert_eq!(app.topics_requested, Some(10));
+        app.open_topics(10);
+        app.topics_move_down();
+        app.topics_move_down();
+        app.select_topic();
+        let shown: Vec<i32> = app.shown_messages().iter().map(|m| m.id).collect();
+        // The topic's first message is its id
+        assert_eq!(shown, [2, 3]);
+        assert_eq!(app.send_topic(), Some(2));
+
+        // General holds everything without a topic, and sends without one
+        app.open_topics(10);
+        app.topics_move_up();
+        app.select_topic();
+        let shown: Vec<i32> = app.shown_messages().iter().map(|m| m.id).collect();
+        assert_eq!(shown, [1, 2, 4]);
+        assert_eq!(app.send_topic(), None);
+    }
+
+    #[test]
+    fn outgoing_and_unknown_senders_have_no_name() {
+        assert_eq!(sender_display_name("You", true, true), None);
+        assert_eq!(sender_display_name("  ", false, true), None);
+        assert_eq!(sender_display_name("Unknown", false, true), None);
+    }
+}
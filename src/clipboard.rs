@@ -0,0 +1,57 @@
+use base64::Engine;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+/// Copy text to the system clipboard through the terminal (OSC 52)
+///
+/// The escape sequence goes to the terminal rather than a local clipboard
+/// daemon, so it also works over SSH and inside tmux (with `set-clipboard on`).
+/// Terminals that don't support it ignore it silently.
+pub fn copy(text: &str) -> io::Result<()> {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", encoded)?;
+    stdout.flush()
+}
+
+/// Read the system clipboard with the platform's paste tool
+///
+/// Reading can't go through the terminal portably the way copying does, so this
+/// needs pbpaste (macOS), PowerShell (Windows), or wl-paste, xclip or xsel
+/// (Linux and BSD, tried in that order).
+pub fn paste() -> io::Result<String> {
+    let tools: &[(&str, &[&str])] = if cfg!(target_os = "macos") {
+        &[("pbpaste", &[])]
+    } else if cfg!(windows) {
+        &[(
+            "powershell",
+            &["-NoProfile", "-Command", "Get-Clipboard -Raw"],
+        )]
+    } else {
+        &[
+            ("wl-paste", &["--no-newline"]),
+            ("xclip", &["-o", "-selection", "clipboard"]),
+            ("xsel", &["--clipboard", "--output"]),
+        ]
+    };
+
+    let mut error = io::Error::new(io::ErrorKind::NotFound, "no clipboard tool found");
+    for (program, args) in tools {
+        let output = Command::new(program)
+            .args(*args)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output();
+        match output {
+            Ok(output) if output.status.success() => {
+                return String::from_utf8(output.stdout)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e));
+            }
+            // No display for this tool (e.g. wl-paste under X11), try the next one
+            Ok(_) => error = io::Error::other(format!("{} failed", program)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => error = e,
+        }
+    }
+    Err(error)
+}
@@ -0,0 +1,471 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local, Utc};
+
+/// How messages deleted on the server are shown
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeletedMessages {
+    #[default]
+    Placeholder, // Keep a dim "this message was deleted" line
+    Remove, // Drop the message from the view entirely
+}
+
+/// How unread counts are shown in the friends list
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnreadStyle {
+    #[default]
+    Count, // " (3)" after the name
+    Badge,  // Colored " 3 " badge
+    Bullet, // A single dot, no number
+    Hidden, // Nothing
+}
+
+/// Time zone timestamps are shown in
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeZoneSetting {
+    #[default]
+    Local,
+    Utc,
+}
+
+/// Default `time_format`, also used when the configured one doesn't parse
+pub const DEFAULT_TIME_FORMAT: &str = "%H:%M";
+
+/// Which key sends the message in insert mode
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SendKey {
+    #[default]
+    Enter, // Enter sends
+    CtrlEnter, // Ctrl+Enter (or Ctrl+J) sends, Enter inserts a newline
+}
+
+/// Which incoming messages of a chat play the receive sound and ring the mention bell
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifyLevel {
+    #[default]
+    All,
+    Mentions, // Only messages that @-mention you
+    None,
+}
+
+impl NotifyLevel {
+    /// The level `b` switches to: All, Mentions, None, then All again
+    pub fn next(self) -> Self {
+        match self {
+            Self::All => Self::Mentions,
+            Self::Mentions => Self::None,
+            Self::None => Self::All,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::All => "all messages",
+            Self::Mentions => "mentions only",
+            Self::None => "none",
+        }
+    }
+}
+
+/// Shape of the terminal cursor in a mode
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CursorShape {
+    Block,
+    Bar,
+    Underline,
+    BlinkingBlock,
+    BlinkingBar,
+    BlinkingUnderline,
+}
+
+/// Line style of the panel borders
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BorderStyle {
+    #[default]
+    Rounded,
+    Plain,
+    Double,
+    Thick,
+}
+
+/// Proxy used to reach Telegram (`"type"` must be socks5)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProxyConfig {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub host: String,
+    pub port: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+}
+
+impl ProxyConfig {
+    /// Parse a `socks5://[user:pass@]host:port` URL (as given to --proxy), with
+    /// the credentials percent-decoded
+    pub fn from_url(url: &str) -> Result<Self, String> {
+        let (kind, rest) = url
+            .split_once("://")
+            .ok_or_else(|| format!("expected <type>://host:port, got {}", url))?;
+        let (auth, address) = match rest.trim_end_matches('/').rsplit_once('@') {
+            Some((auth, address)) => (Some(auth), address),
+            None => (None, rest.trim_end_matches('/')),
+        };
+        let (host, port) = address
+            .rsplit_once(':')
+            .ok_or_else(|| format!("missing port in {}", url))?;
+        let port = port
+            .parse()
+            .map_err(|_| format!("invalid port {:?} in {}", port, url))?;
+        let (username, password) = match auth.map(|a| a.split_once(':')) {
+            Some(Some((user, pass))) => (Some(percent_decode(user)), Some(percent_decode(pass))),
+            Some(None) => (auth.map(percent_decode), None),
+            None => (None, None),
+        };
+
+        let proxy = Self {
+            kind: kind.to_lowercase(),
+            host: host.to_string(),
+            port,
+            username,
+            password,
+        };
+        proxy.validate()?;
+        Ok(proxy)
+    }
+
+    /// Check the settings are something the client can connect through
+    pub fn validate(&self) -> Result<(), String> {
+        match self.kind.as_str() {
+            "socks5" => {}
+            "mtproto" => {
+                return Err("MTProto proxies aren't supported yet, use a SOCKS5 proxy".to_string())
+            }
+            other => return Err(format!("unsupported proxy type {:?} (use socks5)", other)),
+        }
+        if self.host.trim().is_empty() {
+            return Err("proxy host is empty".to_string());
+        }
+        if self.port == 0 {
+            return Err("proxy port must be between 1 and 65535".to_string());
+        }
+        Ok(())
+    }
+
+    /// URL handed to grammers' `InitParams::proxy_url`. The credentials are
+    /// percent-encoded, so an `@`, `:` or `/` in them can't change where the
+    /// URL points (grammers passes them on still encoded, so such ones can
+    /// reach the proxy but not log in to it)
+    pub fn url(&self) -> String {
+        match (&self.username, &self.password) {
+            (Some(user), Some(pass)) => {
+                format!(
+                    "{}://{}:{}@{}:{}",
+                    self.kind,
+                    percent_encode(user),
+                    percent_encode(pass),
+                    self.host,
+                    self.port
+                )
+            }
+            (Some(user), None) => format!(
+                "{}://{}@{}:{}",
+                self.kind,
+                percent_encode(user),
+                self.host,
+                self.port
+            ),
+            _ => self.display(),
+        }
+    }
+
+    /// URL without credentials, safe to print
+    pub fn display(&self) -> String {
+        format!("{}://{}:{}", self.kind, self.host, self.port)
+    }
+}
+
+/// Escape everything but the unreserved URL characters as %XX
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Undo %XX escapes; anything that isn't a valid one is kept as it is
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| text.get(i + 1..i + 3))
+            .flatten()
+            .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(b) => {
+                out.push(b);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// UI and behaviour settings, stored in config.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub deleted_messages: DeletedMessages,
+    pub bubble_width_pct: u16, // Max message bubble width, % of chat panel (40-90)
+    pub show_welcome: bool,    // Show the Welcome chat; otherwise open the first real chat
+    pub unread_style: UnreadStyle,
+    pub unread_bold: bool, // Bold the whole row of chats with unread messages
+    pub wrap_navigation: bool, // j/k in the friends list wrap around at the ends
+    pub natural_scroll: bool, // Invert j/k in the chat panel: k selects newer messages
+    pub tick_interval_ms: u64, // Idle redraw interval for time-based UI (100-60000)
+    pub scroll_on_send: bool, // Jump to the bottom of the chat after sending
+    pub exit_insert_on_send: bool, // Return to normal mode after sending
+    pub send_on: SendKey,
+    pub time_format: String, // strftime-style, e.g. "%H:%M" or "%-I:%M %p"
+    pub time_zone: TimeZoneSetting,
+    pub auto_scroll: bool, // Follow new messages when at the bottom of the chat
+    pub group_window_secs: u64, // Same-sender messages this close together share one name, 0 = off
+    pub border_style: BorderStyle,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ascii_only: Option<bool>, // Plain ASCII instead of Unicode glyphs and emoji, unset = detect
+    pub cursor_shapes: bool, // Change the cursor shape with the mode (off for terminals that can't)
+    pub normal_cursor: CursorShape, // Cursor in normal, command and the other modes
+    pub insert_cursor: CursorShape, // Cursor while typing a message
+    pub outer_frame: bool,   // Draw the outer "Bifrost" frame around everything
+    pub full_width_input: bool, // Input box across the whole bottom, not just under the chat panel
+    pub compact_width: u16,  // Below this many columns show one panel at a time, 0 = never
+    pub max_name_width: u16, // Cap on chat name width in the friends list, 0 = fit the panel
+    pub chat_preview: bool,  // Show the last message under each chat name in the friends list
+    pub compact: bool,       // No blank line between messages, to fit more on screen
+    pub truncate_long_words: bool, // Cut words too long for a line with "…" instead of splitting them
+    pub encrypt_sessions: bool,    // Encrypt session files with a passphrase
+    pub confirm_disconnect: bool,  // Ask before D deletes the session and logs out
+    pub confirm_send_members: u32, // Ask before sending to chats with more members, 0 = never
+    pub markdown: bool, // Turn *bold*, _italic_, ~strike~ and `code` into formatting when sending
+    pub prefetch_chats: usize, // Most recent chats whose messages load in the background at startup
+    pub max_messages_per_chat: usize, // Oldest messages past this many are dropped, 0 = keep all
+    pub load_more_batch: usize, // Older messages fetched per L press (1-1000)
+    pub mention_bell: bool, // Ring the terminal bell when a group message @-mentions you
+    pub sound_on_send: bool, // Play a sound when a message goes out
+    pub sound_on_receive: bool, // Play a sound for incoming messages in unmuted chats
+    pub sound_file: String, // WAV file played for sound cues, empty = terminal bell
+    pub lock_after_mins: u64, // Lock the screen after this many idle minutes, 0 = never
+    pub lock_passphrase: bool, // Unlocking needs the session passphrase, not just a key
+    pub refresh_on_focus: bool, // Re-read the chat list when the terminal gets focus back
+    pub reconnect_delay_secs: u64, // First wait before retrying the connection, doubling each time
+    pub reconnect_max_delay_secs: u64, // Longest wait between retries
+    pub reconnect_attempts: u32, // Failed reconnects before giving up until r, 0 = never give up
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<ProxyConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phone: Option<String>, // Phone number the first login uses instead of asking
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub notify_levels: HashMap<i64, NotifyLevel>, // Per chat id, chats not listed notify for all messages
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            deleted_messages: DeletedMessages::default(),
+            bubble_width_pct: 60,
+            show_welcome: true,
+            unread_style: UnreadStyle::default(),
+            unread_bold: false,
+            wrap_navigation: false,
+            natural_scroll: false,
+            tick_interval_ms: 1000,
+            send_on: SendKey::default(),
+            scroll_on_send: true,
+            exit_insert_on_send: false,
+            time_format: DEFAULT_TIME_FORMAT.to_string(),
+            time_zone: TimeZoneSetting::default(),
+            auto_scroll: true,
+            group_window_secs: 300,
+            border_style: BorderStyle::default(),
+            ascii_only: None,
+            cursor_shapes: true,
+            normal_cursor: CursorShape::Block,
+            insert_cursor: CursorShape::Bar,
+            outer_frame: true,
+            full_width_input: false,
+            compact_width: 70,
+            max_name_width: 0,
+            chat_preview: false,
+            compact: false,
+            truncate_long_words: false,
+            encrypt_sessions: false,
+            confirm_disconnect: true,
+            confirm_send_members: 1000,
+            markdown: true,
+            prefetch_chats: 3,
+            max_messages_per_chat: 500,
+            load_more_batch: 50,
+            mention_bell: true,
+            sound_on_send: false,
+            sound_on_receive: false,
+            sound_file: String::new(),
+            lock_after_mins: 0,
+            lock_passphrase: false,
+            refresh_on_focus: false,
+            reconnect_delay_secs: 2,
+            reconnect_max_delay_secs: 60,
+            reconnect_attempts: 8,
+            proxy: None,
+            phone: None,
+            notify_levels: HashMap::new(),
+        }
+    }
+}
+
+/// Exponential backoff between connection retries
+#[derive(Debug, Clone, Copy)]
+pub struct Backoff {
+    base: Duration,
+    max: Duration,
+}
+
+impl Backoff {
+    /// Wait before retry number `attempt` (from 0): the base delay doubled
+    /// each time, up to the max
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 1u32.checked_shl(attempt).unwrap_or(u32::MAX);
+        self.base.saturating_mul(factor).min(self.max)
+    }
+}
+
+impl Config {
+    /// Get the config file path
+    fn get_config_path() -> Option<PathBuf> {
+        Some(crate::paths::config_file("config.json"))
+    }
+
+    /// Load config from file, falling back to defaults, with a warning to show
+    /// when the file is there but couldn't be used. One that doesn't parse is
+    /// moved to config.json.bak first, so the next save can't overwrite it
+    pub fn load() -> (Self, Option<String>) {
+        let Some(path) = Self::get_config_path().filter(|path| path.exists()) else {
+            return (Self::default(), None);
+        };
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) => {
+                let warning = format!(
+                    "Couldn't read {} ({}), using default settings",
+                    path.display(),
+                    e
+                );
+                tracing::warn!("{}", warning);
+                return (Self::default(), Some(warning));
+            }
+        };
+        let error = match serde_json::from_str::<Self>(&contents) {
+            Ok(config) => return (config.normalized(), None),
+            Err(e) => e,
+        };
+        let backup = path.with_extension("json.bak");
+        let warning = match fs::rename(&path, &backup) {
+            Ok(()) => format!(
+                "{} is invalid ({}), using default settings; it was moved to {}",
+                path.display(),
+                error,
+                backup.display()
+            ),
+            Err(e) => format!(
+                "{} is invalid ({}), using default settings; moving it aside failed ({}), so :set will overwrite it",
+                path.display(),
+                error,
+                e
+            ),
+        };
+        tracing::warn!("{}", warning);
+        (Self::default(), Some(warning))
+    }
+
+    /// Clamp out-of-range values from a hand-edited file
+    fn normalized(mut self) -> Self {
+        self.bubble_width_pct = self.bubble_width_pct.clamp(40, 90);
+        self.tick_interval_ms = self.tick_interval_ms.clamp(100, 60_000);
+        self.load_more_batch = self.load_more_batch.clamp(1, 1000);
+        self.reconnect_delay_secs = self.reconnect_delay_secs.max(1);
+        self.reconnect_max_delay_secs =
+            self.reconnect_max_delay_secs.max(self.reconnect_delay_secs);
+        if let Err(e) = validate_time_format(&self.time_format) {
+            tracing::warn!("{}, using {}", e, DEFAULT_TIME_FORMAT);
+            self.time_format = DEFAULT_TIME_FORMAT.to_string();
+        }
+        self
+    }
+
+    /// Backoff for retrying a failed connection, from the reconnect_* settings
+    pub fn reconnect_backoff(&self) -> Backoff {
+        Backoff {
+            base: Duration::from_secs(self.reconnect_delay_secs),
+            max: Duration::from_secs(self.reconnect_max_delay_secs),
+        }
+    }
+
+    /// Format a unix timestamp with `time_format` in the configured time zone
+    pub fn format_time(&self, timestamp: i64) -> String {
+        self.format_time_with(timestamp, &self.time_format)
+    }
+
+    /// Format a unix timestamp with any strftime format in the configured time zone
+    pub fn format_time_with(&self, timestamp: i64, format: &str) -> String {
+        let Some(utc) = DateTime::<Utc>::from_timestamp(timestamp, 0) else {
+            return String::new();
+        };
+        match self.time_zone {
+            TimeZoneSetting::Local => utc.with_timezone(&Local).format(format).to_string(),
+            TimeZoneSetting::Utc => utc.format(format).to_string(),
+        }
+    }
+
+    /// Save config to file
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(path) = Self::get_config_path() {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let contents = serde_json::to_string_pretty(self)?;
+            fs::write(path, contents)?;
+        }
+        Ok(())
+    }
+}
+
+/// Check a strftime format string before it's used (chrono panics on bad ones)
+pub fn validate_time_format(format: &str) -> Result<(), String> {
+    if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+        return Err(format!("invalid time_format {:?}", format));
+    }
+    Ok(())
+}
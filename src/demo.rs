@@ -0,0 +1,249 @@
+use crate::app::{unix_now, App, ChatFolder, MediaKind, SelfInfo};
+use crate::format::TextFormat;
+
+/// A synthetic chat: (id, name, unread, messages as (sender, text, outgoing, minutes ago))
+type DemoChat = (
+    i64,
+    &'static str,
+    u32,
+    &'static [(&'static str, &'static str, bool, i64)],
+);
+
+const DEMO_CHATS: &[DemoChat] = &[
+    (
+        1001,
+        "Alice",
+        2,
+        &[
+            ("Alice", "hey! did you try vimgram yet?", false, 180),
+            ("You", "yep, using it right now", true, 175),
+            (
+                "Alice",
+                "nice, j/k to move around, h/l to switch panels",
+                false,
+                170,
+            ),
+            ("You", "feels just like vim", true, 160),
+            (
+                "Alice",
+                "the docs are here https://github.com/subhdotsol/vimgram",
+                false,
+                20,
+            ),
+            ("Alice", "try :set bubble_width_pct=80 too", false, 5),
+        ],
+    ),
+    (
+        1002,
+        "Bob",
+        0,
+        &[
+            ("Bob", "lunch tomorrow?", false, 1500),
+            ("You", "sure, 1pm?", true, 1490),
+            ("Bob", "works for me 👍", false, 1485),
+        ],
+    ),
+    (
+        -1003,
+        "Rust Learners",
+        14,
+        &[
+            ("Carol", "anyone know a good async intro?", false, 300),
+            (
+                "Dave",
+                "the tokio tutorial: https://tokio.rs/tokio/tutorial",
+                false,
+                295,
+            ),
+            ("Carol", "thanks!", false, 290),
+            (
+                "Erin",
+                "also the async book if you want the lower level details, it goes \
+                 through futures, pinning and executors step by step",
+                false,
+                120,
+            ),
+            ("You", "+1 for the tokio tutorial", true, 60),
+            (
+                "Dave",
+                "@demo borrow checker question incoming...",
+                false,
+                2,
+            ),
+        ],
+    ),
+    (
+        -1004,
+        "Release Notes",
+        1,
+        &[
+            (
+                "Release Notes",
+                "v0.2.0 is out: multi-account support",
+                false,
+                10080,
+            ),
+            (
+                "Release Notes",
+                "v0.2.1: AI commands and code assist",
+                false,
+                2880,
+            ),
+            ("Release Notes", "next up: message search", false, 30),
+        ],
+    ),
+    (
+        1005,
+        "Mom",
+        0,
+        &[
+            ("Mom", "call me when you're free", false, 4320),
+            ("You", "will do tonight", true, 4300),
+            ("Mom", "", false, 4290),
+        ],
+    ),
+    (
+        1006,
+        "Vimgram Bot",
+        0,
+        &[
+            ("You", "/start", true, 600),
+            (
+                "Vimgram Bot",
+                "Hi! I post release announcements here.",
+                false,
+                599,
+            ),
+        ],
+    ),
+];
+
+/// Fill the app with synthetic chats and messages for `--demo` (no Telegram needed)
+pub fn populate(app: &mut App) {
+    let now = unix_now();
+
+    app.set_account_info(
+        "demo".to_string(),
+        vec![(
+            "demo".to_string(),
+            "Demo User (+0 000 000 0000)".to_string(),
+        )],
+    );
+    app.me = Some(SelfInfo {
+        id: 1000,
+        name: "Demo User".to_string(),
+        username: Some("demo".to_string()),
+        phone: None,
+    });
+
+    let mut next_id = 1;
+    for &(chat_id, name, unread, messages) in DEMO_CHATS {
+        app.add_chat(chat_id, name.to_string());
+        app.set_chat_group(chat_id, name == "Rust Learners");
+        if name == "Rust Learners" {
+            app.set_chat_members(chat_id, 12_400);
+        }
+        if chat_id > 0 && name != "Mom" {
+            app.set_chat_username(chat_id, Some(&name.to_lowercase().replace(' ', "_")));
+        }
+        for &(sender, text, outgoing, minutes_ago) in messages {
+            let date = now - minutes_ago * 60;
+            app.add_message(
+                chat_id,
+                next_id,
+                sender.to_string(),
+                text.to_string(),
+                outgoing,
+                date,
+            );
+            next_id += 1;
+        }
+        if let Some(chat) = app.chats.iter_mut().find(|c| c.id == chat_id) {
+            chat.unread = unread;
+        }
+    }
+
+    // Dave's @demo ping shows the mentions badge
+    if let Some(chat) = app.chats.iter_mut().find(|c| c.id == -1003) {
+        chat.mentions = 1;
+    }
+
+    // A bot chat and a service message, styled apart from normal chats and messages
+    app.set_chat_bot(1006, true);
+    app.set_chat_channel(-1004, true);
+    app.add_message(
+        -1003,
+        next_id,
+        "Frank".to_string(),
+        "Frank joined the group".to_string(),
+        false,
+        now - 60,
+    );
+    app.set_service(-1003, next_id);
+
+    // Folder tabs: one listing chats, one built from a rule
+    app.set_folders(vec![
+        ChatFolder {
+            title: "Personal".to_string(),
+            chat_ids: vec![1001, 1002, 1005],
+            exclude_muted: false,
+            exclude_read: false,
+        },
+        ChatFolder {
+            title: "Unread".to_string(),
+            chat_ids: DEMO_CHATS.iter().map(|c| c.0).collect(),
+            exclude_muted: false,
+            exclude_read: true,
+        },
+    ]);
+
+    // Rust Learners is a forum: the tutorial talk has its own topic (T to pick)
+    app.set_chat_forum(-1003, true);
+    app.set_topics(
+        -1003,
+        vec![(1, "General".to_string()), (10, "Async".to_string())],
+    );
+    for id in 11..=14 {
+        app.set_topic(-1003, id, Some(10));
+    }
+
+    // A forward so its attribution line shows up
+    app.set_forwarded(-1003, 11, (None, Some("Tokio".to_string())));
+
+    // Formatting entities so bold and code spans show up
+    app.set_formats(
+        1001,
+        3,
+        vec![(6..9, TextFormat::Code), (26..29, TextFormat::Code)],
+    );
+    app.set_formats(1001, 6, vec![(4..28, TextFormat::Code)]);
+    app.set_formats(1001, 4, vec![(16..19, TextFormat::Bold)]);
+
+    // A voice note so media labels show up
+    app.set_media(1005, 21, "Voice (0:14)".to_string());
+    app.set_media_kind(1005, 21, MediaKind::Voice);
+
+    // One pinned message so the banner shows up
+    app.set_pinned(
+        -1003,
+        vec![(
+            11,
+            "the tokio tutorial: https://tokio.rs/tokio/tutorial".to_string(),
+        )],
+    );
+}
+
+/// Saved stickers and GIFs for :sticker, as (document id, label)
+pub fn saved_stickers() -> Vec<(i64, String)> {
+    vec![
+        (1, "[sticker: 👍] (favorite)".to_string()),
+        (2, "[sticker: 😂] (recent)".to_string()),
+        (3, "[sticker: 🦀] (recent)".to_string()),
+        (4, "[GIF: ferris-dance.mp4]".to_string()),
+    ]
+}
+
+/// Whether `--demo` was passed on the command line
+pub fn requested() -> bool {
+    std::env::args().skip(1).any(|arg| arg == "--demo")
+}
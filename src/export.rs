@@ -0,0 +1,96 @@
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::app::Message;
+use crate::config::Config;
+
+/// One message in a `--json` export
+#[derive(Serialize)]
+struct ExportedMessage<'a> {
+    id: i32,
+    date: String, // RFC 3339, in the configured time zone
+    sender: &'a str,
+    text: &'a str,
+    outgoing: bool,
+    edited: bool,
+    deleted: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    forwarded_from: Option<&'a str>,
+}
+
+/// Expand a leading `~/` to the home directory
+pub fn expand_path(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => directories::BaseDirs::new()
+            .map(|dirs| dirs.home_dir().join(rest))
+            .unwrap_or_else(|| PathBuf::from(path)),
+        None => PathBuf::from(path),
+    }
+}
+
+/// Write a chat's loaded messages to `path` as plain text or JSON
+pub fn export_chat(
+    path: &Path,
+    chat_name: &str,
+    messages: &[Message],
+    json: bool,
+    config: &Config,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let contents = if json {
+        let exported: Vec<ExportedMessage> = messages
+            .iter()
+            .map(|m| ExportedMessage {
+                id: m.id,
+                date: config.format_time_with(m.date, "%+"),
+                sender: &m.sender,
+                text: &m.text,
+                outgoing: m.outgoing,
+                edited: m.edited,
+                deleted: m.deleted,
+                forwarded_from: m.forwarded_from.as_deref(),
+            })
+            .collect();
+        serde_json::to_string_pretty(&exported)?
+    } else {
+        let mut out = format!(
+            "# {}\n# Exported {} ({} messages)\n",
+            chat_name,
+            config.format_time_with(crate::app::unix_now(), "%Y-%m-%d %H:%M"),
+            messages.len()
+        );
+        for m in messages {
+            let sender = if m.sender.trim().is_empty() {
+                chat_name
+            } else {
+                &m.sender
+            };
+            let mut flags = String::new();
+            if m.edited {
+                flags.push_str(" (edited)");
+            }
+            if m.deleted {
+                flags.push_str(" (deleted)");
+            }
+            match m.forwarded_from.as_deref() {
+                Some("") => flags.push_str(" (forwarded)"),
+                Some(origin) => flags.push_str(&format!(" (forwarded from {})", origin)),
+                None => {}
+            }
+            out.push_str(&format!(
+                "\n[{}] {}{}:\n{}\n",
+                config.format_time_with(m.date, "%Y-%m-%d %H:%M"),
+                sender,
+                flags,
+                m.text
+            ));
+        }
+        out
+    };
+
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, contents)?;
+    Ok(())
+}
@@ -0,0 +1,295 @@
+use std::ops::Range;
+
+use grammers_tl_types as tl;
+
+/// Inline formatting we render, from a message's entities
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextFormat {
+    Bold,
+    Italic,
+    Code, // Inline code and code blocks alike
+    Strike,
+}
+
+/// Byte offset in `text` of a UTF-16 offset (Telegram counts entities in UTF-16 units)
+fn utf16_to_byte(text: &str, offset: usize) -> usize {
+    let mut units = 0;
+    for (i, c) in text.char_indices() {
+        if units >= offset {
+            return i;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
+/// Map Telegram entities onto byte ranges of `text`, keeping the styles we render
+pub fn from_entities(
+    text: &str,
+    entities: &[tl::enums::MessageEntity],
+) -> Vec<(Range<usize>, TextFormat)> {
+    use tl::enums::MessageEntity as E;
+
+    entities
+        .iter()
+        .filter_map(|entity| {
+            let format = match entity {
+                E::Bold(_) => TextFormat::Bold,
+                E::Italic(_) => TextFormat::Italic,
+                E::Code(_) | E::Pre(_) => TextFormat::Code,
+                E::Strike(_) => TextFormat::Strike,
+                _ => return None,
+            };
+            let start = entity.offset().max(0) as usize;
+            let end = start + entity.length().max(0) as usize;
+            let range = utf16_to_byte(text, start)..utf16_to_byte(text, end);
+            (!range.is_empty()).then_some((range, format))
+        })
+        .collect()
+}
+
+/// Formatting of a fetched message's text
+pub fn message_formats(msg: &grammers_client::types::Message) -> Vec<(Range<usize>, TextFormat)> {
+    msg.fmt_entities()
+        .map(|entities| from_entities(msg.text(), entities))
+        .unwrap_or_default()
+}
+
+/// Whether `text` @-mentions the user, by @username or by a mention of their id
+/// (how users without a username get mentioned)
+pub fn mentions_user(
+    text: &str,
+    entities: &[tl::enums::MessageEntity],
+    user_id: i64,
+    username: Option<&str>,
+) -> bool {
+    use tl::enums::MessageEntity as E;
+
+    entities.iter().any(|entity| match entity {
+        E::MentionName(mention) => mention.user_id == user_id,
+        E::Mention(mention) => username.is_some_and(|username| {
+            let start = utf16_to_byte(text, mention.offset.max(0) as usize);
+            let end = utf16_to_byte(text, (mention.offset + mention.length).max(0) as usize);
+            text[start..end]
+                .trim_start_matches('@')
+                .eq_ignore_ascii_case(username)
+        }),
+        _ => false,
+    })
+}
+
+/// Telegram entities for byte-range formatting of `text` (offsets in UTF-16 units)
+pub fn to_entities(
+    text: &str,
+    formats: &[(Range<usize>, TextFormat)],
+) -> Vec<tl::enums::MessageEntity> {
+    use tl::enums::MessageEntity as E;
+
+    let utf16 = |byte: usize| text[..byte].encode_utf16().count() as i32;
+    formats
+        .iter()
+        .map(|(range, format)| {
+            let offset = utf16(range.start);
+            let length = utf16(range.end) - offset;
+            match format {
+                TextFormat::Bold => E::Bold(tl::types::MessageEntityBold { offset, length }),
+                TextFormat::Italic => E::Italic(tl::types::MessageEntityItalic { offset, length }),
+                TextFormat::Code => E::Code(tl::types::MessageEntityCode { offset, length }),
+                TextFormat::Strike => E::Strike(tl::types::MessageEntityStrike { offset, length }),
+            }
+        })
+        .collect()
+}
+
+/// Text and formatting to send for what was typed, markdown parsed if enabled
+pub fn outgoing(input: &str, markdown: bool) -> (String, Vec<(Range<usize>, TextFormat)>) {
+    if markdown {
+        parse_markdown(input)
+    } else {
+        (input.to_string(), Vec::new())
+    }
+}
+
+/// Parse the markdown-ish syntax typed in insert mode: `*bold*`, `_italic_`,
+/// `~strike~`, `` `code` `` and ```` ```code blocks``` ````. Returns the text with
+/// the markers removed and its formatting; unmatched markers stay as typed
+pub fn parse_markdown(input: &str) -> (String, Vec<(Range<usize>, TextFormat)>) {
+    let mut text = String::new();
+    let mut formats = Vec::new();
+    parse_into(input, &mut text, &mut formats);
+    (text, formats)
+}
+
+fn parse_into(input: &str, text: &mut String, formats: &mut Vec<(Range<usize>, TextFormat)>) {
+    let mut rest = input;
+    while let Some(c) = rest.chars().next() {
+        match span_at(rest, text.chars().last()) {
+            Some((format, inner, after)) => {
+                let start = text.len();
+                if format == TextFormat::Code {
+                    text.push_str(inner);
+                } else {
+                    parse_into(inner, text, formats);
+                }
+                formats.push((start..text.len(), format));
+                rest = after;
+            }
+            None => {
+                text.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+}
+
+/// A formatted span starting at the beginning of `rest`: (format, inner text, text after it).
+/// Markers other than backticks only count at word boundaries, so snake_case and 2*3*4 stay
+fn span_at(rest: &str, previous: Option<char>) -> Option<(TextFormat, &str, &str)> {
+    if let Some(body) = rest.strip_prefix("```") {
+        let end = body.find("```")?;
+        let inner = body[..end].trim_matches('\n');
+        return (!inner.is_empty()).then_some((TextFormat::Code, inner, &body[end + 3..]));
+    }
+
+    let marker = rest.chars().next()?;
+    let format = match marker {
+        '*' => TextFormat::Bold,
+        '_' => TextFormat::Italic,
+        '~' => TextFormat::Strike,
+        '`' => TextFormat::Code,
+        _ => return None,
+    };
+    let code = format == TextFormat::Code;
+    if !code && previous.is_some_and(char::is_alphanumeric) {
+        return None;
+    }
+    let body = &rest[1..];
+    if body.starts_with(char::is_whitespace) {
+        return None;
+    }
+    body.match_indices(marker).find_map(|(i, _)| {
+        let inner = &body[..i];
+        let after = &body[i + 1..];
+        let closes = !inner.is_empty()
+            && !inner.ends_with(char::is_whitespace)
+            && (code || !after.starts_with(char::is_alphanumeric));
+        closes.then_some((format, inner, after))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn utf16_entity_offsets_map_to_bytes() {
+        // 🦀 is two UTF-16 units and four bytes, é one unit and two bytes
+        let text = "🦀 héllo *bold*";
+        let entities = [
+            tl::enums::MessageEntity::Italic(tl::types::MessageEntityItalic {
+                offset: 3,
+                length: 5,
+            }),
+            tl::enums::MessageEntity::Bold(tl::types::MessageEntityBold {
+                offset: 9,
+                length: 6,
+            }),
+            tl::enums::MessageEntity::Url(tl::types::MessageEntityUrl {
+                offset: 0,
+                length: 2,
+            }),
+        ];
+        let formats = from_entities(text, &entities);
+        assert_eq!(formats.len(), 2);
+        assert_eq!(&text[formats[0].0.clone()], "héllo");
+        assert_eq!(formats[0].1, TextFormat::Italic);
+        assert_eq!(&text[formats[1].0.clone()], "*bold*");
+        assert_eq!(formats[1].1, TextFormat::Bold);
+    }
+
+    #[test]
+    fn mentions_match_username_or_id() {
+        let text = "hey @Alice and @bob";
+        let mention = |offset, length| {
+            tl::enums::MessageEntity::Mention(tl::types::MessageEntityMention { offset, length })
+        };
+        let entities = [mention(4, 6), mention(15, 4)];
+        assert!(mentions_user(text, &entities, 1, Some("alice")));
+        assert!(mentions_user(text, &entities, 1, Some("bob")));
+        assert!(!mentions_user(text, &entities, 1, Some("carol")));
+        assert!(!mentions_user(text, &entities, 1, None));
+
+        let by_id = [tl::enums::MessageEntity::MentionName(
+            tl::types::MessageEntityMentionName {
+                offset: 0,
+                length: 3,
+                user_id: 42,
+            },
+        )];
+        assert!(mentions_user(text, &by_id, 42, None));
+        assert!(!mentions_user(text, &by_id, 7, None));
+    }
+
+    /// Parse and show each format as (format, formatted text)
+    fn parsed(input: &str) -> (String, Vec<(TextFormat, String)>) {
+        let (text, formats) = parse_markdown(input);
+        let spans = formats
+            .iter()
+            .map(|(range, format)| (*format, text[range.clone()].to_string()))
+            .collect();
+        (text, spans)
+    }
+
+    #[test]
+    fn markdown_markers_become_formats() {
+        assert_eq!(
+            parsed("say *hi* to _everyone_ in `#general` ~now~"),
+            (
+                "say hi to everyone in #general now".to_string(),
+                vec![
+                    (TextFormat::Bold, "hi".to_string()),
+                    (TextFormat::Italic, "everyone".to_string()),
+                    (TextFormat::Code, "#general".to_string()),
+                    (TextFormat::Strike, "now".to_string()),
+                ]
+            )
+        );
+        assert_eq!(
+            parsed("```\nfn main() {}\n```"),
+            (
+                "fn main() {}".to_string(),
+                vec![(TextFormat::Code, "fn main() {}".to_string())]
+            )
+        );
+    }
+
+    #[test]
+    fn markdown_leaves_literal_markers_alone() {
+        for input in [
+            "snake_case_name",
+            "2*3*4",
+            "a * b * c",
+            "*unclosed",
+            "`*not bold*`",
+        ] {
+            let (text, formats) = parse_markdown(input);
+            let expected = input.trim_matches('`');
+            assert_eq!(text, expected, "{}", input);
+            assert!(
+                formats.iter().all(|(_, f)| *f == TextFormat::Code),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn nested_markdown_and_utf16_entities() {
+        let (text, formats) = parse_markdown("🦀 *bold _and italic_*");
+        assert_eq!(text, "🦀 bold and italic");
+        let entities = to_entities(&text, &formats);
+        let spans: Vec<_> = entities.iter().map(|e| (e.offset(), e.length())).collect();
+        // 🦀 is two UTF-16 units
+        assert_eq!(spans, [(8, 10), (3, 15)]);
+    }
+}
@@ -0,0 +1,40 @@
+use std::ops::Range;
+
+/// Find http(s)/www URLs in text, returned as byte ranges
+pub fn find_urls(text: &str) -> Vec<Range<usize>> {
+    let mut urls = Vec::new();
+    let mut offset = 0;
+
+    for word in text.split_inclusive(char::is_whitespace) {
+        let token = word.trim_end();
+        let lower = token.to_ascii_lowercase();
+        if let Some(start) = ["https://", "http://", "www."]
+            .iter()
+            .filter_map(|scheme| lower.find(scheme))
+            .min()
+        {
+            // Drop punctuation that usually ends the sentence, not the URL
+            let url = token[start..].trim_end_matches(|c: char| {
+                matches!(
+                    c,
+                    '.' | ',' | ';' | ':' | '!' | '?' | ')' | ']' | '}' | '>' | '"' | '\''
+                )
+            });
+            if url.len() > "www.".len() {
+                urls.push(offset + start..offset + start + url.len());
+            }
+        }
+        offset += word.len();
+    }
+
+    urls
+}
+
+/// Make a detected URL openable (bare www. links get a scheme)
+pub fn normalize_url(url: &str) -> String {
+    if url.to_ascii_lowercase().starts_with("www.") {
+        format!("https://{}", url)
+    } else {
+        url.to_string()
+    }
+}
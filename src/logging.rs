@@ -0,0 +1,58 @@
+use std::fs::{self, OpenOptions};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tracing::level_filters::LevelFilter;
+
+/// Env var setting the log level when `--log-level` isn't given
+const LOG_LEVEL_VAR: &str = "VIMGRAM_LOG";
+/// The log is rotated to `vimgram.log.old` once it grows past this
+const MAX_LOG_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Path of the log file, next to config.json
+pub fn log_path() -> Option<PathBuf> {
+    Some(crate::paths::config_file("vimgram.log"))
+}
+
+/// Start logging to the log file at the level from `--log-level` or VIMGRAM_LOG
+/// (error, warn, info, debug, trace or off; info by default)
+///
+/// The TUI owns stdout, so this file is the only trace of what went wrong.
+/// Logging is best effort: a bad level or unwritable file only prints a warning.
+pub fn init(cli_level: Option<String>) {
+    let level = cli_level
+        .or_else(|| std::env::var(LOG_LEVEL_VAR).ok())
+        .unwrap_or_else(|| "info".to_string());
+    let level = match level.parse::<LevelFilter>() {
+        Ok(level) => level,
+        Err(_) => {
+            eprintln!("⚠️ Unknown log level {:?}, using info", level);
+            LevelFilter::INFO
+        }
+    };
+    if level == LevelFilter::OFF {
+        return;
+    }
+
+    let Some(path) = log_path() else {
+        return;
+    };
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    if fs::metadata(&path).is_ok_and(|m| m.len() > MAX_LOG_BYTES) {
+        let _ = fs::rename(&path, path.with_extension("log.old"));
+    }
+    let file = match OpenOptions::new().create(true).append(true).open(&path) {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("⚠️ Can't open log file {}: {}", path.display(), e);
+            return;
+        }
+    };
+
+    tracing_subscriber::fmt()
+        .with_writer(Mutex::new(file))
+        .with_ansi(false)
+        .with_max_level(level)
+        .init();
+}
@@ -1,48 +1,210 @@
 mod ai;
 mod app;
+mod clipboard;
+mod config;
+mod demo;
+mod export;
+mod format;
+mod links;
+mod logging;
+mod palette;
+mod paths;
+mod scratchpad;
+mod sound;
 mod telegram;
 mod ui;
 
 use std::collections::HashMap;
 use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 
 use crossterm::event::EventStream;
 use crossterm::{
-    event::{self, Event},
+    cursor::SetCursorStyle,
+    event::{
+        DisableBracketedPaste, DisableFocusChange, EnableBracketedPaste, EnableFocusChange, Event,
+        KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    terminal::{
+        disable_raw_mode, enable_raw_mode, supports_keyboard_enhancement, EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
 };
 use futures::{FutureExt, StreamExt};
 use grammers_client::Update;
 use ratatui::{backend::CrosstermBackend, Terminal};
 use tokio::sync::mpsc;
 
-use app::{App, FindResult};
-use telegram::accounts::AccountRegistry;
+use app::{App, FindResult, MediaKind, OutgoingMessage};
+use telegram::accounts::{AccountRegistry, SessionHealth};
 use telegram::auth::{authenticate, prompt_for_credentials};
-use telegram::client::{delete_session, TelegramClient};
+use telegram::client::{
+    bare_chat_id, chat_is_bot, chat_is_forum, chat_members, delete_credentials, delete_session,
+    delete_session_for_account, dialog_mute_until, dialog_read_state, download_message_media,
+    export_invite_link, fetch_blocked, fetch_folders, fetch_full_channel, fetch_pinned,
+    fetch_saved_stickers, fetch_topics, flood_wait_seconds, forward_origin, is_auth_error,
+    is_permission_error, is_quote_error, is_transient_error, media_label, message_topic,
+    resolve_chat_id, send_quote_reply, send_saved_sticker, service_text, set_blocked,
+    set_mute_until, slow_mode_wait_seconds, ForwardOrigin, FullChannel, TelegramClient,
+};
 use ui::draw::draw;
-use ui::input::handle_key;
+use ui::input::{handle_key, handle_paste};
+
+/// A fetched message, with what the app needs to show it
+struct LoadedMessage {
+    id: i32,
+    sender: String,
+    text: String, // For a service message, what happened
+    outgoing: bool,
+    date: i64, // Unix timestamp
+    forwarded_from: Option<ForwardOrigin>,
+    media: Option<String>,         // Label of attached media
+    media_kind: Option<MediaKind>, // Of the media, or the call a service message is about
+    service: bool,                 // Join, pin, call, ...
+    formats: Vec<(std::ops::Range<usize>, format::TextFormat)>,
+    topic: Option<i32>, // Forum topic
+}
+
+/// Progress reported by a background message load
+enum LoadEvent {
+    /// Finished, oldest first
+    Messages(Vec<LoadedMessage>),
+    /// Hit a flood wait; the loader sleeps this many seconds and resumes
+    RateLimited(u32),
+    /// Pinned messages as (id, text), newest first; sent just before Messages
+    Pinned(Vec<(i32, String)>),
+    /// Member count and slow mode of a channel or supergroup, from one full info request
+    FullChannel(FullChannel),
+    /// Messages and pinned messages fetched ahead of time at startup
+    Prefetched(Vec<LoadedMessage>, Vec<(i32, String)>),
+}
+
+/// Result of a background connection health check or reconnect
+enum HealthEvent {
+    Alive,
+    Dead,
+    Reconnected(TelegramClient),
+    ReconnectFailed(String),
+}
+
+/// How often the connection is pinged (catches dead sockets after a laptop sleeps)
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+/// A ping slower than this counts as a dead connection
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// refresh_on_focus re-reads the chat list at most this often
+const FOCUS_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How often chats read locally are marked read on the server
+const READ_SYNC_INTERVAL: Duration = Duration::from_secs(2);
+/// Most read marks sent at once while flushing the queue
+const READ_SYNC_CONCURRENCY: usize = 4;
+
+/// Most chats pre-fetched at the same time at startup
+const PREFETCH_CONCURRENCY: usize = 2;
+/// Messages fetched per chat when it's loaded
+const HISTORY_PAGE: usize = 50;
+/// Most chats read from the dialog list, at startup and on :refresh
+const MAX_CHATS: usize = 100;
+
+/// Most results a :search returns
+const SEARCH_LIMIT: usize = 50;
+
+/// Most messages :jump fetches going back before giving up on the target
+const JUMP_MAX_FETCH: usize = 1000;
+/// Messages older than the :jump target fetched along with it, for context
+const JUMP_CONTEXT: usize = 10;
+
+/// Extra attempts for a :find lookup that failed on a network error
+const FIND_RETRIES: u32 = 2;
+/// Wait before the first :find retry, doubled for each one after
+const FIND_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Env var carrying how many automatic re-logins happened in a row
+const REAUTH_ATTEMPT_VAR: &str = "VIMGRAM_REAUTH_ATTEMPT";
+/// Give up re-authenticating after this many consecutive expired sessions
+const MAX_REAUTH_ATTEMPTS: u32 = 2;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Set up panic hook to restore terminal on crash
     let original_hook = std::panic::take_hook();
     std::panic::set_hook(Box::new(move |panic_info| {
-        // Restore terminal
-        let _ = disable_raw_mode();
-        let _ = execute!(io::stdout(), LeaveAlternateScreen);
+        restore_terminal(true);
         original_hook(panic_info);
     }));
 
     // Load .env file if present
     dotenvy::dotenv().ok();
 
+    // Debug log in the config dir, since the TUI hides stdout
+    logging::init(cli_option("--log-level"));
+    tracing::info!("vimgram {} starting", env!("CARGO_PKG_VERSION"));
+    if let Some(warning) = paths::persistence_warning() {
+        tracing::warn!("{}", warning);
+        eprintln!("⚠️ {}", warning);
+    }
+
+    // Offline demo: synthetic chats, no credentials, login or network
+    if demo::requested() {
+        let (config, warning) = config::Config::load();
+        if let Some(warning) = &warning {
+            eprintln!("⚠️ {}", warning);
+        }
+        return run_demo(config, warning).await;
+    }
+
+    let mut restarting: Option<&str> = None;
+    loop {
+        match run().await {
+            Ok(Exit::Quit) => return Ok(()),
+            Ok(Exit::Restart(hint)) => {
+                let err = restart();
+                // Can't start a fresh process: go through startup again in this one
+                tracing::warn!("restarting failed, starting over in-process: {}", err);
+                eprintln!("⚠️ Couldn't restart vimgram ({}), starting over...", err);
+                restarting = Some(hint);
+            }
+            Err(e) => {
+                restore_terminal(true);
+                if let Some(hint) = restarting {
+                    tracing::error!("starting over in-process failed: {}", e);
+                    eprintln!("❌ Starting over failed: {}", e);
+                    eprintln!("   {}", hint);
+                    std::process::exit(1);
+                }
+                return Err(e);
+            }
+        }
+    }
+}
+
+/// How the UI closed: for good, or to start over (account switch, re-login)
+enum Exit {
+    Quit,
+    Restart(&'static str), // What to tell the user if vimgram can't start over by itself
+}
+
+/// Connect, log in if needed and run the UI until it closes
+async fn run() -> Result<Exit, Box<dyn std::error::Error>> {
+    // Consecutive automatic re-logins (set by the session-expired restart below)
+    let reauth_attempt: u32 = std::env::var(REAUTH_ATTEMPT_VAR)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    // Only the session-expired restart should carry the counter forward
+    std::env::remove_var(REAUTH_ATTEMPT_VAR);
+
     // Load account registry
     let mut account_registry = AccountRegistry::load();
+    if account_registry.accounts.len() > 1 && !cli_flag("--no-account-summary") {
+        print_account_summary(&account_registry);
+    }
 
     // Get API credentials (priority: Env, then Config File, then Prompt)
     let (api_id, api_hash) = match (
@@ -73,18 +235,72 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     };
 
+    // UI settings and proxy (--proxy overrides the config file)
+    let (config, config_warning) = config::Config::load();
+    if let Some(warning) = &config_warning {
+        eprintln!("⚠️ {}", warning);
+    }
+    let proxy = match cli_option("--proxy") {
+        Some(url) => Some(config::ProxyConfig::from_url(&url)),
+        None => config.proxy.clone().map(|p| p.validate().map(|()| p)),
+    };
+    let proxy = match proxy.transpose() {
+        Ok(proxy) => proxy,
+        Err(e) => {
+            eprintln!("❌ Invalid proxy settings: {}", e);
+            std::process::exit(1);
+        }
+    };
+
     // Connect with account from registry, or use legacy connect
-    println!("🔌 Connecting to Telegram...");
-    let tg = if account_registry.has_accounts() {
+    match &proxy {
+        Some(proxy) => println!("🔌 Connecting to Telegram through {}...", proxy.display()),
+        None => println!("🔌 Connecting to Telegram..."),
+    }
+    let proxy_url = proxy.as_ref().map(|p| p.url());
+    let connected = if account_registry.has_accounts() {
         let active_id = account_registry.active.clone();
-        TelegramClient::connect_with_account(api_id, &api_hash, &active_id).await?
+        TelegramClient::connect_with_account(api_id, &api_hash, &active_id, proxy_url).await
     } else {
-        TelegramClient::connect(api_id, &api_hash).await?
+        TelegramClient::connect(api_id, &api_hash, proxy_url).await
     };
+    let mut tg = match (connected, &proxy) {
+        (Ok(tg), _) => tg,
+        (Err(e), Some(proxy)) => {
+            tracing::error!("connect through proxy {} failed: {}", proxy.display(), e);
+            // Most likely the proxy itself, not Telegram, so say which one
+            eprintln!(
+                "❌ Could not connect through proxy {}: {}",
+                proxy.display(),
+                e
+            );
+            eprintln!("   Check the \"proxy\" entry in config.json or the --proxy flag.");
+            std::process::exit(1);
+        }
+        (Err(e), None) => {
+            tracing::error!("connect failed: {}", e);
+            return Err(e);
+        }
+    };
+    tracing::info!("connected to Telegram");
+
+    // Store the session the way config.json asks, converting an existing one
+    tg.encrypt_session = config.encrypt_sessions;
+    if let Err(e) = tg.sync_session_encryption() {
+        tracing::warn!("converting the session file failed: {}", e);
+    }
 
     if !tg.is_authorized().await? {
-        authenticate(&tg.client).await?;
-        tg.save_session()?;
+        // Only a first login takes the preset number: an existing account logs back in as itself
+        let phone = cli_option("--phone")
+            .or_else(|| config.phone.clone())
+            .map(|phone| phone.trim().to_string())
+            .filter(|phone| !phone.is_empty() && !account_registry.has_accounts());
+        authenticate(&tg.client, phone.as_deref()).await?;
+        // Without a writable config dir the login only lasts for this run
+        if let Err(e) = tg.save_session() {
+            tracing::warn!("saving the session failed: {}", e);
+        }
 
         // Update the current account's info in registry
         let me = tg.client.get_me().await?;
@@ -116,13 +332,24 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
+    execute!(
+        stdout,
+        EnterAlternateScreen,
+        EnableBracketedPaste,
+        EnableFocusChange
+    )?;
+    let key_enhancement = enable_key_enhancement(&config);
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
     // Create app state
     let mut app = App::new();
+    app.config = config;
+    app.scratchpad = scratchpad::load();
     app.loading_status = Some("Loading chats...".to_string());
+    // Once, in the UI too, since the startup output is gone by now
+    app.status_message =
+        config_warning.or_else(|| paths::persistence_warning().map(str::to_string));
 
     // Set account info in app state
     let account_info: Vec<(String, String)> = account_registry
@@ -131,9 +358,23 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .map(|a| (a.id.clone(), format!("{} ({})", a.name, a.phone)))
         .collect();
     app.set_account_info(account_registry.active.clone(), account_info);
+    app.account_accents = account_registry
+        .accounts
+        .iter()
+        .enumerate()
+        .map(|(i, a)| (a.id.clone(), account_registry.accent(i)))
+        .collect();
+    app.me = Some(app::SelfInfo {
+        id: me.id(),
+        name: me.full_name(),
+        username: me.username().map(|u| u.to_string()),
+        phone: me.phone().map(|p| p.to_string()),
+    });
 
     // Add welcome chat (the keybindings box is rendered by draw_welcome_box in draw.rs)
-    app.add_chat(1, "Welcome".to_string());
+    if app.config.show_welcome {
+        app.add_chat(app::WELCOME_CHAT_ID, "Welcome".to_string());
+    }
 
     // Load dialogs (just chat names, no messages for faster loading)
     // Limit to 100 chats to prevent overload
@@ -141,16 +382,39 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut chat_cache: HashMap<i64, grammers_client::types::Chat> = HashMap::new();
     let mut dialogs = tg.client.iter_dialogs();
     let mut count = 0;
-    const MAX_CHATS: usize = 100;
     while let Some(dialog) = dialogs.next().await? {
         if count >= MAX_CHATS {
             break;
         }
-        let chat = dialog.chat();
-        chat_cache.insert(chat.id(), chat.clone());
-        app.add_chat(chat.id(), chat.name().to_string());
+        apply_dialog(&mut app, &mut chat_cache, &dialog);
         count += 1;
     }
+    // Chat folders become tabs above the friends list; without them it's just "All chats"
+    match fetch_folders(&tg.client, &chat_cache).await {
+        Ok(folders) => app.set_folders(
+            folders
+                .into_iter()
+                .map(
+                    |(title, chat_ids, exclude_muted, exclude_read)| app::ChatFolder {
+                        title,
+                        chat_ids,
+                        exclude_muted,
+                        exclude_read,
+                    },
+                )
+                .collect(),
+        ),
+        Err(e) => tracing::warn!("loading chat folders failed: {}", e),
+    }
+    match fetch_blocked(&tg.client).await {
+        Ok(blocked) => {
+            for id in blocked {
+                app.set_chat_blocked(id, true);
+            }
+        }
+        Err(e) => tracing::warn!("loading blocked users failed: {}", e),
+    }
+
     // Wrap in Arc<RwLock> for sharing with async tasks (allows mutable updates for new users)
     let chat_cache = Arc::new(RwLock::new(chat_cache));
 
@@ -160,29 +424,78 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Create a channel for updates
     let (tx, mut rx) = mpsc::unbounded_channel();
-    let client_clone = tg.client.clone();
 
-    // Spawn update listener task
-    tokio::spawn(async move {
-        loop {
-            match client_clone.next_update().await {
-                Ok(Some(update)) => {
-                    if tx.send(update).is_err() {
-                        break;
-                    }
+    // Create a channel for session-expired signals from background tasks
+    let (auth_tx, mut auth_rx) = mpsc::unbounded_channel::<()>();
+
+    // Spawn update listener task (respawned after a reconnect)
+    let mut listener = spawn_update_listener(
+        tg.client.clone(),
+        tx.clone(),
+        auth_tx.clone(),
+        app.config.reconnect_backoff(),
+    );
+
+    // Periodic connection health checks
+    let (health_tx, mut health_rx) = mpsc::unbounded_channel::<HealthEvent>();
+    let mut health_tick = tokio::time::interval(HEALTH_CHECK_INTERVAL);
+    health_tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut last_focus_refresh: Option<Instant> = None; // For refresh_on_focus
+
+    // Dialogs re-read in the background for refresh_on_focus, so input never waits on them
+    type FocusRefresh = Result<
+        Vec<grammers_client::types::Dialog>,
+        grammers_client::client::chats::InvocationError,
+    >;
+    let (focus_tx, mut focus_rx) = mpsc::unbounded_channel::<FocusRefresh>();
+    let mut health_busy = false; // A check or reconnect is already running
+    let mut reconnect_failures = 0; // Failed reconnects in a row
+    let mut manual_reconnect: Option<app::ConnectionState> = None; // State before a :reconnect in flight
+
+    // Batched read marks, reporting the chats that should be retried
+    let (read_tx, mut read_rx) = mpsc::unbounded_channel::<Vec<i64>>();
+    let mut read_tick = tokio::time::interval(READ_SYNC_INTERVAL);
+    read_tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut read_busy = false; // A batch is still being sent
+
+    // Create a channel for loaded messages (chat_id, progress)
+    type LoadedMessages = (i64, LoadEvent);
+    let (msg_tx, mut msg_rx) = mpsc::unbounded_channel::<LoadedMessages>();
+
+    // Pre-warm the most recent chats (besides the one opened first) so switching to them
+    // is instant. A flood wait closes the semaphore and stops the rest
+    let prefetch: Vec<_> = {
+        let cache = chat_cache.read().await;
+        app.chats
+            .iter()
+            .filter(|c| c.id != app::WELCOME_CHAT_ID && Some(c.id) != app.current_chat_id())
+            .take(app.config.prefetch_chats)
+            .filter_map(|c| Some((c.id, cache.get(&c.id)?.clone())))
+            .collect()
+    };
+    let prefetch_permits = Arc::new(tokio::sync::Semaphore::new(PREFETCH_CONCURRENCY));
+    for (chat_id, chat) in prefetch {
+        let client = tg.client.clone();
+        let prefetch_tx = msg_tx.clone();
+        let permits = prefetch_permits.clone();
+        tokio::spawn(async move {
+            let Ok(_permit) = permits.acquire().await else {
+                return;
+            };
+            match fetch_recent(&client, &chat).await {
+                Ok(messages) => {
+                    let pinned = fetch_pinned(&client, &chat).await.unwrap_or_default();
+                    let _ = prefetch_tx.send((chat_id, LoadEvent::Prefetched(messages, pinned)));
                 }
-                Ok(None) => break,
-                Err(_) => {
-                    // Wait a bit before retrying on error
-                    tokio::time::sleep(Duration::from_secs(1)).await;
+                Err(e) => {
+                    tracing::debug!("prefetching chat {} failed: {}", chat_id, e);
+                    if flood_wait_seconds(&e).is_some() {
+                        permits.close();
+                    }
                 }
             }
-        }
-    });
-
-    // Create a channel for loaded messages (chat_id, messages)
-    type LoadedMessages = (i64, Vec<(String, String, bool)>);
-    let (msg_tx, mut msg_rx) = mpsc::unbounded_channel::<LoadedMessages>();
+        });
+    }
 
     // Create a channel for find user results
     type FindUserResult = (
@@ -191,18 +504,53 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     );
     let (find_tx, mut find_rx) = mpsc::unbounded_channel::<FindUserResult>();
 
+    // Create a channel for finished media downloads (saved path or error)
+    let (download_tx, mut download_rx) = mpsc::unbounded_channel::<Result<PathBuf, String>>();
+
+    // Create a channel for :jump fetches (chat_id, target, oldest loaded id, older messages
+    // or None if the target doesn't exist)
+    type JumpResult = (i64, i32, i32, Result<Option<Vec<LoadedMessage>>, String>);
+    let (jump_tx, mut jump_rx) = mpsc::unbounded_channel::<JumpResult>();
+
+    // Create a channel for L batches (chat_id, oldest loaded id, count asked for, older messages)
+    type OlderResult = (i64, i32, usize, Result<Vec<LoadedMessage>, String>);
+    let (older_tx, mut older_rx) = mpsc::unbounded_channel::<OlderResult>();
+
+    // Create a channel for :search results (query, matching chats and message ids)
+    type MessageSearchResult = (
+        String,
+        Result<Vec<(grammers_client::types::Chat, i32)>, String>,
+    );
+    let (search_tx, mut search_rx) = mpsc::unbounded_channel::<MessageSearchResult>();
+
     // Create AI client and channel for AI results
     let ai_config = ai::AIConfig::load();
     let ai_client = Arc::new(ai::AIClient::new(ai_config));
     type AIResult = Result<String, String>;
     let (ai_tx, mut ai_rx) = mpsc::unbounded_channel::<AIResult>();
 
+    // Idle tick so time-based UI (status expiry, mute labels) refreshes without events
+    let mut idle_tick = idle_interval(&app.config);
+
+    // Saved stickers/GIFs from the last :sticker, by document id, for sending the one picked
+    let mut sticker_cache: HashMap<i64, grammers_tl_types::enums::Document> = HashMap::new();
+
     // Main loop
     let mut reader = EventStream::new();
 
+    let mut cursor_shape = None; // Shape last set, None for the terminal's own
     loop {
         // Draw UI
         terminal.draw(|f| draw(f, &app))?;
+        update_cursor_shape(&app, &mut cursor_shape);
+
+        // Ring the bell for a mention and play sound cues (neither goes through the frame)
+        if std::mem::take(&mut app.bell_requested) {
+            sound::bell();
+        }
+        if std::mem::take(&mut app.sound_requested) {
+            sound::play(&app.config.sound_file);
+        }
 
         // Handle reloading status from previous loop
         if app.reload_requested {
@@ -214,43 +562,22 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 let mut dialogs = tg.client.iter_dialogs();
                 while let Some(dialog) = dialogs.next().await? {
                     if dialog.chat().id() == chat_id {
-                        // Clear existing messages for this chat
-                        app.messages.remove(&chat_id);
-
                         // Fetch last 50 messages
                         let mut messages_iter = tg.client.iter_messages(dialog.chat());
+                        let mut loaded = Vec::new();
                         let mut fetched = 0;
                         while let Some(msg) = messages_iter.next().await? {
                             if fetched >= 50 {
                                 break;
                             }
-                            let sender = if msg.outgoing() {
-                                "You".to_string()
-                            } else {
-                                msg.sender()
-                                    .map(|s| {
-                                        let name = s.name().to_string();
-                                        if name.is_empty() {
-                                            dialog.chat().name().to_string()
-                                        } else {
-                                            name
-                                        }
-                                    })
-                                    .unwrap_or_else(|| dialog.chat().name().to_string())
-                            };
-                            app.add_message(
-                                chat_id,
-                                sender,
-                                msg.text().to_string(),
-                                msg.outgoing(),
-                            );
+                            loaded.push(loaded_message(&msg, dialog.chat().name()));
                             fetched += 1;
                         }
 
-                        // Reverse messages to show oldest first
-                        if let Some(msgs) = app.messages.get_mut(&chat_id) {
-                            msgs.reverse();
-                        }
+                        // Replace existing messages, oldest first
+                        app.clear_messages(chat_id);
+                        add_loaded_messages(&mut app, chat_id, loaded.into_iter().rev(), false);
+                        app.mark_loaded(chat_id);
                         break;
                     }
                 }
@@ -262,7 +589,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             app.needs_message_load = false;
             if let Some(chat_id) = app.current_chat_id() {
                 // Only load if we don't have messages for this chat yet
-                if !app.messages.contains_key(&chat_id) && chat_id != 1 {
+                if !app.messages.contains_key(&chat_id) && chat_id != app::WELCOME_CHAT_ID {
                     // Check if we're already loading this chat
                     if app.pending_load != Some(chat_id) {
                         app.loading_status = Some("Loading...".to_string());
@@ -271,7 +598,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         // Spawn background loader using cached chat (O(1) lookup!)
                         let client = tg.client.clone();
                         let loader_tx = msg_tx.clone();
+                        let loader_auth_tx = auth_tx.clone();
                         let cache = chat_cache.clone();
+                        let needs_full_channel = app.chat_members(chat_id).is_none()
+                            || app.slow_mode_secs(chat_id).is_none();
                         tokio::spawn(async move {
                             // Use cached chat directly - no dialog iteration!
                             let cache_read = cache.read().await;
@@ -280,46 +610,62 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                                 let cached_chat = cached_chat.clone();
                                 drop(cache_read); // Release lock before async iteration
                                 let mut messages_iter = client.iter_messages(&cached_chat);
-                                let mut loaded_msgs: Vec<(String, String, bool)> = Vec::new();
+                                let mut loaded_msgs: Vec<LoadedMessage> = Vec::new();
                                 let mut fetched = 0;
-                                while let Ok(Some(msg)) = messages_iter.next().await {
-                                    if fetched >= 50 {
+                                loop {
+                                    let msg = match messages_iter.next().await {
+                                        Ok(Some(msg)) => msg,
+                                        Ok(None) => break,
+                                        Err(e) => {
+                                            if let Some(secs) = flood_wait_seconds(&e) {
+                                                tracing::warn!(
+                                                    "flood wait {}s loading chat {}",
+                                                    secs,
+                                                    chat_id
+                                                );
+                                                // Back off as asked, then resume where we stopped
+                                                let _ = loader_tx
+                                                    .send((chat_id, LoadEvent::RateLimited(secs)));
+                                                tokio::time::sleep(Duration::from_secs(
+                                                    secs as u64,
+                                                ))
+                                                .await;
+                                                continue;
+                                            }
+                                            tracing::warn!(
+                                                "loading chat {} failed: {}",
+                                                chat_id,
+                                                e
+                                            );
+                                            if is_auth_error(&e) {
+                                                let _ = loader_auth_tx.send(());
+                                            }
+                                            break;
+                                        }
+                                    };
+                                    if fetched >= HISTORY_PAGE {
                                         break;
                                     }
-                                    let sender = if msg.outgoing() {
-                                        "You".to_string()
-                                    } else {
-                                        msg.sender()
-                                            .map(|s| {
-                                                let name = s.name().to_string();
-                                                if name.trim().is_empty() {
-                                                    if chat_name.trim().is_empty() {
-                                                        String::new()
-                                                    } else {
-                                                        chat_name.clone()
-                                                    }
-                                                } else {
-                                                    name
-                                                }
-                                            })
-                                            .unwrap_or_else(|| {
-                                                if chat_name.trim().is_empty() {
-                                                    String::new()
-                                                } else {
-                                                    chat_name.clone()
-                                                }
-                                            })
-                                    };
-                                    loaded_msgs.push((
-                                        sender,
-                                        msg.text().to_string(),
-                                        msg.outgoing(),
-                                    ));
+                                    loaded_msgs.push(loaded_message(&msg, &chat_name));
                                     fetched += 1;
                                 }
+                                // Pinned banner is best effort, a failure just leaves it empty
+                                if let Ok(pinned) = fetch_pinned(&client, &cached_chat).await {
+                                    let _ = loader_tx.send((chat_id, LoadEvent::Pinned(pinned)));
+                                }
+                                // So are the member count and slow mode sending asks about
+                                if needs_full_channel {
+                                    if let Ok(Some(full)) =
+                                        fetch_full_channel(&client, &cached_chat).await
+                                    {
+                                        let _ =
+                                            loader_tx.send((chat_id, LoadEvent::FullChannel(full)));
+                                    }
+                                }
+
                                 // Reverse to oldest-first and send via channel
                                 loaded_msgs.reverse();
-                                let _ = loader_tx.send((chat_id, loaded_msgs));
+                                let _ = loader_tx.send((chat_id, LoadEvent::Messages(loaded_msgs)));
                             }
                         });
                     }
@@ -335,9 +681,22 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         if let Some(username) = app.find_requested.take() {
             let client = tg.client.clone();
             let find_tx_clone = find_tx.clone();
+            let find_auth_tx = auth_tx.clone();
             let username_clone = username.clone();
             tokio::spawn(async move {
-                match client.resolve_username(&username_clone).await {
+                // Retry network hiccups; "not found" and other server answers are final
+                let mut attempt = 0;
+                let result = loop {
+                    match client.resolve_username(&username_clone).await {
+                        Err(e) if attempt < FIND_RETRIES && is_transient_error(&e) => {
+                            tracing::debug!("find @{} failed, retrying: {}", username_clone, e);
+                            tokio::time::sleep(FIND_RETRY_DELAY * 2u32.pow(attempt)).await;
+                            attempt += 1;
+                        }
+                        result => break result,
+                    }
+                };
+                match result {
                     Ok(Some(chat)) => {
                         let id = chat.id();
                         let name = chat.name().to_string();
@@ -350,12 +709,465 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         ));
                     }
                     Err(e) => {
+                        tracing::warn!("find @{} failed: {}", username_clone, e);
+                        if is_auth_error(&e) {
+                            let _ = find_auth_tx.send(());
+                        }
                         let _ = find_tx_clone.send((username_clone, Err(format!("Error: {}", e))));
                     }
                 }
             });
         }
 
+        // Open a link picked from a message in the default browser
+        if let Some(url) = app.open_url_requested.take() {
+            app.status_message = Some(match open::that_detached(&url) {
+                Ok(()) => format!("Opened {}", url),
+                Err(e) => format!("Failed to open {}: {}", url, e),
+            });
+        }
+
+        // r after giving up on the connection: start over with a fresh set of attempts
+        if std::mem::take(&mut app.reconnect_requested) {
+            reconnect_failures = 0;
+            let _ = health_tx.send(HealthEvent::Dead);
+        }
+
+        // :reconnect: a new connection whatever the health checks say, then the chat list again
+        if std::mem::take(&mut app.manual_reconnect_requested) {
+            if health_busy && app.connection != app::ConnectionState::Lost {
+                app.status_message =
+                    Some("Already checking the connection, try again in a moment".to_string());
+            } else {
+                health_busy = true;
+                reconnect_failures = 0;
+                manual_reconnect = Some(app.connection);
+                let _ = health_tx.send(HealthEvent::Dead);
+            }
+        }
+
+        // Forget saved API credentials (confirmed via :forget-credentials)
+        if app.forget_credentials_requested {
+            app.forget_credentials_requested = false;
+            let env_note = if std::env::var("TELEGRAM_API_ID").is_ok() {
+                " (TELEGRAM_API_ID in the environment still takes priority)"
+            } else {
+                ""
+            };
+            app.status_message = Some(match delete_credentials() {
+                Ok(true) => format!(
+                    "API credentials deleted, you'll be asked for them next launch{}",
+                    env_note
+                ),
+                Ok(false) => format!("No saved API credentials to delete{}", env_note),
+                Err(e) => format!("Failed to delete credentials: {}", e),
+            });
+        }
+
+        // Log out another account (confirmed from the account picker)
+        if let Some(account_id) = app.log_out_account_requested.take() {
+            app.status_message = Some(match delete_session_for_account(&account_id) {
+                Ok(true) => format!(
+                    "Logged out {}, it will ask for a login code on next switch",
+                    account_id
+                ),
+                Ok(false) => format!("{} has no saved session", account_id),
+                Err(e) => format!("Failed to log out {}: {}", account_id, e),
+            });
+        }
+
+        // Mute/unmute the chat picked with :mute / :unmute
+        if let Some((chat_id, until)) = app.mute_requested.take() {
+            let chat = chat_cache.read().await.get(&chat_id).cloned();
+            if let Some(chat) = chat {
+                match set_mute_until(&tg.client, &chat, until as i32).await {
+                    Ok(()) => {
+                        app.set_chat_muted(chat_id, Some(until));
+                        app.status_message = Some(if until == 0 {
+                            "Unmuted".to_string()
+                        } else {
+                            "Muted".to_string()
+                        });
+                    }
+                    Err(e) if is_auth_error(&e) => app.session_expired = true,
+                    Err(e) => app.status_message = Some(format!("Failed to change mute: {}", e)),
+                }
+            }
+        }
+
+        // Block/unblock the user picked with :block / :unblock
+        if let Some((chat_id, block)) = app.block_requested.take() {
+            let chat = chat_cache.read().await.get(&chat_id).cloned();
+            match chat {
+                Some(chat @ grammers_client::types::Chat::User(_)) => {
+                    match set_blocked(&tg.client, &chat, block).await {
+                        Ok(()) => {
+                            app.set_chat_blocked(chat_id, block);
+                            app.status_message = Some(format!(
+                                "{} {}",
+                                if block { "Blocked" } else { "Unblocked" },
+                                chat.name()
+                            ));
+                        }
+                        Err(e) if is_auth_error(&e) => app.session_expired = true,
+                        Err(e) => {
+                            app.status_message = Some(format!("Failed to change block: {}", e))
+                        }
+                    }
+                }
+                Some(_) => app.status_message = Some("Only users can be blocked".to_string()),
+                None => {}
+            }
+        }
+
+        // Send the last failed message again (.)
+        if std::mem::take(&mut app.retry_send_requested) {
+            if let Some(failed) = app.last_failed_send.clone() {
+                let chat = chat_cache.read().await.get(&failed.chat_id).cloned();
+                match chat {
+                    Some(chat) => {
+                        send_text(&mut app, &tg.client, &chat, failed, true).await;
+                        if app.last_failed_send.is_none() {
+                            app.status_message = Some(format!("Sent to {}", chat.name()));
+                        }
+                    }
+                    None => app.status_message = Some("That chat isn't loaded".to_string()),
+                }
+            }
+        }
+
+        // Re-read the dialog list for :refresh, adding new chats and renaming old ones
+        if std::mem::take(&mut app.refresh_requested) {
+            let known = app.chats.len();
+            let mut dialogs = tg.client.iter_dialogs();
+            let mut count = 0;
+            let result = loop {
+                match dialogs.next().await {
+                    Ok(Some(dialog)) if count < MAX_CHATS => {
+                        apply_dialog(&mut app, &mut *chat_cache.write().await, &dialog);
+                        count += 1;
+                        if count % 20 == 0 {
+                            app.status_message = Some(format!("Refreshing chats… {}", count));
+                            terminal.draw(|f| draw(f, &app))?;
+                        }
+                    }
+                    Ok(_) => break Ok(()),
+                    Err(e) => break Err(e),
+                }
+            };
+            match result {
+                Ok(()) => {
+                    let added = app.chats.len() - known;
+                    app.status_message = Some(format!("Refreshed {} chats, {} new", count, added));
+                }
+                Err(e) if is_auth_error(&e) => app.session_expired = true,
+                Err(e) => app.status_message = Some(format!("Failed to refresh chats: {}", e)),
+            }
+        }
+
+        // Fetch the pinned messages for :pins, then list them
+        if let Some(chat_id) = app.pins_requested.take() {
+            let chat = chat_cache.read().await.get(&chat_id).cloned();
+            if let Some(chat) = chat {
+                match fetch_pinned(&tg.client, &chat).await {
+                    Ok(pinned) => {
+                        app.set_pinned(chat_id, pinned);
+                        app.open_pins(chat_id);
+                    }
+                    Err(e) if is_auth_error(&e) => app.session_expired = true,
+                    Err(e) => {
+                        app.status_message = Some(format!("Failed to load pinned messages: {}", e))
+                    }
+                }
+            }
+        }
+
+        // Fetch a forum's topics for T, then pick one
+        if let Some(chat_id) = app.topics_requested.take() {
+            let chat = chat_cache.read().await.get(&chat_id).cloned();
+            if let Some(chat) = chat {
+                match fetch_topics(&tg.client, &chat).await {
+                    Ok(topics) => {
+                        app.set_topics(chat_id, topics);
+                        app.open_topics(chat_id);
+                    }
+                    Err(e) if is_auth_error(&e) => app.session_expired = true,
+                    Err(e) => app.status_message = Some(format!("Failed to load topics: {}", e)),
+                }
+            }
+        }
+
+        // Fetch the saved stickers and GIFs for :sticker, then pick one
+        if let Some(query) = app.sticker_requested.take() {
+            match fetch_saved_stickers(&tg.client).await {
+                Ok(saved) => {
+                    let choices = saved.iter().map(|s| (s.id, s.label.clone())).collect();
+                    sticker_cache = saved.into_iter().map(|s| (s.id, s.document)).collect();
+                    app.set_sticker_results(&query, choices);
+                }
+                Err(e) if is_auth_error(&e) => app.session_expired = true,
+                Err(e) => app.status_message = Some(format!("Failed to load stickers: {}", e)),
+            }
+        }
+
+        // Send the sticker picked from the :sticker list
+        if let Some((chat_id, sticker_id)) = app.sticker_send_requested.take() {
+            let chat = chat_cache.read().await.get(&chat_id).cloned();
+            if let (Some(chat), Some(document)) = (chat, sticker_cache.get(&sticker_id)) {
+                match send_saved_sticker(&tg.client, &chat, document, app.send_topic()).await {
+                    Ok(sent) => {
+                        let sent = loaded_message(&sent, "");
+                        add_loaded_messages(&mut app, chat_id, [sent], false);
+                        app.note_sent();
+                    }
+                    Err(e) if is_auth_error(&e) => app.session_expired = true,
+                    Err(e) => {
+                        tracing::warn!("sending a sticker to chat {} failed: {}", chat_id, e);
+                        app.status_message = Some(format!("Failed to send sticker: {}", e));
+                    }
+                }
+            }
+        }
+
+        // Pin/unpin the message picked with p
+        if let Some((chat_id, message_id, pin)) = app.pin_requested.take() {
+            let chat = chat_cache.read().await.get(&chat_id).cloned();
+            if let Some(chat) = chat {
+                let result = if pin {
+                    tg.client.pin_message(&chat, message_id).await
+                } else {
+                    tg.client.unpin_message(&chat, message_id).await
+                };
+                match result {
+                    Ok(()) => {
+                        app.pin_applied(chat_id, message_id, pin);
+                        app.status_message = Some(if pin {
+                            "Pinned".to_string()
+                        } else {
+                            "Unpinned".to_string()
+                        });
+                    }
+                    Err(e) if is_auth_error(&e) => app.session_expired = true,
+                    Err(e) if is_permission_error(&e) => {
+                        app.status_message = Some("You can't pin messages in this chat".to_string())
+                    }
+                    Err(e) => app.status_message = Some(format!("Failed to change pin: {}", e)),
+                }
+            }
+        }
+
+        // Copy the chat link picked with Y, exporting an invite link first if needed
+        if let Some(chat_id) = app.invite_link_requested.take() {
+            let chat = chat_cache.read().await.get(&chat_id).cloned();
+            match chat {
+                Some(grammers_client::types::Chat::User(_)) | None => {
+                    app.status_message = Some("This chat has no public link".to_string())
+                }
+                Some(chat) => match export_invite_link(&tg.client, &chat).await {
+                    Ok(Some(link)) => app.copy_requested = Some(link),
+                    Ok(None) => {
+                        app.status_message = Some("This chat has no invite link".to_string())
+                    }
+                    Err(e) if is_auth_error(&e) => app.session_expired = true,
+                    Err(e) if is_permission_error(&e) => {
+                        app.status_message =
+                            Some("You can't create invite links for this chat".to_string())
+                    }
+                    Err(e) => {
+                        app.status_message = Some(format!("Failed to get invite link: {}", e))
+                    }
+                },
+            }
+        }
+        if let Some(text) = app.copy_requested.take() {
+            app.status_message = Some(copy_to_clipboard(&text));
+        }
+        if std::mem::take(&mut app.paste_requested) {
+            match clipboard::paste() {
+                Ok(text) => app.insert_pasted(&text),
+                Err(e) => app.status_message = Some(format!("Failed to paste: {}", e)),
+            }
+        }
+
+        // Send to a chat by id with :msg, resolving it first if it isn't loaded
+        if let Some((typed_id, text)) = app.send_to_id_requested.take() {
+            let cached = chat_cache
+                .read()
+                .await
+                .get(&bare_chat_id(typed_id))
+                .cloned();
+            let resolved = match cached {
+                Some(chat) => Ok(Some(chat)),
+                None => resolve_chat_id(&tg.client, typed_id).await,
+            };
+            match resolved {
+                Ok(Some(chat)) => {
+                    let chat_id = chat.id();
+                    let name = chat.name().to_string();
+                    app.add_chat(chat_id, name.clone());
+                    app.set_chat_group(
+                        chat_id,
+                        matches!(chat, grammers_client::types::Chat::Group(_)),
+                    );
+                    app.set_chat_username(chat_id, chat.username());
+                    app.set_chat_bot(chat_id, chat_is_bot(&chat));
+                    app.set_chat_channel(
+                        chat_id,
+                        matches!(chat, grammers_client::types::Chat::Channel(_)),
+                    );
+                    chat_cache.write().await.insert(chat_id, chat.clone());
+                    let (text, formats) = format::outgoing(&text, app.config.markdown);
+                    let message = grammers_client::InputMessage::text(&text)
+                        .fmt_entities(format::to_entities(&text, &formats));
+                    match tg.client.send_message(&chat, message).await {
+                        Ok(sent) => {
+                            app.add_message(
+                                chat_id,
+                                sent.id(),
+                                "You".to_string(),
+                                text,
+                                true,
+                                sent.date().timestamp(),
+                            );
+                            app.set_formats(chat_id, sent.id(), formats);
+                            app.status_message = Some(format!("Sent to {}", name));
+                        }
+                        Err(e) if is_auth_error(&e) => app.session_expired = true,
+                        Err(e) => {
+                            tracing::warn!("sending to chat {} by id failed: {}", chat_id, e);
+                            app.status_message = Some(format!("Failed to send to {}: {}", name, e));
+                        }
+                    }
+                }
+                Ok(None) => {
+                    app.status_message = Some(format!(
+                        "Chat {} doesn't exist or isn't reachable from this account",
+                        typed_id
+                    ))
+                }
+                Err(e) if is_auth_error(&e) => app.session_expired = true,
+                Err(e) => {
+                    app.status_message = Some(format!("Failed to look up chat {}: {}", typed_id, e))
+                }
+            }
+        }
+
+        // Download the media picked with :download in the background
+        if let Some((chat_id, message_id, path)) = app.download_requested.take() {
+            let chat = chat_cache.read().await.get(&chat_id).cloned();
+            if let Some(chat) = chat {
+                app.status_message = Some("Downloading...".to_string());
+                let client = tg.client.clone();
+                let download_tx = download_tx.clone();
+                tokio::spawn(async move {
+                    let result = download_message_media(&client, &chat, message_id, path).await;
+                    if let Err(e) = &result {
+                        tracing::warn!("download of message {} failed: {}", message_id, e);
+                    }
+                    let _ = download_tx.send(result);
+                });
+            }
+        }
+
+        // Fetch older history back to a :jump target that isn't loaded yet
+        if let Some((chat_id, message_id, oldest_id)) = app.jump_requested.take() {
+            let chat = chat_cache.read().await.get(&chat_id).cloned();
+            if let Some(chat) = chat {
+                app.status_message = Some("Loading older messages...".to_string());
+                let client = tg.client.clone();
+                let jump_tx = jump_tx.clone();
+                tokio::spawn(async move {
+                    let chat_name = chat.name().to_string();
+                    let mut messages_iter = client.iter_messages(&chat).offset_id(oldest_id);
+                    let mut loaded: Vec<LoadedMessage> = Vec::new();
+                    let mut found = false;
+                    let mut context = 0;
+                    let result = loop {
+                        let msg = match messages_iter.next().await {
+                            Ok(Some(msg)) => msg,
+                            Ok(None) => break Ok(found.then_some(loaded)),
+                            Err(e) => {
+                                tracing::warn!("jump to message {} failed: {}", message_id, e);
+                                break Err(e.to_string());
+                            }
+                        };
+                        if found {
+                            if context == JUMP_CONTEXT {
+                                break Ok(Some(loaded));
+                            }
+                            context += 1;
+                        } else if msg.id() < message_id {
+                            // Went past it: it doesn't exist in this chat
+                            break Ok(None);
+                        } else if loaded.len() >= JUMP_MAX_FETCH {
+                            break Err(format!("more than {} messages back", JUMP_MAX_FETCH));
+                        }
+                        found |= msg.id() == message_id;
+                        loaded.push(loaded_message(&msg, &chat_name));
+                    };
+                    let _ = jump_tx.send((chat_id, message_id, oldest_id, result));
+                });
+            }
+        }
+
+        // L: fetch a batch of the messages before the oldest loaded one
+        if let Some((chat_id, oldest_id, count)) = app.load_more_requested.take() {
+            let chat = chat_cache.read().await.get(&chat_id).cloned();
+            if let Some(chat) = chat {
+                let client = tg.client.clone();
+                let older_tx = older_tx.clone();
+                tokio::spawn(async move {
+                    let chat_name = chat.name().to_string();
+                    let mut messages_iter = client
+                        .iter_messages(&chat)
+                        .offset_id(oldest_id)
+                        .limit(count);
+                    let mut loaded: Vec<LoadedMessage> = Vec::new();
+                    let result = loop {
+                        match messages_iter.next().await {
+                            Ok(Some(msg)) => loaded.push(loaded_message(&msg, &chat_name)),
+                            Ok(None) => break Ok(loaded),
+                            Err(e) => {
+                                tracing::warn!(
+                                    "loading older messages of {} failed: {}",
+                                    chat_id,
+                                    e
+                                );
+                                break Err(e.to_string());
+                            }
+                        }
+                    };
+                    let _ = older_tx.send((chat_id, oldest_id, count, result));
+                });
+            } else {
+                app.note_load_more_failed(chat_id, None);
+            }
+        }
+
+        // Search messages in all chats in the background
+        if let Some(query) = app.message_search_requested.take() {
+            let client = tg.client.clone();
+            let search_tx = search_tx.clone();
+            tokio::spawn(async move {
+                let mut results = Vec::new();
+                let mut messages_iter = client
+                    .search_all_messages()
+                    .query(&query)
+                    .limit(SEARCH_LIMIT);
+                let result = loop {
+                    match messages_iter.next().await {
+                        Ok(Some(msg)) => results.push((msg.chat(), msg.id())),
+                        Ok(None) => break Ok(results),
+                        Err(e) => {
+                            tracing::warn!("message search failed: {}", e);
+                            break Err(e.to_string());
+                        }
+                    }
+                };
+                let _ = search_tx.send((query, result));
+            });
+        }
+
         // Handle AI request
         if let Some(ai_request) = app.ai_request.take() {
             let ai_client_clone = ai_client.clone();
@@ -364,7 +1176,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             tokio::spawn(async move {
                 let result = match ai_request {
                     AIRequest::Command(cmd) => ai_client_clone.complete(&cmd).await,
-                    AIRequest::Reply(tone) => {
+                    AIRequest::Reply(_tone) => {
                         // For now, just return an error - needs chat context
                         Err(ai::client::AIError::NotConfigured)
                     }
@@ -387,28 +1199,44 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 match maybe_event {
                     Some(Ok(Event::Key(key))) => {
                          if let Some(message_to_send) = handle_key(&mut app, key) {
+                            app.remember_chat();
                             // Send message to current chat using cached chat (O(1) lookup!)
                             if let Some(chat_id) = app.current_chat_id() {
                                 let cache_read = chat_cache.read().await;
                                 if let Some(cached_chat) = cache_read.get(&chat_id) {
                                     let cached_chat = cached_chat.clone();
                                     drop(cache_read); // Release lock before async operation
-                                    tg.client
-                                        .send_message(&cached_chat, message_to_send.clone())
-                                        .await?;
-                                    app.add_message(
-                                        chat_id,
-                                        "You".to_string(),
-                                        message_to_send,
-                                        true,
-                                    );
+                                    let outgoing = app.prepare_send(chat_id, message_to_send);
+                                    send_text(&mut app, &tg.client, &cached_chat, outgoing, false).await;
                                 }
                             }
                         }
-                        if app.should_quit || app.disconnect_requested || app.add_account_requested || app.switch_account_requested.is_some() {
+                        if app.should_quit || app.disconnect_requested || app.add_account_requested || app.switch_account_requested.is_some() || app.session_expired {
                             break;
                         }
                     }
+                    Some(Ok(Event::Paste(text))) => handle_paste(&mut app, &text),
+                    // Back from another window: catch up on unread counts (one request, not too often)
+                    Some(Ok(Event::FocusGained))
+                        if app.config.refresh_on_focus
+                            && last_focus_refresh.is_none_or(|at| at.elapsed() >= FOCUS_REFRESH_INTERVAL) =>
+                    {
+                        last_focus_refresh = Some(Instant::now());
+                        let client = tg.client.clone();
+                        let focus_tx = focus_tx.clone();
+                        tokio::spawn(async move {
+                            let mut dialogs = client.iter_dialogs();
+                            let mut fetched = Vec::new();
+                            let result = loop {
+                                match dialogs.next().await {
+                                    Ok(Some(dialog)) if fetched.len() < MAX_CHATS => fetched.push(dialog),
+                                    Ok(_) => break Ok(fetched),
+                                    Err(e) => break Err(e),
+                                }
+                            };
+                            let _ = focus_tx.send(result);
+                        });
+                    }
                     Some(Err(e)) => println!("Error: {:?}\r", e),
                     _ => {}
                 }
@@ -416,25 +1244,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
             // Handle Telegram Updates
             Some(update) = rx.recv() => {
-               if let Update::NewMessage(msg) = update {
-                    if !msg.outgoing() {
+                match update {
+                    // Sent messages are added on send, so an echo of one is already loaded
+                    Update::NewMessage(msg) if !msg.outgoing() && !app.has_message(msg.chat().id(), msg.id()) => {
                         let chat = msg.chat();
-                        // Get sender name - fallback to chat name for private chats
-                        let mut sender_name = msg.sender()
-                            .map(|s| {
-                                let name = s.name().to_string();
-                                if name.trim().is_empty() {
-                                    let cname = chat.name().to_string();
-                                    if cname.trim().is_empty() { String::new() } else { cname }
-                                } else { name }
-                            })
-                            .unwrap_or_else(|| {
-                                let cname = chat.name().to_string();
-                                if cname.trim().is_empty() { String::new() } else { cname }
-                            });
+                        // Same resolution as loaded messages; only nameless DMs are left empty
+                        let mut sender_name = message_sender(&msg, chat.name());
 
                         // If sender is still Unknown, try to refresh via dialogs
                         if sender_name == "Unknown" || sender_name.trim().is_empty() {
+                            tracing::debug!("no sender name for message {} in chat {}", msg.id(), chat.id());
                             // If it's a DM (positive ID), the chat name IS the sender name.
                             // Trust the chat name over "Unknown"
                             let mut resolved_name = chat.name().to_string();
@@ -464,30 +1283,196 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         }
 
                         app.add_chat(chat.id(), chat.name().to_string());
-                        app.add_message(chat.id(), sender_name, msg.text().to_string(), false);
+                        app.set_chat_group(chat.id(), matches!(chat, grammers_client::types::Chat::Group(_)));
+                        app.set_chat_username(chat.id(), chat.username());
+                        app.set_chat_bot(chat.id(), chat_is_bot(&chat));
+                        app.set_chat_channel(chat.id(), matches!(chat, grammers_client::types::Chat::Channel(_)));
+                        let service = service_text(&msg);
+                        let is_service = service.is_some();
+                        let (call_kind, text) = service.unwrap_or_else(|| (None, msg.text().to_string()));
+                        app.add_message(chat.id(), msg.id(), sender_name, text, false, msg.date().timestamp());
+                        if is_service {
+                            app.set_service(chat.id(), msg.id());
+                        }
+                        if let Some(origin) = forward_origin(&msg) {
+                            app.set_forwarded(chat.id(), msg.id(), origin);
+                        }
+                        let media = media_label(&msg);
+                        if let Some(kind) = media.as_ref().map(|(kind, _)| *kind).or(call_kind) {
+                            app.set_media_kind(chat.id(), msg.id(), kind);
+                        }
+                        if let Some((_, label)) = media {
+                            app.set_media(chat.id(), msg.id(), label);
+                        }
+                        if !is_service {
+                            app.set_formats(chat.id(), msg.id(), format::message_formats(&msg));
+                        }
+                        app.set_topic(chat.id(), msg.id(), message_topic(&msg));
+                        let mentioned = matches!(chat, grammers_client::types::Chat::Group(_))
+                            && app.me.as_ref().zip(msg.fmt_entities()).is_some_and(|(me, entities)| {
+                                format::mentions_user(msg.text(), entities, me.id, me.username.as_deref())
+                            });
+                        if mentioned {
+                            app.note_mention(chat.id());
+                        }
+                        app.note_new_message(chat.id());
+                        app.note_received(chat.id(), mentioned);
+                    }
+                    Update::MessageEdited(msg) => {
+                        app.edit_message(msg.chat().id(), msg.id(), msg.text().to_string());
+                        app.set_formats(msg.chat().id(), msg.id(), format::message_formats(&msg));
+                    }
+                    Update::MessageDeleted(deletion) => {
+                        // Channel deletions name their channel; all other ids are account-wide
+                        let chat_ids: Vec<i64> = match deletion.channel_id() {
+                            Some(channel_id) => vec![channel_id],
+                            None => {
+                                let cache = chat_cache.read().await;
+                                app.messages
+                                    .keys()
+                                    .filter(|id| !cache.get(id).is_some_and(|c| c.pack().is_channel()))
+                                    .copied()
+                                    .collect()
+                            }
+                        };
+                        app.delete_messages(&chat_ids, deletion.messages());
+                    }
+                    // Everything else is dropped; say what at debug level, for "my message didn't update"
+                    other => {
+                        if tracing::enabled!(tracing::Level::DEBUG) {
+                            tracing::debug!("ignored update: {}", update_kind(&other));
+                        }
                     }
                 }
             }
 
             // Handle loaded messages from background task
-            Some((chat_id, messages)) = msg_rx.recv() => {
-                // Only apply if this is still the chat we're waiting for (debounce)
-                if app.pending_load == Some(chat_id) {
-                    for (sender, text, outgoing) in messages {
-                        app.add_message(chat_id, sender, text, outgoing);
+            Some((chat_id, event)) = msg_rx.recv() => {
+                if let LoadEvent::Prefetched(messages, pinned) = event {
+                    // Skip chats that got loaded the normal way first
+                    if !app.messages.contains_key(&chat_id) {
+                        add_loaded_messages(&mut app, chat_id, messages, true);
+                        app.mark_loaded(chat_id);
+                        app.set_pinned(chat_id, pinned);
+                        // Opened while it was in flight: this load is as good as the lazy one
+                        if app.pending_load == Some(chat_id) {
+                            app.pending_load = None;
+                            app.loading_status = None;
+                        }
+                    }
+                } else if let LoadEvent::FullChannel(full) = event {
+                    if let Some(members) = full.members {
+                        app.set_chat_members(chat_id, members);
+                    }
+                    app.set_slow_mode(chat_id, full.slow_mode_secs);
+                } else if app.pending_load == Some(chat_id) {
+                    // Only apply if this is still the chat we're waiting for (debounce)
+                    match event {
+                        LoadEvent::Messages(messages) => {
+                            add_loaded_messages(&mut app, chat_id, messages, false);
+                            app.mark_loaded(chat_id);
+                            app.loading_status = None;
+                            app.pending_load = None;
+                        }
+                        LoadEvent::Pinned(pinned) => app.set_pinned(chat_id, pinned),
+                        LoadEvent::RateLimited(secs) => {
+                            app.loading_status = Some(format!("Rate limited, waiting {}s...", secs));
+                        }
+                        LoadEvent::Prefetched(..) | LoadEvent::FullChannel(_) => {}
                     }
-                    app.loading_status = None;
-                    app.pending_load = None;
                 }
                 // If user navigated away, just ignore the loaded messages
             }
 
+            // Handle finished downloads
+            Some(result) = download_rx.recv() => {
+                app.status_message = Some(match result {
+                    Ok(path) => format!("Saved to {}", path.display()),
+                    Err(e) => format!("Download failed: {}", e),
+                });
+            }
+
+            // Handle history fetched for :jump
+            Some((chat_id, message_id, oldest_id, result)) = jump_rx.recv() => {
+                // Skip it if the chat was reloaded meanwhile, it would leave a gap
+                let oldest = app.messages.get(&chat_id).and_then(|m| m.first()).map(|m| m.id);
+                match result {
+                    Ok(Some(messages)) if oldest == Some(oldest_id) => {
+                        app.status_message = None;
+                        add_loaded_messages(&mut app, chat_id, messages, true);
+                        if app.current_chat_id() == Some(chat_id) && !app.jump_to_message(message_id) {
+                            app.status_message = Some("Message not found".to_string());
+                        }
+                    }
+                    Ok(Some(_)) => {}
+                    Ok(None) => app.status_message = Some("Message not found".to_string()),
+                    Err(e) => app.status_message = Some(format!("Jump failed: {}", e)),
+                }
+            }
+
+            // Dialogs re-read on focus: apply them quietly, no status line
+            Some(result) = focus_rx.recv() => {
+                match result {
+                    Ok(dialogs) => {
+                        let mut cache = chat_cache.write().await;
+                        for dialog in &dialogs {
+                            apply_dialog(&mut app, &mut cache, dialog);
+                        }
+                    }
+                    Err(e) if is_auth_error(&e) => app.session_expired = true,
+                    Err(e) => tracing::warn!("refreshing chats on focus failed: {}", e),
+                }
+            }
+
+            // Older messages loaded with L
+            Some((chat_id, oldest_id, count, result)) = older_rx.recv() => {
+                // Skip it if the chat was reloaded meanwhile, it would leave a gap
+                let oldest = app.messages.get(&chat_id).and_then(|m| m.first()).map(|m| m.id);
+                match result {
+                    Ok(messages) if oldest == Some(oldest_id) => {
+                        let loaded = messages.len();
+                        add_loaded_messages(&mut app, chat_id, messages, true);
+                        app.note_loaded_more(chat_id, count, loaded);
+                    }
+                    Ok(_) => app.note_load_more_failed(chat_id, None),
+                    Err(e) => app.note_load_more_failed(chat_id, Some(&e)),
+                }
+            }
+
+            // Handle :search results, adding chats we didn't list yet
+            Some((query, result)) = search_rx.recv() => {
+                match result {
+                    Ok(found) => {
+                        let mut cache = chat_cache.write().await;
+                        let mut results = Vec::new();
+                        for (chat, message_id) in found {
+                            let chat_id = chat.id();
+                            if !app.chats.iter().any(|c| c.id == chat_id) {
+                                app.add_chat(chat_id, chat.name().to_string());
+                                app.set_chat_group(chat_id, matches!(chat, grammers_client::types::Chat::Group(_)));
+                                app.set_chat_username(chat_id, chat.username());
+                                app.set_chat_bot(chat_id, chat_is_bot(&chat));
+                                app.set_chat_channel(chat_id, matches!(chat, grammers_client::types::Chat::Channel(_)));
+                            }
+                            cache.entry(chat_id).or_insert(chat);
+                            results.push((chat_id, message_id));
+                        }
+                        drop(cache);
+                        app.set_message_search_results(&query, results);
+                    }
+                    Err(e) => app.status_message = Some(format!("Search failed: {}", e)),
+                }
+            }
+
             // Handle find user results
             Some((username, result)) = find_rx.recv() => {
                 match result {
                     Ok((id, name, chat)) => {
                         // Add the user to the chat list and cache
                         app.add_chat(id, name.clone());
+                        app.set_chat_username(id, chat.username());
+                        app.set_chat_bot(id, chat_is_bot(&chat));
+                        app.set_chat_channel(id, matches!(chat, grammers_client::types::Chat::Channel(_)));
                         chat_cache.write().await.insert(id, chat);
                         app.set_find_result(FindResult::Found { id, name });
                     }
@@ -501,6 +1486,146 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
             }
 
+            // Nothing happened for a while: expire transient UI and redraw
+            _ = idle_tick.tick() => app.tick(),
+
+            // Flush the read queue, a few chats at a time
+            _ = read_tick.tick(), if !read_busy && !app.read_queue.is_empty() => {
+                let chat_ids = std::mem::take(&mut app.read_queue);
+                let cache = chat_cache.read().await;
+                let chats: Vec<_> = chat_ids
+                    .into_iter()
+                    .filter_map(|id| Some((id, cache.get(&id)?.clone())))
+                    .collect();
+                drop(cache);
+                read_busy = true;
+                let client = tg.client.clone();
+                let read_tx = read_tx.clone();
+                tokio::spawn(async move {
+                    let retry = futures::stream::iter(chats)
+                        .map(|(id, chat)| {
+                            let client = client.clone();
+                            async move { (id, client.mark_as_read(&chat).await) }
+                        })
+                        .buffer_unordered(READ_SYNC_CONCURRENCY)
+                        .filter_map(|(id, result)| async move {
+                            let e = result.err()?;
+                            tracing::warn!("marking chat {} read failed: {}", id, e);
+                            (flood_wait_seconds(&e).is_some() || is_transient_error(&e)).then_some(id)
+                        })
+                        .collect::<Vec<_>>()
+                        .await;
+                    let _ = read_tx.send(retry);
+                });
+            }
+
+            // Read marks sent: requeue the ones that hit a rate limit or a network error
+            Some(retry) = read_rx.recv() => {
+                read_busy = false;
+                for id in retry {
+                    if !app.read_queue.contains(&id) {
+                        app.read_queue.push(id);
+                    }
+                }
+            }
+
+            // Ping Telegram so a connection that died during suspend gets noticed
+            _ = health_tick.tick(), if !health_busy => {
+                health_busy = true;
+                let client = tg.client.clone();
+                let health_tx = health_tx.clone();
+                tokio::spawn(async move {
+                    let ping = TelegramClient { client, account_id: None, encrypt_session: false };
+                    let alive = matches!(
+                        tokio::time::timeout(HEALTH_CHECK_TIMEOUT, ping.ping()).await,
+                        Ok(Ok(()))
+                    );
+                    let _ = health_tx.send(if alive { HealthEvent::Alive } else { HealthEvent::Dead });
+                });
+            }
+
+            // Health check results: reconnect and re-register the update listener
+            Some(event) = health_rx.recv() => {
+                match event {
+                    HealthEvent::Alive => {
+                        health_busy = false;
+                        app.connection = app::ConnectionState::Online;
+                    }
+                    HealthEvent::Dead => {
+                        if manual_reconnect.is_some() {
+                            tracing::info!("reconnecting on request");
+                            app.status_message = Some("Reconnecting to Telegram…".to_string());
+                        } else {
+                            tracing::warn!("health check failed, reconnecting");
+                        }
+                        app.connection = app::ConnectionState::Reconnecting;
+                        let proxy_url = proxy.as_ref().map(|p| p.url());
+                        let api_hash = api_hash.clone();
+                        let old = TelegramClient { client: tg.client.clone(), account_id: tg.account_id.clone(), encrypt_session: tg.encrypt_session };
+                        let health_tx = health_tx.clone();
+                        tokio::spawn(async move {
+                            let event = match old.reconnect(api_id, &api_hash, proxy_url).await {
+                                Ok(new) => HealthEvent::Reconnected(new),
+                                Err(e) => HealthEvent::ReconnectFailed(e.to_string()),
+                            };
+                            let _ = health_tx.send(event);
+                        });
+                    }
+                    HealthEvent::Reconnected(new) => {
+                        tracing::info!("reconnected to Telegram");
+                        health_busy = false;
+                        reconnect_failures = 0;
+                        app.stats.reconnects += 1;
+                        listener.abort();
+                        tg = new;
+                        listener = spawn_update_listener(tg.client.clone(), tx.clone(), auth_tx.clone(), app.config.reconnect_backoff());
+                        app.connection = app::ConnectionState::Online;
+                        app.status_message = Some("Reconnected to Telegram".to_string());
+                        // Chats that changed while the old connection was stuck
+                        if manual_reconnect.take().is_some() {
+                            app.refresh_requested = true;
+                        }
+                    }
+                    HealthEvent::ReconnectFailed(e) if manual_reconnect.is_some() => {
+                        // Keep the old connection; the next health check says whether it still works
+                        tracing::warn!("reconnect on request failed: {}", e);
+                        health_busy = false;
+                        app.connection = manual_reconnect.take().unwrap_or_default();
+                        app.status_message = Some(format!(
+                            "Reconnect failed, keeping the current connection: {}", e
+                        ));
+                    }
+                    HealthEvent::ReconnectFailed(e) => {
+                        tracing::warn!("reconnect failed: {}", e);
+                        reconnect_failures += 1;
+                        let attempts = app.config.reconnect_attempts;
+                        if attempts > 0 && reconnect_failures >= attempts {
+                            // Health checks stay paused (health_busy) until r asks to retry
+                            app.connection = app::ConnectionState::Lost;
+                            app.status_message = Some("Connection lost — press r to retry".to_string());
+                        } else {
+                            let delay = app.config.reconnect_backoff().delay(reconnect_failures - 1);
+                            app.connection = app::ConnectionState::Offline;
+                            app.status_message = Some(format!(
+                                "Reconnect failed, retrying in {}s: {}", delay.as_secs(), e
+                            ));
+                            let health_tx = health_tx.clone();
+                            tokio::spawn(async move {
+                                tokio::time::sleep(delay).await;
+                                let _ = health_tx.send(HealthEvent::Dead);
+                            });
+                        }
+                    }
+                }
+            }
+
+            // Session revoked/expired server-side: leave the TUI and log in again
+            Some(()) = auth_rx.recv() => {
+                tracing::warn!("session expired or revoked, logging in again");
+                app.session_expired = true;
+                break;
+            }
+
             // Handle AI results
             Some(result) = ai_rx.recv() => {
                 match result {
@@ -521,9 +1646,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     // Restore terminal
-    disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-    terminal.show_cursor()?;
+    restore_terminal(key_enhancement);
 
     // Handle disconnect request
     if app.disconnect_requested {
@@ -534,31 +1657,36 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             Ok(false) => println!("⚠️ No session file found."),
             Err(e) => println!("❌ Failed to delete session: {}", e),
         }
+    } else if app.session_expired {
+        let attempt = reauth_attempt + 1;
+        if attempt > MAX_REAUTH_ATTEMPTS {
+            eprintln!(
+                "❌ Telegram keeps rejecting this session. Giving up after {} re-logins.",
+                MAX_REAUTH_ATTEMPTS
+            );
+            eprintln!("   Check the account on another device, then run vimgram again.");
+            std::process::exit(1);
+        }
+
+        // The stale session is useless; drop it but keep the registry entry
+        println!("🔑 Your Telegram session expired or was revoked. Logging in again...");
+        let deleted = if account_registry.has_accounts() {
+            delete_session_for_account(&account_registry.active)
+        } else {
+            delete_session()
+        };
+        if let Err(e) = deleted {
+            eprintln!("⚠️ Failed to delete stale session: {}", e);
+        }
+
+        std::env::set_var(REAUTH_ATTEMPT_VAR, attempt.to_string());
+        return Ok(Exit::Restart("Run vimgram again to log in."));
     } else if let Some(account_id) = app.switch_account_requested {
         // Switch to the selected account and auto-restart
         account_registry.set_active(&account_id);
         let _ = account_registry.save();
         println!("🔄 Switching to account: {}...", account_id);
-
-        // Auto-restart by exec'ing ourselves
-        let exe = std::env::current_exe().expect("Failed to get current executable");
-        let args: Vec<String> = std::env::args().collect();
-
-        // Use exec to replace current process (Unix-like systems)
-        #[cfg(unix)]
-        {
-            use std::os::unix::process::CommandExt;
-            let mut cmd = std::process::Command::new(&exe);
-            cmd.args(&args[1..]);
-            let err = cmd.exec();
-            eprintln!("Failed to restart: {}", err);
-        }
-
-        // On non-Unix, just tell user to restart
-        #[cfg(not(unix))]
-        {
-            println!("   Run vimgram again to load the account.");
-        }
+        return Ok(Exit::Restart("Run vimgram again to load the account."));
     } else if app.add_account_requested {
         // Create a new account entry and set it as active (session doesn't exist yet)
         let new_id = format!("account_{}", account_registry.accounts.len() + 1);
@@ -566,30 +1694,670 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             id: new_id.clone(),
             phone: "New".to_string(),
             name: "New Account".to_string(),
+            accent: None,
         });
         account_registry.set_active(&new_id);
         let _ = account_registry.save();
 
         // Auto-restart for new account authentication
         println!("➕ Adding new account...");
-        let exe = std::env::current_exe().expect("Failed to get current executable");
-        let args: Vec<String> = std::env::args().collect();
+        return Ok(Exit::Restart(
+            "Run vimgram again to authenticate the new account.",
+        ));
+    } else {
+        println!("👋 Goodbye!");
+    }
+    Ok(Exit::Quit)
+}
 
-        #[cfg(unix)]
+/// Run the UI on synthetic data from demo.rs, with no Telegram client at all
+async fn run_demo(
+    config: config::Config,
+    config_warning: Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen, EnableBracketedPaste)?;
+    let key_enhancement = enable_key_enhancement(&config);
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    let mut app = App::new();
+    app.config = config;
+    app.scratchpad = scratchpad::load();
+    if app.config.show_welcome {
+        app.add_chat(app::WELCOME_CHAT_ID, "Welcome".to_string());
+    }
+    demo::populate(&mut app);
+    app.status_message = Some(
+        config_warning
+            .unwrap_or_else(|| "Demo mode: offline, nothing is sent to Telegram".to_string()),
+    );
+
+    let mut idle_tick = idle_interval(&app.config);
+    let mut reader = EventStream::new();
+    let mut cursor_shape = None;
+    loop {
+        terminal.draw(|f| draw(f, &app))?;
+        update_cursor_shape(&app, &mut cursor_shape);
+
+        // Everything is already loaded; network actions are applied locally or refused
+        app.needs_message_load = false;
+        app.reload_requested = false;
+        if let Some(url) = app.open_url_requested.take() {
+            app.status_message = Some(match open::that_detached(&url) {
+                Ok(()) => format!("Opened {}", url),
+                Err(e) => format!("Failed to open {}: {}", url, e),
+            });
+        }
+        if let Some((chat_id, until)) = app.mute_requested.take() {
+            app.set_chat_muted(chat_id, Some(until));
+        }
+        if let Some((chat_id, block)) = app.block_requested.take() {
+            app.set_chat_blocked(chat_id, block);
+            app.status_message = Some(if block { "Blocked" } else { "Unblocked" }.to_string());
+        }
+        if let Some((chat_id, message_id, pin)) = app.pin_requested.take() {
+            app.pin_applied(chat_id, message_id, pin);
+        }
+        if std::mem::take(&mut app.refresh_requested) {
+            app.status_message = Some("Refreshed chats (demo mode)".to_string());
+        }
+        if std::mem::take(&mut app.manual_reconnect_requested) {
+            app.status_message = Some("Reconnected (demo mode)".to_string());
+        }
+        if let Some(chat_id) = app.pins_requested.take() {
+            app.open_pins(chat_id);
+        }
+        if let Some(chat_id) = app.topics_requested.take() {
+            app.open_topics(chat_id);
+        }
+        if let Some(query) = app.sticker_requested.take() {
+            app.set_sticker_results(&query, demo::saved_stickers());
+        }
+        if let Some((chat_id, sticker_id)) = app.sticker_send_requested.take() {
+            let label = app
+                .sticker_choices
+                .iter()
+                .find(|(id, _)| *id == sticker_id)
+                .map(|(_, label)| label.split(" (").next().unwrap_or(label).to_string());
+            if let Some(label) = label {
+                let id = app.current_messages().last().map_or(1, |m| m.id + 1);
+                app.add_message(
+                    chat_id,
+                    id,
+                    "You".to_string(),
+                    String::new(),
+                    true,
+                    app::unix_now(),
+                );
+                app.set_media(chat_id, id, label);
+                app.set_media_kind(chat_id, id, MediaKind::Sticker);
+                app.set_topic(chat_id, id, app.send_topic());
+                app.note_sent();
+            }
+        }
+        if let Some(username) = app.find_requested.take() {
+            app.set_find_result(FindResult::NotFound(username));
+        }
+        if app.ai_request.take().is_some() {
+            app.set_ai_error("AI is not available in demo mode".to_string());
+        }
+        if app.invite_link_requested.take().is_some() {
+            app.status_message = Some("This chat has no public link".to_string());
+        }
+        if let Some(text) = app.copy_requested.take() {
+            app.status_message = Some(copy_to_clipboard(&text));
+        }
+        if std::mem::take(&mut app.paste_requested) {
+            match clipboard::paste() {
+                Ok(text) => app.insert_pasted(&text),
+                Err(e) => app.status_message = Some(format!("Failed to paste: {}", e)),
+            }
+        }
+        if app.send_to_id_requested.take().is_some() {
+            app.status_message = Some("Sending by id is not available in demo mode".to_string());
+        }
+        if app.download_requested.take().is_some() {
+            app.status_message = Some("Downloads are not available in demo mode".to_string());
+        }
+        app.read_queue.clear();
+        if let Some(query) = app.message_search_requested.take() {
+            // Only what's loaded, which in the demo is everything
+            let results = app.search_loaded(&query);
+            app.set_message_search_results(&query, results);
+        }
+        if app.jump_requested.take().is_some() {
+            // Demo chats are fully loaded, so anything older doesn't exist
+            app.status_message = Some("Message not found".to_string());
+        }
+        if let Some((chat_id, _, count)) = app.load_more_requested.take() {
+            app.note_loaded_more(chat_id, count, 0);
+        }
+        if app.forget_credentials_requested
+            || app.log_out_account_requested.is_some()
+            || app.disconnect_requested
+            || app.add_account_requested
+            || app.switch_account_requested.is_some()
         {
-            use std::os::unix::process::CommandExt;
-            let mut cmd = std::process::Command::new(&exe);
-            cmd.args(&args[1..]);
-            let err = cmd.exec();
-            eprintln!("Failed to restart: {}", err);
+            app.forget_credentials_requested = false;
+            app.log_out_account_requested = None;
+            app.disconnect_requested = false;
+            app.add_account_requested = false;
+            app.switch_account_requested = None;
+            app.status_message = Some("Accounts can't be changed in demo mode".to_string());
+        }
+
+        let event = tokio::select! {
+            event = reader.next().fuse() => event,
+            _ = idle_tick.tick() => {
+                app.tick();
+                continue;
+            }
+        };
+        match event {
+            Some(Ok(Event::Key(key))) => {
+                if let Some(text) = handle_key(&mut app, key) {
+                    app.remember_chat();
+                    if let Some(chat_id) = app.current_chat_id() {
+                        let id = app.current_messages().last().map_or(1, |m| m.id + 1);
+                        let (text, formats) = format::outgoing(&text, app.config.markdown);
+                        app.add_message(
+                            chat_id,
+                            id,
+                            "You".to_string(),
+                            text,
+                            true,
+                            app::unix_now(),
+                        );
+                        app.set_formats(chat_id, id, formats);
+                        app.set_topic(chat_id, id, app.send_topic());
+                        app.note_sent();
+                    }
+                    app.reply_to = None;
+                    app.reply_quote = None;
+                }
+                if app.should_quit {
+                    break;
+                }
+            }
+            Some(Ok(Event::Paste(text))) => handle_paste(&mut app, &text),
+            Some(Err(e)) => println!("Error: {:?}\r", e),
+            None => break,
+            _ => {}
+        }
+    }
+
+    restore_terminal(key_enhancement);
+    println!("👋 Goodbye!");
+    Ok(())
+}
+
+/// Add fetched messages to a chat, oldest first, with their media, formatting
+/// and other details. `older` ones come from further back than what's loaded
+fn add_loaded_messages(
+    app: &mut App,
+    chat_id: i64,
+    messages: impl IntoIterator<Item = LoadedMessage>,
+    older: bool,
+) {
+    for msg in messages {
+        let id = msg.id;
+        if older {
+            app.add_older_message(chat_id, id, msg.sender, msg.text, msg.outgoing, msg.date);
+        } else {
+            app.add_message(chat_id, id, msg.sender, msg.text, msg.outgoing, msg.date);
+        }
+        if msg.service {
+            app.set_service(chat_id, id);
         }
+        if let Some(origin) = msg.forwarded_from {
+            app.set_forwarded(chat_id, id, origin);
+        }
+        if let Some(label) = msg.media {
+            app.set_media(chat_id, id, label);
+        }
+        if let Some(kind) = msg.media_kind {
+            app.set_media_kind(chat_id, id, kind);
+        }
+        if !msg.formats.is_empty() {
+            app.set_formats(chat_id, id, msg.formats);
+        }
+        if msg.topic.is_some() {
+            app.set_topic(chat_id, id, msg.topic);
+        }
+    }
+}
+
+/// Add a dialog's chat to the list and cache, or bring an existing entry up
+/// to date (name, preview, mute and read state)
+fn apply_dialog(
+    app: &mut App,
+    chat_cache: &mut HashMap<i64, grammers_client::types::Chat>,
+    dialog: &grammers_client::types::Dialog,
+) {
+    let chat = dialog.chat();
+    chat_cache.insert(chat.id(), chat.clone());
+    app.add_chat(chat.id(), chat.name().to_string());
+    app.rename_chat(chat.id(), chat.name());
+    if let Some(msg) = &dialog.last_message {
+        let last = loaded_message(msg, chat.name());
+        let text = match last.media {
+            Some(label) if last.text.trim().is_empty() => {
+                ui::glyphs::glyphs(&app.config).with_media(last.media_kind, &label)
+            }
+            _ => last.text,
+        };
+        app.set_chat_preview(chat.id(), text, last.outgoing);
+    }
+    app.set_chat_muted(chat.id(), dialog_mute_until(dialog).map(i64::from));
+    if let Some((read_max_id, unread, mentions)) = dialog_read_state(dialog) {
+        app.set_read_state(chat.id(), read_max_id, unread, mentions);
+    }
+    app.set_chat_group(
+        chat.id(),
+        matches!(chat, grammers_client::types::Chat::Group(_)),
+    );
+    app.set_chat_username(chat.id(), chat.username());
+    app.set_chat_bot(chat.id(), chat_is_bot(chat));
+    app.set_chat_channel(
+        chat.id(),
+        matches!(chat, grammers_client::types::Chat::Channel(_)),
+    );
+    app.set_chat_forum(chat.id(), chat_is_forum(chat));
+    if let Some(members) = chat_members(chat) {
+        app.set_chat_members(chat.id(), members);
+    }
+}
 
-        #[cfg(not(unix))]
+/// Send a typed message, adding it to the chat once the server has it. A
+/// reply with a quote falls back to a plain reply if the server won't take
+/// the quote. A failure is kept in `last_failed_send` for `.` to retry, which
+/// passes `retrying` so slow mode doesn't put the text back in the input
+async fn send_text(
+    app: &mut App,
+    client: &grammers_client::Client,
+    chat: &grammers_client::types::Chat,
+    outgoing: OutgoingMessage,
+    retrying: bool,
+) {
+    let chat_id = chat.id();
+    let (reply_to, topic) = (outgoing.reply_to, outgoing.topic);
+    let (text, formats) = format::outgoing(&outgoing.text, app.config.markdown);
+    let entities = format::to_entities(&text, &formats);
+    let quoted = reply_to
+        .zip(outgoing.quote.as_ref())
+        .and_then(|(id, range)| {
+            app.quote_for(chat_id, id, range)
+                .map(|(quoted, offset)| (id, quoted, offset))
+        });
+    let mut result = None;
+    if let Some((id, quoted, offset)) = quoted {
+        match send_quote_reply(
+            client,
+            chat,
+            &text,
+            entities.clone(),
+            id,
+            (&quoted, offset),
+            topic,
+        )
+        .await
         {
-            println!("   Run vimgram again to authenticate the new account.");
+            Err(e) if is_quote_error(&e) => {
+                tracing::debug!(
+                    "quote rejected in chat {}, replying without it: {}",
+                    chat_id,
+                    e
+                )
+            }
+            sent => result = Some(sent),
+        }
+    }
+    let result = match result {
+        Some(sent) => sent,
+        None => {
+            let message = grammers_client::InputMessage::text(&text)
+                .fmt_entities(entities)
+                .reply_to(reply_to.or(topic));
+            client
+                .send_message(chat, message)
+                .await
+                .map(|sent| Some((sent.id(), sent.date().timestamp())))
+        }
+    };
+    match result {
+        Ok(sent) => {
+            // Without the id it shows up when the chat is next loaded
+            if let Some((id, date)) = sent {
+                app.add_message(chat_id, id, "You".to_string(), text, true, date);
+                app.set_formats(chat_id, id, formats);
+                app.set_topic(chat_id, id, topic);
+            }
+            app.note_sent();
+            app.note_send_succeeded(&outgoing);
+            // Slow mode groups make us wait before the next one
+            if app.slow_mode_secs(chat_id).is_none() {
+                match fetch_full_channel(client, chat).await {
+                    Ok(Some(full)) => {
+                        if let Some(members) = full.members {
+                            app.set_chat_members(chat_id, members);
+                        }
+                        app.set_slow_mode(chat_id, full.slow_mode_secs);
+                    }
+                    Ok(None) => app.set_slow_mode(chat_id, 0),
+                    Err(e) => {
+                        tracing::debug!("checking slow mode of chat {} failed: {}", chat_id, e)
+                    }
+                }
+            }
+            app.start_slow_mode(chat_id, app.slow_mode_secs(chat_id).unwrap_or(0));
+        }
+        Err(e) if slow_mode_wait_seconds(&e).is_some() => {
+            // Sent too soon: keep the message to send once the wait is over
+            let secs = slow_mode_wait_seconds(&e).unwrap_or(0);
+            app.start_slow_mode(chat_id, secs);
+            if retrying {
+                app.status_message = Some(format!("Slow mode: wait {}s (. retries)", secs));
+            } else {
+                app.input = outgoing.text;
+                app.reply_to = outgoing.reply_to;
+                app.reply_quote = outgoing.quote;
+                app.status_message = Some(format!("Slow mode: wait {}s", secs));
+            }
+        }
+        Err(e) if is_auth_error(&e) => {
+            tracing::warn!("send rejected, session expired: {}", e);
+            app.session_expired = true
         }
+        Err(e) => {
+            tracing::error!("sending to chat {} failed: {}", chat_id, e);
+            app.note_send_failed(outgoing, &e.to_string());
+        }
+    }
+}
+
+/// Put text on the clipboard, returning the status line to show. Multi-line
+/// text (a visual selection) is summed up rather than echoed
+fn copy_to_clipboard(text: &str) -> String {
+    let what = match text.lines().count() {
+        0 | 1 => text.to_string(),
+        lines => format!("{} lines", lines),
+    };
+    match clipboard::copy(text) {
+        Ok(()) => format!("Copied {}", what),
+        Err(e) => format!("Failed to copy {}: {}", what, e),
+    }
+}
+
+/// The latest page of a chat's history, oldest first
+async fn fetch_recent(
+    client: &grammers_client::Client,
+    chat: &grammers_client::types::Chat,
+) -> Result<Vec<LoadedMessage>, grammers_client::client::chats::InvocationError> {
+    let chat_name = chat.name().to_string();
+    let mut messages_iter = client.iter_messages(chat).limit(HISTORY_PAGE);
+    let mut loaded = Vec::new();
+    while let Some(msg) = messages_iter.next().await? {
+        loaded.push(loaded_message(&msg, &chat_name));
+    }
+    loaded.reverse();
+    Ok(loaded)
+}
+
+/// Convert a fetched message for the app, falling back to the chat name for
+/// senders without one
+fn loaded_message(msg: &grammers_client::types::Message, chat_name: &str) -> LoadedMessage {
+    let sender = message_sender(msg, chat_name);
+    let service = service_text(msg);
+    let formats = if service.is_some() {
+        Vec::new()
     } else {
-        println!("👋 Goodbye!");
+        format::message_formats(msg)
+    };
+    let is_service = service.is_some();
+    let (call_kind, text) = service.unwrap_or_else(|| (None, msg.text().to_string()));
+    let media = media_label(msg);
+    LoadedMessage {
+        id: msg.id(),
+        sender,
+        text,
+        outgoing: msg.outgoing(),
+        date: msg.date().timestamp(),
+        forwarded_from: forward_origin(msg),
+        media_kind: media.as_ref().map(|(kind, _)| *kind).or(call_kind),
+        media: media.map(|(_, label)| label),
+        service: is_service,
+        formats,
+        topic: message_topic(msg),
     }
-    Ok(())
+}
+
+/// Who a message is from (see `app::resolve_sender`), with `chat_name` for the
+/// name of its chat
+fn message_sender(msg: &grammers_client::types::Message, chat_name: &str) -> String {
+    let chat = msg.chat();
+    let sender = msg.sender();
+    app::resolve_sender(
+        msg.outgoing(),
+        msg.post(),
+        sender.as_ref().map(|s| (s.id(), s.name())),
+        chat.id(),
+        chat_name,
+        matches!(chat, grammers_client::types::Chat::Group(_)),
+    )
+}
+
+/// Ask the terminal to report Ctrl+Enter distinctly when it sends messages
+///
+/// Returns whether the flags were pushed and need popping on exit. Terminals
+/// without the kitty keyboard protocol still get Ctrl+J as the send key.
+fn enable_key_enhancement(config: &config::Config) -> bool {
+    if config.send_on != config::SendKey::CtrlEnter
+        || !supports_keyboard_enhancement().unwrap_or(false)
+    {
+        return false;
+    }
+    execute!(
+        io::stdout(),
+        PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES)
+    )
+    .is_ok()
+}
+
+/// Timer for the main loop's idle tick, at the configured interval
+fn idle_interval(config: &config::Config) -> tokio::time::Interval {
+    let mut tick = tokio::time::interval(Duration::from_millis(config.tick_interval_ms));
+    tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    tick
+}
+
+/// Forward Telegram updates into the main loop until the client goes away
+fn spawn_update_listener(
+    client: grammers_client::Client,
+    tx: mpsc::UnboundedSender<Update>,
+    auth_tx: mpsc::UnboundedSender<()>,
+    backoff: config::Backoff,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut failures = 0;
+        loop {
+            match client.next_update().await {
+                Ok(Some(update)) => {
+                    failures = 0;
+                    if tx.send(update).is_err() {
+                        break;
+                    }
+                }
+                Ok(None) => break,
+                Err(e) if is_auth_error(&e) => {
+                    // Retrying can't help once the session is revoked
+                    let _ = auth_tx.send(());
+                    break;
+                }
+                Err(e) => {
+                    tracing::warn!("update listener error: {}", e);
+                    // Back off before retrying, longer each time it keeps failing
+                    tokio::time::sleep(backoff.delay(failures)).await;
+                    failures += 1;
+                }
+            }
+        }
+    })
+}
+
+/// Kind of an update for the log, e.g. "CallbackQuery" or "Raw(UserStatus)",
+/// with the chat and message ids of message updates
+fn update_kind(update: &Update) -> String {
+    match update {
+        Update::NewMessage(msg) => format!(
+            "NewMessage (chat {}, message {}{})",
+            msg.chat().id(),
+            msg.id(),
+            if msg.outgoing() { ", outgoing" } else { "" }
+        ),
+        Update::MessageEdited(msg) => {
+            format!(
+                "MessageEdited (chat {}, message {})",
+                msg.chat().id(),
+                msg.id()
+            )
+        }
+        Update::MessageDeleted(_) => "MessageDeleted".to_string(),
+        Update::CallbackQuery(_) => "CallbackQuery".to_string(),
+        Update::InlineQuery(_) => "InlineQuery".to_string(),
+        Update::Raw(raw) => {
+            // The Debug output starts with the variant's name
+            let debug = format!("{:?}", raw);
+            let name = debug
+                .split(|c: char| !c.is_alphanumeric())
+                .next()
+                .unwrap_or_default();
+            format!("Raw({})", name)
+        }
+        _ => "unknown".to_string(),
+    }
+}
+
+/// Whether a `--name` switch was passed on the command line
+fn cli_flag(name: &str) -> bool {
+    std::env::args().skip(1).any(|arg| arg == name)
+}
+
+/// List every account and whether its saved session will work, so it's
+/// clear up front which ones are going to ask for a login
+fn print_account_summary(registry: &AccountRegistry) {
+    println!("👥 Accounts:");
+    for account in &registry.accounts {
+        let marker = if account.id == registry.active {
+            "●"
+        } else {
+            " "
+        };
+        let status = match AccountRegistry::session_health(&account.id) {
+            SessionHealth::Valid => "✅ ready",
+            SessionHealth::Missing => "⚠️ needs login (no session)",
+            SessionHealth::SignedOut => "⚠️ needs login (login never finished)",
+            SessionHealth::Corrupt => "❌ needs login (session file unreadable)",
+            SessionHealth::Encrypted => "🔐 encrypted",
+        };
+        println!(
+            "   {} {} ({}) {}",
+            marker, account.name, account.phone, status
+        );
+    }
+}
+
+/// Value of a `--name <value>` or `--name=<value>` command line option, if given
+fn cli_option(name: &str) -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == name {
+            return args.next();
+        }
+        if let Some(value) = arg.strip_prefix(name).and_then(|v| v.strip_prefix('=')) {
+            return Some(value.to_string());
+        }
+    }
+    None
+}
+
+/// Start vimgram over as a fresh process with the same arguments. Only
+/// returns if that couldn't be done, with the reason
+fn restart() -> io::Error {
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => return e,
+    };
+    let mut cmd = std::process::Command::new(exe);
+    cmd.args(std::env::args().skip(1));
+
+    // Replace this process (Unix-like systems)
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.exec()
+    }
+
+    // No exec elsewhere: run the new process in this console and pass on its exit code
+    #[cfg(not(unix))]
+    match cmd.status() {
+        Ok(status) => std::process::exit(status.code().unwrap_or(1)),
+        Err(e) => e,
+    }
+}
+
+/// Set once vimgram changes the cursor shape, so exiting puts back the terminal's own
+static CURSOR_SHAPED: AtomicBool = AtomicBool::new(false);
+
+/// Give the cursor the shape configured for the current mode: `insert_cursor`
+/// while typing a message, `normal_cursor` otherwise. Only writes to the
+/// terminal when the shape changes
+fn update_cursor_shape(app: &App, shown: &mut Option<config::CursorShape>) {
+    if !app.config.cursor_shapes {
+        // Turned off with :set: back to the terminal's own
+        if shown.take().is_some() {
+            let _ = execute!(io::stdout(), SetCursorStyle::DefaultUserShape);
+        }
+        return;
+    }
+    let shape = if app.mode == app::Mode::Insert {
+        app.config.insert_cursor
+    } else {
+        app.config.normal_cursor
+    };
+    if *shown == Some(shape) {
+        return;
+    }
+    let style = match shape {
+        config::CursorShape::Block => SetCursorStyle::SteadyBlock,
+        config::CursorShape::Bar => SetCursorStyle::SteadyBar,
+        config::CursorShape::Underline => SetCursorStyle::SteadyUnderScore,
+        config::CursorShape::BlinkingBlock => SetCursorStyle::BlinkingBlock,
+        config::CursorShape::BlinkingBar => SetCursorStyle::BlinkingBar,
+        config::CursorShape::BlinkingUnderline => SetCursorStyle::BlinkingUnderScore,
+    };
+    let _ = execute!(io::stdout(), style);
+    CURSOR_SHAPED.store(true, Ordering::Relaxed);
+    *shown = Some(shape);
+}
+
+/// Put the terminal back the way it was. Best effort: every step runs even if
+/// one before it fails, so the shell is never left in raw mode
+fn restore_terminal(pop_keyboard: bool) {
+    let mut stdout = io::stdout();
+    if pop_keyboard {
+        let _ = execute!(stdout, PopKeyboardEnhancementFlags);
+    }
+    if CURSOR_SHAPED.load(Ordering::Relaxed) {
+        let _ = execute!(stdout, SetCursorStyle::DefaultUserShape);
+    }
+    let _ = disable_raw_mode();
+    let _ = execute!(
+        stdout,
+        DisableBracketedPaste,
+        DisableFocusChange,
+        LeaveAlternateScreen,
+        crossterm::cursor::Show
+    );
 }
@@ -0,0 +1,161 @@
+/// Actions that can be run from the command palette (Ctrl+P)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PaletteAction {
+    SearchChats,
+    FilterChats,
+    JumpToChatNumber,
+    FindUser,
+    SearchMessages,
+    JumpToMessage,
+    LoadMore,
+    ReplyToLatest,
+    RetrySend,
+    PasteClipboard,
+    OpenLink,
+    TogglePin,
+    NextPinned,
+    PinnedList,
+    Topics,
+    Sticker,
+    CopyChatLink,
+    Mute,
+    Unmute,
+    NotifyLevel,
+    Download,
+    Export,
+    Reload,
+    RefreshChats,
+    Reconnect,
+    Scratchpad,
+    AiCommand,
+    CodeAssistant,
+    SwitchAccount,
+    WhoAmI,
+    Stats,
+    Lock,
+    SetOption,
+    ForgetCredentials,
+    Disconnect,
+    Quit,
+}
+
+/// A palette entry: what it does, what it's called and its usual key or command
+pub struct PaletteEntry {
+    pub action: PaletteAction,
+    pub name: &'static str,
+    pub keys: &'static str,
+}
+
+const fn entry(action: PaletteAction, name: &'static str, keys: &'static str) -> PaletteEntry {
+    PaletteEntry { action, name, keys }
+}
+
+/// Every palette entry, in the order shown before anything is typed
+pub const ENTRIES: &[PaletteEntry] = &[
+    entry(PaletteAction::SearchChats, "Search chats", "/"),
+    entry(PaletteAction::FilterChats, "Filter chats by type", "F"),
+    entry(
+        PaletteAction::JumpToChatNumber,
+        "Jump to chat by number",
+        "f",
+    ),
+    entry(PaletteAction::FindUser, "Find user", ":find"),
+    entry(PaletteAction::SearchMessages, "Search messages", ":search"),
+    entry(PaletteAction::JumpToMessage, "Jump to message", ":jump"),
+    entry(PaletteAction::LoadMore, "Load older messages", "L"),
+    entry(PaletteAction::ReplyToLatest, "Reply to latest message", "R"),
+    entry(PaletteAction::RetrySend, "Retry failed message", "."),
+    entry(PaletteAction::PasteClipboard, "Paste clipboard", "Ctrl+V"),
+    entry(PaletteAction::OpenLink, "Open link", "o"),
+    entry(PaletteAction::TogglePin, "Pin / unpin message", "p"),
+    entry(PaletteAction::NextPinned, "Next pinned message", "P"),
+    entry(PaletteAction::PinnedList, "List pinned messages", ":pins"),
+    entry(PaletteAction::Topics, "Forum topics", "T"),
+    entry(PaletteAction::Sticker, "Send sticker or GIF", ":sticker"),
+    entry(PaletteAction::CopyChatLink, "Copy chat link", "Y"),
+    entry(PaletteAction::Mute, "Mute chat", ":mute"),
+    entry(PaletteAction::Unmute, "Unmute chat", ":unmute"),
+    entry(PaletteAction::NotifyLevel, "Cycle notification level", "b"),
+    entry(PaletteAction::Download, "Download media", ":download"),
+    entry(PaletteAction::Export, "Export chat", ":export"),
+    entry(PaletteAction::Reload, "Reload chat", "r"),
+    entry(PaletteAction::RefreshChats, "Refresh chat list", ":refresh"),
+    entry(
+        PaletteAction::Reconnect,
+        "Reconnect to Telegram",
+        ":reconnect",
+    ),
+    entry(PaletteAction::Scratchpad, "Scratchpad notes", "S"),
+    entry(PaletteAction::AiCommand, "AI command", ":ai"),
+    entry(PaletteAction::CodeAssistant, "Code assistant", ":code"),
+    entry(PaletteAction::SwitchAccount, "Switch account", "A"),
+    entry(PaletteAction::WhoAmI, "Who am I", ":whoami"),
+    entry(PaletteAction::Stats, "Session stats", ":stats"),
+    entry(PaletteAction::Lock, "Lock screen", ":lock"),
+    entry(PaletteAction::SetOption, "Change setting", ":set"),
+    entry(
+        PaletteAction::ForgetCredentials,
+        "Forget API credentials",
+        ":forget-credentials",
+    ),
+    entry(PaletteAction::Disconnect, "Disconnect (log out)", "D"),
+    entry(PaletteAction::Quit, "Quit", "q"),
+];
+
+/// How well `query` fuzzy-matches `name`, None if it doesn't: the query's
+/// characters must appear in order (case-insensitive, spaces ignored). Runs of
+/// consecutive characters and matches at word starts score higher
+pub fn fuzzy_score(query: &str, name: &str) -> Option<u32> {
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut next = 0;
+    let mut previous: Option<usize> = None;
+    for c in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = next + name[next..].iter().position(|&n| n == c)?;
+        score += 1;
+        if previous.is_some_and(|p| p + 1 == found) {
+            score += 4;
+        }
+        if found == 0 || !name[found - 1].is_alphanumeric() {
+            score += 2;
+        }
+        previous = Some(found);
+        next = found + 1;
+    }
+    Some(score)
+}
+
+/// Entries matching `query`, best first (ties keep their listed order)
+pub fn matching(query: &str) -> Vec<&'static PaletteEntry> {
+    let mut scored: Vec<(u32, &PaletteEntry)> = ENTRIES
+        .iter()
+        .filter_map(|entry| fuzzy_score(query, entry.name).map(|score| (score, entry)))
+        .collect();
+    scored.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+    scored.into_iter().map(|(_, entry)| entry).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(query: &str) -> Vec<&'static str> {
+        matching(query).iter().map(|e| e.name).collect()
+    }
+
+    #[test]
+    fn empty_query_lists_everything_in_order() {
+        let all: Vec<_> = ENTRIES.iter().map(|e| e.name).collect();
+        assert_eq!(names(""), all);
+    }
+
+    #[test]
+    fn fuzzy_matches_in_order_and_ranks_word_starts_first() {
+        assert!(fuzzy_score("srch", "Search chats").is_some());
+        assert!(fuzzy_score("hcraes", "Search chats").is_none());
+        assert_eq!(names("sw acc")[0], "Switch account");
+        assert_eq!(names("MUTE"), ["Mute chat", "Unmute chat"]);
+        assert_eq!(names("exp")[0], "Export chat");
+        assert!(names("zzz").is_empty());
+    }
+}
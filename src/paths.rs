@@ -0,0 +1,200 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use directories::ProjectDirs;
+
+struct Resolved {
+    dir: PathBuf,
+    warning: Option<String>, // Set when files won't end up in the usual place
+}
+
+static RESOLVED: OnceLock<Resolved> = OnceLock::new();
+
+fn resolved() -> &'static Resolved {
+    RESOLVED.get_or_init(resolve)
+}
+
+/// Directory vimgram's files (config, credentials, sessions, log, notes) are
+/// read from and saved to for this run. Normally the platform config directory;
+/// when that can't be written (a read-only home in a container, no home at all)
+/// a private temp directory seeded with its files, so saved sessions still work.
+/// With no safe directory at all, a path that doesn't exist, so nothing is saved
+pub fn config_dir() -> &'static Path {
+    &resolved().dir
+}
+
+/// Path of a file in the config directory
+pub fn config_file(name: &str) -> PathBuf {
+    config_dir().join(name)
+}
+
+/// Why settings and sessions aren't being saved to the usual place, if they aren't
+pub fn persistence_warning() -> Option<&'static str> {
+    resolved().warning.as_deref()
+}
+
+fn resolve() -> Resolved {
+    let preferred = ProjectDirs::from("", "", "vimgram").map(|p| p.config_dir().to_path_buf());
+    if let Some(dir) = preferred.as_ref().filter(|dir| writable(dir)) {
+        return Resolved {
+            dir: dir.clone(),
+            warning: None,
+        };
+    }
+
+    // The per-user temp dir keeps sessions across runs, but anyone can create
+    // it first; if it isn't ours, a fresh one with an unguessable name
+    let shared = fallback_dir();
+    let (fallback, usable) = if claim(&shared) {
+        (shared, true)
+    } else {
+        let fresh = std::env::temp_dir().join(format!("vimgram-{}", random_suffix()));
+        let usable = create_private(&fresh) && claim(&fresh);
+        (fresh, usable)
+    };
+    if usable {
+        if let Some(dir) = &preferred {
+            seed(dir, &fallback);
+        }
+    }
+    let warning = match (&preferred, usable) {
+        (Some(dir), true) => format!(
+            "Can't write to {}, saving to {} for this run",
+            dir.display(),
+            fallback.display()
+        ),
+        (None, true) => format!(
+            "No config directory, saving to {} for this run",
+            fallback.display()
+        ),
+        (Some(dir), false) => format!(
+            "Can't write to {}: settings and sessions last for this run only",
+            dir.display()
+        ),
+        (None, false) => {
+            "No writable config directory: settings and sessions last for this run only".to_string()
+        }
+    };
+    Resolved {
+        dir: fallback,
+        warning: Some(warning),
+    }
+}
+
+/// Per-user directory under the system temp dir
+fn fallback_dir() -> PathBuf {
+    let user = std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_default();
+    let name = if user.is_empty() {
+        "vimgram".to_string()
+    } else {
+        format!("vimgram-{}", user)
+    };
+    std::env::temp_dir().join(name)
+}
+
+/// Whether files can be created in `dir`, creating it if needed
+fn writable(dir: &Path) -> bool {
+    let probe = dir.join(".write-test");
+    fs::create_dir_all(dir).is_ok()
+        && fs::write(&probe, b"").is_ok()
+        && fs::remove_file(&probe).is_ok()
+}
+
+/// Hex string nobody can guess ahead of time, for temp names
+fn random_suffix() -> String {
+    use ring::rand::{SecureRandom, SystemRandom};
+
+    let mut bytes = [0u8; 8];
+    if SystemRandom::new().fill(&mut bytes).is_err() {
+        // Still unlikely to be taken, and create_private won't reuse one that is
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default();
+        bytes = (nanos ^ u64::from(std::process::id())).to_le_bytes();
+    }
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Create `dir` readable by us alone, failing if anything is already there
+#[cfg(unix)]
+fn create_private(dir: &Path) -> bool {
+    use std::os::unix::fs::DirBuilderExt;
+    fs::DirBuilder::new().mode(0o700).create(dir).is_ok()
+}
+
+#[cfg(not(unix))]
+fn create_private(dir: &Path) -> bool {
+    fs::create_dir(dir).is_ok()
+}
+
+/// Make a temp directory ours and keep other users out, as sessions are as good
+/// as a login. Refuses one that's a symlink or owned by someone else, which
+/// could be a trap set by another user of a shared temp dir
+#[cfg(unix)]
+fn claim(dir: &Path) -> bool {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    create_private(dir);
+    // Not following links, so a planted symlink shows up as one
+    let Ok(meta) = fs::symlink_metadata(dir) else {
+        return false;
+    };
+    if !meta.is_dir() {
+        return false;
+    }
+    // We own whatever we just created; create_new won't follow a planted link
+    let probe = dir.join(format!(".owner-{}", random_suffix()));
+    let created = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe)
+        .is_ok();
+    let ours = created && fs::symlink_metadata(&probe).is_ok_and(|p| p.uid() == meta.uid());
+    if created {
+        let _ = fs::remove_file(&probe);
+    }
+    ours && fs::set_permissions(dir, fs::Permissions::from_mode(0o700)).is_ok()
+}
+
+#[cfg(not(unix))]
+fn claim(dir: &Path) -> bool {
+    writable(dir)
+}
+
+/// Copy the config directory's files (and sessions) into the fallback, unless
+/// the fallback already has a newer copy from an earlier run
+fn seed(from: &Path, to: &Path) {
+    for sub in ["", "sessions"] {
+        let Ok(entries) = fs::read_dir(from.join(sub)) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            if name.to_string_lossy().starts_with("vimgram.log") {
+                continue;
+            }
+            let Ok(meta) = entry.metadata() else {
+                continue;
+            };
+            if !meta.is_file() {
+                continue;
+            }
+            let target = to.join(sub).join(&name);
+            let newer = match (
+                meta.modified(),
+                fs::metadata(&target).and_then(|m| m.modified()),
+            ) {
+                (Ok(source), Ok(copied)) => source > copied,
+                _ => true,
+            };
+            if newer {
+                let _ = fs::create_dir_all(to.join(sub));
+                let _ = fs::copy(entry.path(), &target);
+            }
+        }
+    }
+}
@@ -0,0 +1,25 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Where the scratchpad lives, next to config.json
+fn scratchpad_path() -> Option<PathBuf> {
+    Some(crate::paths::config_file("scratchpad.txt"))
+}
+
+/// Notes saved last time, empty if there are none
+pub fn load() -> String {
+    scratchpad_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .unwrap_or_default()
+}
+
+/// Save the notes locally (they are never sent anywhere)
+pub fn save(notes: &str) -> io::Result<()> {
+    let path = scratchpad_path()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, notes)
+}
@@ -0,0 +1,54 @@
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+/// Play a sound cue: the WAV at `file` through the platform's player, or the
+/// terminal bell when no file is set or no player could be started
+///
+/// The player runs in the background, so a long file never holds up the UI,
+/// and a thread waits for it to exit so it doesn't linger as a zombie.
+/// Uses afplay (macOS), PowerShell (Windows), or paplay, pw-play or aplay
+/// (Linux and BSD, tried in that order).
+pub fn play(file: &str) {
+    if file.is_empty() || !play_file(file) {
+        bell();
+    }
+}
+
+/// Ring the terminal bell
+pub fn bell() {
+    let mut stdout = io::stdout();
+    let _ = stdout.write_all(b"\x07");
+    let _ = stdout.flush();
+}
+
+/// Start the first available player on `file`, false if none could be started
+fn play_file(file: &str) -> bool {
+    let powershell = format!(
+        "(New-Object Media.SoundPlayer '{}').PlaySync()",
+        file.replace('\'', "''")
+    );
+    let players: Vec<(&str, Vec<&str>)> = if cfg!(target_os = "macos") {
+        vec![("afplay", vec![file])]
+    } else if cfg!(windows) {
+        vec![("powershell", vec!["-NoProfile", "-Command", &powershell])]
+    } else {
+        vec![
+            ("paplay", vec![file]),
+            ("pw-play", vec![file]),
+            ("aplay", vec!["-q", file]),
+        ]
+    };
+
+    players.into_iter().any(|(program, args)| {
+        Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map(|mut child| {
+                std::thread::spawn(move || child.wait());
+            })
+            .is_ok()
+    })
+}
@@ -1,6 +1,6 @@
 use std::fs;
 use std::path::PathBuf;
-use directories::ProjectDirs;
+use grammers_session::Session;
 use serde::{Deserialize, Serialize};
 
 /// Represents a single Telegram account
@@ -9,38 +9,53 @@ pub struct Account {
     pub id: String,      // Unique ID like "account_1"
     pub phone: String,   // Phone number for display
     pub name: String,    // User-given name like "Personal"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accent: Option<String>, // "#rrggbb" color shown while active, None picks one by position
 }
 
-/// Registry of all accounts
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct AccountRegistry {
-    pub active: String,           // Currently active account ID
-    pub accounts: Vec<Account>,   // All accounts
-}
+/// Accents for accounts without their own, by position (the first is the classic steel blue)
+const ACCENT_PALETTE: &[(u8, u8, u8)] = &[
+    (70, 130, 180),
+    (230, 160, 60),
+    (80, 190, 120),
+    (210, 90, 140),
+    (150, 110, 220),
+    (60, 190, 190),
+];
 
-impl Default for AccountRegistry {
-    fn default() -> Self {
-        Self {
-            active: String::new(),
-            accounts: Vec::new(),
-        }
+/// Parse a "#rrggbb" color
+fn parse_hex_color(color: &str) -> Option<(u8, u8, u8)> {
+    let hex = color.trim().strip_prefix('#')?;
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
     }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some((channel(0)?, channel(2)?, channel(4)?))
 }
 
-fn get_config_dir() -> Option<PathBuf> {
-    ProjectDirs::from("", "", "vimgram").map(|p| p.config_dir().to_path_buf())
+/// State of an account's saved session, judged from the file alone (no network)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SessionHealth {
+    Valid,      // Present, readable and signed in
+    Missing,    // No session file: never logged in, or logged out
+    SignedOut,  // Readable, but the login never finished
+    Corrupt,    // Present but can't be read
+    Encrypted,  // Encrypted, can't be checked without the passphrase
+}
+
+/// Registry of all accounts
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AccountRegistry {
+    pub active: String,           // Currently active account ID
+    pub accounts: Vec<Account>,   // All accounts
 }
 
 fn get_accounts_path() -> PathBuf {
-    get_config_dir()
-        .map(|d| d.join("accounts.json"))
-        .unwrap_or_else(|| PathBuf::from("accounts.json"))
+    crate::paths::config_file("accounts.json")
 }
 
 fn get_sessions_dir() -> PathBuf {
-    get_config_dir()
-        .map(|d| d.join("sessions"))
-        .unwrap_or_else(|| PathBuf::from("sessions"))
+    crate::paths::config_file("sessions")
 }
 
 /// Get the session file path for a specific account
@@ -66,9 +81,7 @@ impl AccountRegistry {
     
     /// Migrate legacy single-session setup to multi-account
     fn migrate_legacy_session() -> Self {
-        let legacy_session = get_config_dir()
-            .map(|d| d.join("session.dat"))
-            .unwrap_or_else(|| PathBuf::from("session.dat"));
+        let legacy_session = crate::paths::config_file("session.dat");
         
         if legacy_session.exists() {
             // Create sessions directory
@@ -85,6 +98,7 @@ impl AccountRegistry {
                         id: "default".to_string(),
                         phone: "Migrated".to_string(),
                         name: "Default".to_string(),
+                        accent: None,
                     }],
                 };
                 let _ = registry.save();
@@ -108,6 +122,7 @@ impl AccountRegistry {
     }
     
     /// Get the currently active account
+    #[allow(dead_code)] // Not wired up to the UI yet
     pub fn get_active_account(&self) -> Option<&Account> {
         self.accounts.iter().find(|a| a.id == self.active)
     }
@@ -119,6 +134,7 @@ impl AccountRegistry {
             id: id.clone(),
             phone,
             name,
+            accent: None,
         });
         
         // If this is the first account, make it active
@@ -142,11 +158,38 @@ impl AccountRegistry {
     }
     
     /// Get account by index
+    #[allow(dead_code)] // Not wired up to the UI yet
     pub fn get_account_by_index(&self, index: usize) -> Option<&Account> {
         self.accounts.get(index)
     }
+
+    /// Accent color of the account at `index`: its own if set and valid, else from the palette
+    pub fn accent(&self, index: usize) -> (u8, u8, u8) {
+        self.accounts
+            .get(index)
+            .and_then(|a| a.accent.as_deref())
+            .and_then(parse_hex_color)
+            .unwrap_or(ACCENT_PALETTE[index % ACCENT_PALETTE.len()])
+    }
     
+    /// Check an account's session file without connecting
+    pub fn session_health(account_id: &str) -> SessionHealth {
+        let session_path = get_session_path_for_account(account_id);
+        if !session_path.exists() {
+            return SessionHealth::Missing;
+        }
+        if super::session_crypto::is_encrypted(&session_path) {
+            return SessionHealth::Encrypted;
+        }
+        match Session::load_file(&session_path) {
+            Ok(session) if session.signed_in() => SessionHealth::Valid,
+            Ok(_) => SessionHealth::SignedOut,
+            Err(_) => SessionHealth::Corrupt,
+        }
+    }
+
     /// Delete an account's session file
+    #[allow(dead_code)] // Not wired up to the UI yet
     pub fn delete_account_session(account_id: &str) -> Result<bool, Box<dyn std::error::Error>> {
         let session_path = get_session_path_for_account(account_id);
         if session_path.exists() {
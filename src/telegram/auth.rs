@@ -1,14 +1,24 @@
 use grammers_client::Client;
 use std::io::{self, BufRead, Write};
 
-pub async fn authenticate(client: &Client) -> Result<(), Box<dyn std::error::Error>> {
+/// Log in interactively. A `phone` given up front (--phone or config.json)
+/// skips asking for the number; the code and 2FA password are always typed
+pub async fn authenticate(client: &Client, phone: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
     println!("📱 Telegram Authentication");
     println!("──────────────────────────");
 
     // Get phone number
-    print!("Enter phone number (with country code, e.g. +91...): ");
-    io::stdout().flush()?;
-    let phone = io::stdin().lock().lines().next().unwrap()?;
+    let phone = match phone {
+        Some(phone) => {
+            println!("Phone number: {}", phone);
+            phone.to_string()
+        }
+        None => {
+            print!("Enter phone number (with country code, e.g. +91...): ");
+            io::stdout().flush()?;
+            io::stdin().lock().lines().next().unwrap()?
+        }
+    };
 
     // Request login code
     let token = client.request_login_code(&phone).await?;
@@ -42,7 +52,7 @@ pub fn prompt_for_credentials() -> (i32, String) {
     println!("Get these from https://my.telegram.org");
     println!("──────────────────────────────────────");
 
-    let mut stdin = io::stdin();
+    let stdin = io::stdin();
     let mut stdout = io::stdout();
 
     let api_id: i32 = loop {
@@ -1,9 +1,15 @@
+use grammers_client::client::chats::InvocationError;
+use grammers_client::types::{Chat, Dialog, Downloadable, Media, Message};
+use grammers_client::types::media::Document;
 use grammers_client::{Client, Config, InitParams};
-use grammers_session::Session;
+use grammers_tl_types as tl;
+use grammers_session::{PackedChat, PackedType, Session};
+use std::collections::HashMap;
 use std::path::PathBuf;
-use directories::ProjectDirs;
 use std::fs;
 use serde::{Deserialize, Serialize};
+use super::session_crypto;
+use crate::app::MediaKind;
 
 #[derive(Serialize, Deserialize)]
 pub struct Credentials {
@@ -11,20 +17,12 @@ pub struct Credentials {
     pub api_hash: String,
 }
 
-fn get_config_dir() -> Option<PathBuf> {
-    ProjectDirs::from("", "", "vimgram").map(|p| p.config_dir().to_path_buf())
-}
-
 fn get_session_path() -> PathBuf {
-    get_config_dir()
-        .map(|d| d.join("session.dat"))
-        .unwrap_or_else(|| PathBuf::from(".bifrost_session"))
+    crate::paths::config_file("session.dat")
 }
 
 fn get_credentials_path() -> PathBuf {
-    get_config_dir()
-        .map(|d| d.join("credentials.json"))
-        .unwrap_or_else(|| PathBuf::from("credentials.json"))
+    crate::paths::config_file("credentials.json")
 }
 
 impl Credentials {
@@ -52,14 +50,15 @@ impl Credentials {
 pub struct TelegramClient {
     pub client: Client,
     pub account_id: Option<String>,
+    pub encrypt_session: bool, // Save the session encrypted with a passphrase
 }
 
 impl TelegramClient {
     /// Connect with legacy session (for backward compatibility)
-    pub async fn connect(api_id: i32, api_hash: &str) -> Result<Self, Box<dyn std::error::Error>> {
+    pub async fn connect(api_id: i32, api_hash: &str, proxy_url: Option<String>) -> Result<Self, Box<dyn std::error::Error>> {
         let session_path = get_session_path();
         let session = if session_path.exists() {
-            Session::load_file(&session_path)?
+            session_crypto::load_session(&session_path)?
         } else {
             Session::new()
         };
@@ -69,27 +68,29 @@ impl TelegramClient {
             api_id,
             api_hash: api_hash.to_string(),
             params: InitParams {
+                proxy_url,
                 ..Default::default()
             },
         })
         .await?;
 
-        Ok(Self { client, account_id: None })
+        Ok(Self { client, account_id: None, encrypt_session: false })
     }
     
     /// Connect with a specific account
-    pub async fn connect_with_account(api_id: i32, api_hash: &str, account_id: &str) -> Result<Self, Box<dyn std::error::Error>> {
+    pub async fn connect_with_account(api_id: i32, api_hash: &str, account_id: &str, proxy_url: Option<String>) -> Result<Self, Box<dyn std::error::Error>> {
         use super::accounts::get_session_path_for_account;
         
         let session_path = get_session_path_for_account(account_id);
         
-        // Ensure sessions directory exists
+        // Ensure sessions directory exists (best effort: without it the
+        // session just isn't saved, see paths::persistence_warning)
         if let Some(parent) = session_path.parent() {
-            fs::create_dir_all(parent)?;
+            let _ = fs::create_dir_all(parent);
         }
         
         let session = if session_path.exists() {
-            Session::load_file(&session_path)?
+            session_crypto::load_session(&session_path)?
         } else {
             Session::new()
         };
@@ -99,30 +100,65 @@ impl TelegramClient {
             api_id,
             api_hash: api_hash.to_string(),
             params: InitParams {
+                proxy_url,
                 ..Default::default()
             },
         })
         .await?;
 
-        Ok(Self { client, account_id: Some(account_id.to_string()) })
+        Ok(Self { client, account_id: Some(account_id.to_string()), encrypt_session: false })
     }
 
-    /// Save session (uses account_id if set)
-    pub fn save_session(&self) -> Result<(), Box<dyn std::error::Error>> {
+    /// Open a fresh connection with the same session, e.g. after the old one died in a suspend
+    pub async fn reconnect(&self, api_id: i32, api_hash: &str, proxy_url: Option<String>) -> Result<Self, Box<dyn std::error::Error>> {
+        if let Err(e) = self.save_session() {
+            tracing::warn!("saving the session before reconnecting failed: {}", e);
+        }
+        let mut reconnected = match &self.account_id {
+            Some(account_id) => Self::connect_with_account(api_id, api_hash, account_id, proxy_url).await?,
+            None => Self::connect(api_id, api_hash, proxy_url).await?,
+        };
+        reconnected.encrypt_session = self.encrypt_session;
+        Ok(reconnected)
+    }
+
+    /// Cheap round-trip to check the connection still works
+    pub async fn ping(&self) -> Result<(), InvocationError> {
+        self.client.invoke(&tl::functions::updates::GetState {}).await?;
+        Ok(())
+    }
+
+    /// Path of this client's session file (uses account_id if set)
+    fn session_path(&self) -> PathBuf {
         use super::accounts::get_session_path_for_account;
-        
-        let data = self.client.session().save();
-        let session_path = if let Some(ref account_id) = self.account_id {
+
+        if let Some(ref account_id) = self.account_id {
             get_session_path_for_account(account_id)
         } else {
             get_session_path()
-        };
+        }
+    }
+
+    /// Save session (uses account_id if set, encrypted if encrypt_session is on)
+    pub fn save_session(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let data = self.client.session().save();
+        let session_path = self.session_path();
         
         // Ensure parent exists
         if let Some(parent) = session_path.parent() {
             fs::create_dir_all(parent)?;
         }
-        fs::write(session_path, data)?;
+        session_crypto::save_session(&session_path, &data, self.encrypt_session)?;
+        Ok(())
+    }
+
+    /// Rewrite the session file if it isn't stored the way encrypt_session asks,
+    /// so turning the option on (or off) converts existing sessions
+    pub fn sync_session_encryption(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let session_path = self.session_path();
+        if session_path.exists() && session_crypto::is_encrypted(&session_path) != self.encrypt_session {
+            self.save_session()?;
+        }
         Ok(())
     }
 
@@ -131,6 +167,75 @@ impl TelegramClient {
     }
 }
 
+/// Check if an API error means the session was revoked or expired server-side
+/// (AUTH_KEY_UNREGISTERED, SESSION_REVOKED, ... are all 401s)
+pub fn is_auth_error(err: &InvocationError) -> bool {
+    matches!(err, InvocationError::Rpc(rpc) if rpc.code == 401)
+}
+
+/// Seconds Telegram asked us to back off for, if this is a FLOOD_WAIT error
+pub fn flood_wait_seconds(err: &InvocationError) -> Option<u32> {
+    match err {
+        InvocationError::Rpc(rpc) if rpc.name.starts_with("FLOOD_") && rpc.name.ends_with("WAIT") => rpc.value,
+        _ => None,
+    }
+}
+
+/// Seconds until a slow mode group accepts another message, from a SLOWMODE_WAIT_X error
+pub fn slow_mode_wait_seconds(err: &InvocationError) -> Option<u32> {
+    match err {
+        InvocationError::Rpc(rpc) if rpc.name == "SLOWMODE_WAIT" => rpc.value,
+        _ => None,
+    }
+}
+
+/// What a channel or supergroup's full info says about it
+pub struct FullChannel {
+    pub members: Option<u32>,
+    pub slow_mode_secs: u32, // 0 when it's off
+}
+
+/// Member count and slow mode of a channel or supergroup from its full info,
+/// None for other chats. Only those cost a request
+pub async fn fetch_full_channel(client: &Client, chat: &Chat) -> Result<Option<FullChannel>, InvocationError> {
+    let Some(channel) = chat.pack().try_to_input_channel() else {
+        return Ok(None);
+    };
+    let tl::enums::messages::ChatFull::Full(full) =
+        client.invoke(&tl::functions::channels::GetFullChannel { channel }).await?;
+    Ok(match full.full_chat {
+        tl::enums::ChatFull::ChannelFull(f) => Some(FullChannel {
+            members: f.participants_count.map(|n| n.max(0) as u32),
+            slow_mode_secs: f.slowmode_seconds.unwrap_or(0).max(0) as u32,
+        }),
+        tl::enums::ChatFull::Full(_) => None,
+    })
+}
+
+/// Member count of a group or channel as the dialog list reports it. Small
+/// groups always carry one; channels and supergroups often don't
+pub fn chat_members(chat: &Chat) -> Option<u32> {
+    let count = match chat {
+        Chat::Group(group) => match tl::enums::Chat::from(group.clone()) {
+            tl::enums::Chat::Chat(c) => Some(c.participants_count),
+            tl::enums::Chat::Channel(c) => c.participants_count,
+            _ => None,
+        },
+        Chat::Channel(channel) => tl::types::Channel::from(channel.clone()).participants_count,
+        Chat::User(_) => None,
+    };
+    count.map(|n| n.max(0) as u32)
+}
+
+/// Whether an error is likely a network hiccup worth retrying (not a real answer)
+pub fn is_transient_error(err: &InvocationError) -> bool {
+    match err {
+        InvocationError::Dropped | InvocationError::Read(_) => true,
+        // Telegram's own internal errors and timeouts
+        InvocationError::Rpc(rpc) => rpc.code == 500 || rpc.code == -503,
+    }
+}
+
 /// Delete the session file for the active account
 pub fn delete_session() -> Result<bool, Box<dyn std::error::Error>> {
     let session_path = get_session_path();
@@ -142,6 +247,655 @@ pub fn delete_session() -> Result<bool, Box<dyn std::error::Error>> {
     }
 }
 
+/// Mute a chat's notifications until a unix timestamp (0 unmutes, i32::MAX is forever)
+pub async fn set_mute_until(client: &Client, chat: &Chat, until: i32) -> Result<(), InvocationError> {
+    client
+        .invoke(&tl::functions::account::UpdateNotifySettings {
+            peer: tl::enums::InputNotifyPeer::Peer(tl::types::InputNotifyPeer {
+                peer: chat.pack().to_input_peer(),
+            }),
+            settings: tl::enums::InputPeerNotifySettings::Settings(tl::types::InputPeerNotifySettings {
+                show_previews: None,
+                silent: None,
+                mute_until: Some(until),
+                sound: None,
+                stories_muted: None,
+                stories_hide_sender: None,
+                stories_sound: None,
+            }),
+        })
+        .await?;
+    Ok(())
+}
+
+/// Block or unblock a user
+pub async fn set_blocked(client: &Client, chat: &Chat, blocked: bool) -> Result<(), InvocationError> {
+    let id = chat.pack().to_input_peer();
+    if blocked {
+        client.invoke(&tl::functions::contacts::Block { my_stories_from: false, id }).await?;
+    } else {
+        client.invoke(&tl::functions::contacts::Unblock { my_stories_from: false, id }).await?;
+    }
+    Ok(())
+}
+
+/// Ids of the users this account has blocked
+pub async fn fetch_blocked(client: &Client) -> Result<Vec<i64>, InvocationError> {
+    const PAGE: i32 = 100;
+    let mut ids = Vec::new();
+    let mut offset = 0;
+    loop {
+        let request = tl::functions::contacts::GetBlocked { my_stories_from: false, offset, limit: PAGE };
+        let (blocked, total) = match client.invoke(&request).await? {
+            tl::enums::contacts::Blocked::Blocked(b) => (b.blocked, None),
+            tl::enums::contacts::Blocked::Slice(s) => (s.blocked, Some(s.count)),
+        };
+        let page = blocked.len() as i32;
+        ids.extend(blocked.into_iter().filter_map(|tl::enums::PeerBlocked::Blocked(b)| match b.peer_id {
+            tl::enums::Peer::User(user) => Some(user.user_id),
+            _ => None,
+        }));
+        offset += page;
+        if page == 0 || total.is_none_or(|total| offset >= total) {
+            return Ok(ids);
+        }
+    }
+}
+
+/// Whether an API error means we lack the admin rights for an action
+pub fn is_permission_error(err: &InvocationError) -> bool {
+    matches!(err, InvocationError::Rpc(rpc) if rpc.code == 403 || rpc.name == "CHAT_ADMIN_REQUIRED")
+}
+
+/// Export an invite link for a private group or channel (needs the right to invite)
+pub async fn export_invite_link(client: &Client, chat: &Chat) -> Result<Option<String>, InvocationError> {
+    let invite = client
+        .invoke(&tl::functions::messages::ExportChatInvite {
+            legacy_revoke_permanent: false,
+            request_needed: false,
+            peer: chat.pack().to_input_peer(),
+            expire_date: None,
+            usage_limit: None,
+            title: None,
+        })
+        .await?;
+    Ok(match invite {
+        tl::enums::ExportedChatInvite::ChatInviteExported(invite) => Some(invite.link),
+        tl::enums::ExportedChatInvite::ChatInvitePublicJoinRequests => None,
+    })
+}
+
+/// Offset Bot API style ids add to channel ids (-100<channel id>)
+const BOT_API_CHANNEL_OFFSET: i64 = 1_000_000_000_000;
+
+/// The id grammers uses for a chat given as a plain or Bot API style id
+/// (`-<group id>`, `-100<channel id>`)
+pub fn bare_chat_id(id: i64) -> i64 {
+    if id <= -BOT_API_CHANNEL_OFFSET {
+        -id - BOT_API_CHANNEL_OFFSET
+    } else {
+        id.abs()
+    }
+}
+
+/// Look up a chat by id that isn't among the loaded dialogs
+///
+/// Positive ids are tried as users, `-<id>` as basic groups and `-100<id>`
+/// as channels. Without an access hash the server only answers for peers
+/// the account has already met, so unknown ids come back as `None`.
+pub async fn resolve_chat_id(client: &Client, id: i64) -> Result<Option<Chat>, InvocationError> {
+    let chats = |chats: tl::enums::messages::Chats| match chats {
+        tl::enums::messages::Chats::Chats(c) => c.chats,
+        tl::enums::messages::Chats::Slice(c) => c.chats,
+    };
+    let packed = if id <= -BOT_API_CHANNEL_OFFSET {
+        let channel_id = bare_chat_id(id);
+        let result = client
+            .invoke(&tl::functions::channels::GetChannels {
+                id: vec![tl::enums::InputChannel::Channel(tl::types::InputChannel { channel_id, access_hash: 0 })],
+            })
+            .await;
+        match result.map(chats) {
+            Ok(found) => found.into_iter().find_map(|chat| match chat {
+                tl::enums::Chat::Channel(c) => Some(PackedChat {
+                    ty: if c.megagroup { PackedType::Megagroup } else if c.gigagroup { PackedType::Gigagroup } else { PackedType::Broadcast },
+                    id: c.id,
+                    access_hash: c.access_hash,
+                }),
+                _ => None,
+            }),
+            Err(InvocationError::Rpc(rpc)) if rpc.code == 400 => None,
+            Err(e) => return Err(e),
+        }
+    } else if id < 0 {
+        let result = client.invoke(&tl::functions::messages::GetChats { id: vec![-id] }).await;
+        match result.map(chats) {
+            Ok(found) => found.into_iter().find_map(|chat| match chat {
+                tl::enums::Chat::Chat(c) => Some(PackedChat { ty: PackedType::Chat, id: c.id, access_hash: None }),
+                _ => None,
+            }),
+            Err(InvocationError::Rpc(rpc)) if rpc.code == 400 => None,
+            Err(e) => return Err(e),
+        }
+    } else {
+        let result = client
+            .invoke(&tl::functions::users::GetUsers {
+                id: vec![tl::enums::InputUser::User(tl::types::InputUser { user_id: id, access_hash: 0 })],
+            })
+            .await;
+        match result {
+            Ok(found) => found.into_iter().find_map(|user| match user {
+                tl::enums::User::User(u) => Some(PackedChat {
+                    ty: if u.bot { PackedType::Bot } else { PackedType::User },
+                    id: u.id,
+                    access_hash: u.access_hash,
+                }),
+                tl::enums::User::Empty(_) => None,
+            }),
+            Err(InvocationError::Rpc(rpc)) if rpc.code == 400 => None,
+            Err(e) => return Err(e),
+        }
+    };
+
+    // Channels and users need their access hash to be unpacked
+    match packed {
+        Some(packed) if packed.ty == PackedType::Chat || packed.access_hash.is_some() => {
+            Ok(Some(client.unpack_chat(packed).await?))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Fetch a chat's pinned messages as (id, text), newest first
+pub async fn fetch_pinned(client: &Client, chat: &Chat) -> Result<Vec<(i32, String)>, InvocationError> {
+    let mut pinned = Vec::new();
+    let mut iter = client
+        .search_messages(chat)
+        .filter(tl::enums::MessagesFilter::InputMessagesFilterPinned)
+        .limit(20);
+    while let Some(msg) = iter.next().await? {
+        pinned.push((msg.id(), msg.text().to_string()));
+    }
+    Ok(pinned)
+}
+
+/// Mute expiry the server reported for a dialog, if it has one
+pub fn dialog_mute_until(dialog: &Dialog) -> Option<i32> {
+    match &dialog.dialog {
+        tl::enums::Dialog::Dialog(d) => {
+            let tl::enums::PeerNotifySettings::Settings(settings) = &d.notify_settings;
+            settings.mute_until
+        }
+        tl::enums::Dialog::Folder(_) => None,
+    }
+}
+
+/// Server-side read state of a dialog: (newest read incoming message id,
+/// unread count, unread mentions), shared by all of the account's devices
+pub fn dialog_read_state(dialog: &Dialog) -> Option<(i32, u32, u32)> {
+    match &dialog.dialog {
+        tl::enums::Dialog::Dialog(d) => Some((
+            d.read_inbox_max_id,
+            d.unread_count.max(0) as u32,
+            d.unread_mentions_count.max(0) as u32,
+        )),
+        tl::enums::Dialog::Folder(_) => None,
+    }
+}
+
+/// Original author of a forwarded message: (peer id, name sent along with it)
+pub type ForwardOrigin = (Option<i64>, Option<String>);
+
+/// Where a message was forwarded from, if it's a forward
+///
+/// Authors who hide their account on forwards only come with a name, and
+/// channel posts may add a signature; either is preferred over the peer id.
+pub fn forward_origin(msg: &Message) -> Option<ForwardOrigin> {
+    let tl::enums::MessageFwdHeader::Header(header) = msg.forward_header()?;
+    let peer_id = header.from_id.map(|peer| match peer {
+        tl::enums::Peer::User(p) => p.user_id,
+        tl::enums::Peer::Chat(p) => p.chat_id,
+        tl::enums::Peer::Channel(p) => p.channel_id,
+    });
+    Some((peer_id, header.from_name.or(header.post_author)))
+}
+
+/// Kind and label of a message's downloadable media: "Voice (0:14)", "Photo", "notes.pdf".
+/// The glyph for the kind is added when it's drawn
+///
+/// grammers keeps the document attributes private, so a voice note is told
+/// apart as unnamed, untitled ogg audio with a duration.
+pub fn media_label(msg: &Message) -> Option<(MediaKind, String)> {
+    match msg.media()? {
+        Media::Photo(_) => Some((MediaKind::Photo, "Photo".to_string())),
+        Media::Sticker(sticker) => Some((MediaKind::Sticker, sticker_label(sticker.emoji()))),
+        Media::Document(doc) => {
+            // Expired documents come without one, and name() can't cope with that
+            doc.mime_type()?;
+            if doc.is_animated() {
+                return Some((MediaKind::Gif, "[GIF]".to_string()));
+            }
+            let voice = doc.mime_type() == Some("audio/ogg") && doc.name().is_empty() && doc.audio_title().is_none();
+            Some(match doc.duration() {
+                Some(secs) if voice => (MediaKind::Voice, format!("Voice ({})", format_duration(secs.round() as u64))),
+                _ if doc.name().is_empty() => (MediaKind::File, "File".to_string()),
+                _ => (MediaKind::File, doc.name().to_string()),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// A call or voice note length as m:ss
+fn format_duration(secs: u64) -> String {
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+/// One-line description of a service message (joins, pins, calls, ...), with
+/// the kind of call for its glyph, or `None` for normal messages and actions
+/// not worth a line in the chat
+pub fn service_text(msg: &Message) -> Option<(Option<MediaKind>, String)> {
+    use tl::enums::MessageAction as A;
+
+    let actor = if msg.outgoing() {
+        "You".to_string()
+    } else {
+        msg.sender()
+            .map(|s| s.name().to_string())
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or_else(|| "Someone".to_string())
+    };
+    let sender_id = msg.sender().map(|s| s.id());
+    let text = match msg.action()? {
+        A::ChatCreate(a) => format!("{} created the group \"{}\"", actor, a.title),
+        A::ChannelCreate(a) => format!("Channel \"{}\" created", a.title),
+        A::ChatEditTitle(a) => format!("{} renamed the group to \"{}\"", actor, a.title),
+        A::ChatEditPhoto(_) => format!("{} changed the group photo", actor),
+        A::ChatDeletePhoto => format!("{} removed the group photo", actor),
+        A::ChatAddUser(a) if a.users.len() == 1 && Some(a.users[0]) == sender_id => format!("{} joined the group", actor),
+        A::ChatAddUser(a) if a.users.len() == 1 => format!("{} added a member", actor),
+        A::ChatAddUser(a) => format!("{} added {} members", actor, a.users.len()),
+        A::ChatDeleteUser(a) if Some(a.user_id) == sender_id => format!("{} left the group", actor),
+        A::ChatDeleteUser(_) => format!("{} removed a member", actor),
+        A::ChatJoinedByLink(_) => format!("{} joined via invite link", actor),
+        A::ChatJoinedByRequest => format!("{} was accepted into the group", actor),
+        A::PinMessage => format!("{} pinned a message", actor),
+        A::HistoryClear => "History was cleared".to_string(),
+        A::ScreenshotTaken => format!("{} took a screenshot", actor),
+        A::ContactSignUp => format!("{} joined Telegram", actor),
+        A::PhoneCall(call) => {
+            let (kind, name) = if call.video {
+                (MediaKind::VideoCall, "Video call")
+            } else {
+                (MediaKind::Call, "Call")
+            };
+            let text = match call.duration {
+                Some(secs) if secs > 0 => format!("{} ({})", name, format_duration(secs as u64)),
+                _ if matches!(call.reason, Some(tl::enums::PhoneCallDiscardReason::Missed)) => format!("{} missed", name),
+                _ => format!("{} from {}", name, actor),
+            };
+            return Some((Some(kind), text));
+        }
+        A::GroupCall(call) => {
+            let text = match call.duration {
+                Some(secs) => format!("Voice chat ended ({})", format_duration(secs as u64)),
+                None => format!("{} started a voice chat", actor),
+            };
+            return Some((Some(MediaKind::Call), text));
+        }
+        _ => return None,
+    };
+    Some((None, text))
+}
+
+/// Where `:download` saves media when no path is given
+fn downloads_dir() -> PathBuf {
+    directories::UserDirs::new()
+        .and_then(|dirs| dirs.download_dir().map(|d| d.to_path_buf()))
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// File name for downloaded media: the document's own name, or one made from the ids.
+/// The sender picks the document's name, so only its last component is used
+/// and anything that could point outside the downloads folder is ignored
+fn media_file_name(media: &Media, chat_id: i64, message_id: i32) -> String {
+    let extension = match media {
+        Media::Photo(_) => "jpg",
+        Media::Sticker(_) => "webp",
+        Media::Document(doc) => {
+            let name = std::path::Path::new(doc.name())
+                .file_name()
+                .and_then(|name| name.to_str())
+                .filter(|name| !name.trim().is_empty() && !name.contains(['/', '\\']) && !name.starts_with(".."));
+            if let Some(name) = name {
+                return name.to_string();
+            }
+            match doc.mime_type() {
+                Some("audio/ogg") => "ogg",
+                Some(mime) => mime.rsplit('/').next().unwrap_or("bin"),
+                None => "bin",
+            }
+        }
+        _ => "bin",
+    };
+    format!("vimgram_{}_{}.{}", chat_id.unsigned_abs(), message_id, extension)
+}
+
+/// Download the media of a message to `path`, or into the downloads folder
+/// under its own name, and return where it was saved
+pub async fn download_message_media(
+    client: &Client,
+    chat: &Chat,
+    message_id: i32,
+    path: Option<PathBuf>,
+) -> Result<PathBuf, String> {
+    let message = client
+        .get_messages_by_id(chat, &[message_id])
+        .await
+        .map_err(|e| e.to_string())?
+        .pop()
+        .flatten()
+        .ok_or("The message is gone")?;
+    let media = message
+        .media()
+        .filter(|m| matches!(m, Media::Photo(_) | Media::Sticker(_)) || matches!(m, Media::Document(d) if d.mime_type().is_some()))
+        .ok_or("This message has no media to download")?;
+
+    let path = path.unwrap_or_else(|| downloads_dir().join(media_file_name(&media, chat.id(), message_id)));
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    client
+        .download_media(&Downloadable::Media(media), &path)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+/// A chat folder: (title, ids of the loaded chats in it, hides muted chats, hides read chats)
+pub type Folder = (String, Vec<i64>, bool, bool);
+
+/// Fetch the account's chat folders and work out which of `chats` each one holds
+///
+/// Folders list some chats explicitly and take others by rule (contacts,
+/// groups, bots, ...). The muted/read exclusions change while the app runs,
+/// so they're returned as flags instead of being applied here.
+pub async fn fetch_folders(client: &Client, chats: &HashMap<i64, Chat>) -> Result<Vec<Folder>, InvocationError> {
+    let peer_ids = |peers: &[tl::enums::InputPeer]| -> Vec<i64> {
+        peers.iter().filter_map(input_peer_id).collect()
+    };
+
+    let tl::enums::messages::DialogFilters::Filters(result) =
+        client.invoke(&tl::functions::messages::GetDialogFilters {}).await?;
+    let mut folders = Vec::new();
+    for filter in result.filters {
+        match filter {
+            tl::enums::DialogFilter::Filter(f) => {
+                let excluded = peer_ids(&f.exclude_peers);
+                let mut ids = peer_ids(&f.pinned_peers);
+                ids.extend(peer_ids(&f.include_peers));
+                for (id, chat) in chats {
+                    let by_rule = match chat {
+                        Chat::User(user) if user.is_bot() => f.bots,
+                        Chat::User(user) if user.contact() => f.contacts,
+                        Chat::User(_) => f.non_contacts,
+                        Chat::Group(_) => f.groups,
+                        Chat::Channel(_) => f.broadcasts,
+                    };
+                    if by_rule && !ids.contains(id) {
+                        ids.push(*id);
+                    }
+                }
+                ids.retain(|id| !excluded.contains(id));
+                folders.push((f.title, ids, f.exclude_muted, f.exclude_read));
+            }
+            // Shared folders only ever list their chats
+            tl::enums::DialogFilter::Chatlist(f) => {
+                let mut ids = peer_ids(&f.pinned_peers);
+                ids.extend(peer_ids(&f.include_peers));
+                folders.push((f.title, ids, false, false));
+            }
+            // The built-in "All chats" entry
+            tl::enums::DialogFilter::Default => {}
+        }
+    }
+    Ok(folders)
+}
+
+/// Bare chat id of a peer listed in a folder
+fn input_peer_id(peer: &tl::enums::InputPeer) -> Option<i64> {
+    match peer {
+        tl::enums::InputPeer::User(p) => Some(p.user_id),
+        tl::enums::InputPeer::Chat(p) => Some(p.chat_id),
+        tl::enums::InputPeer::Channel(p) => Some(p.channel_id),
+        tl::enums::InputPeer::UserFromMessage(p) => Some(p.user_id),
+        tl::enums::InputPeer::ChannelFromMessage(p) => Some(p.channel_id),
+        tl::enums::InputPeer::Empty | tl::enums::InputPeer::PeerSelf => None,
+    }
+}
+
+/// Whether a chat is a forum: a supergroup whose messages are split into topics
+pub fn chat_is_forum(chat: &Chat) -> bool {
+    match chat {
+        Chat::Group(group) => matches!(tl::enums::Chat::from(group.clone()), tl::enums::Chat::Channel(c) if c.forum),
+        _ => false,
+    }
+}
+
+/// Forum topic a message was posted in, None for the General topic (and non-forum chats)
+pub fn message_topic(msg: &Message) -> Option<i32> {
+    match msg.reply_header()? {
+        tl::enums::MessageReplyHeader::Header(h) if h.forum_topic => h.reply_to_top_id.or(h.reply_to_msg_id),
+        _ => None,
+    }
+}
+
+/// Open topics of a forum as (topic id, title), in the server's order (most recently active first)
+pub async fn fetch_topics(client: &Client, chat: &Chat) -> Result<Vec<(i32, String)>, InvocationError> {
+    let Some(channel) = chat.pack().try_to_input_channel() else {
+        return Ok(Vec::new());
+    };
+    let tl::enums::messages::ForumTopics::Topics(result) = client
+        .invoke(&tl::functions::channels::GetForumTopics {
+            channel,
+            q: None,
+            offset_date: 0,
+            offset_id: 0,
+            offset_topic: 0,
+            limit: 100,
+        })
+        .await?;
+    Ok(result
+        .topics
+        .into_iter()
+        .filter_map(|topic| match topic {
+            tl::enums::ForumTopic::Topic(t) if !t.hidden => Some((t.id, t.title)),
+            _ => None,
+        })
+        .collect())
+}
+
+/// How a sticker shows in the chat, by its emoji
+pub fn sticker_label(emoji: &str) -> String {
+    if emoji.is_empty() {
+        "[sticker]".to_string()
+    } else {
+        format!("[sticker: {}]", emoji)
+    }
+}
+
+/// A sticker or GIF saved to the account, sendable with :sticker
+pub struct SavedSticker {
+    pub id: i64,
+    pub label: String, // What the picker shows and :sticker matches against
+    pub document: tl::enums::Document,
+}
+
+/// The account's favorite stickers, recently used stickers and saved GIFs, in
+/// that order and without repeats. Empty when nothing has been saved
+pub async fn fetch_saved_stickers(client: &Client) -> Result<Vec<SavedSticker>, InvocationError> {
+    let mut documents = Vec::new();
+    if let tl::enums::messages::FavedStickers::Stickers(faved) =
+        client.invoke(&tl::functions::messages::GetFavedStickers { hash: 0 }).await?
+    {
+        documents.extend(faved.stickers.into_iter().map(|d| (d, "favorite")));
+    }
+    if let tl::enums::messages::RecentStickers::Stickers(recent) = client
+        .invoke(&tl::functions::messages::GetRecentStickers { attached: false, hash: 0 })
+        .await?
+    {
+        documents.extend(recent.stickers.into_iter().map(|d| (d, "recent")));
+    }
+    if let tl::enums::messages::SavedGifs::Gifs(saved) =
+        client.invoke(&tl::functions::messages::GetSavedGifs { hash: 0 }).await?
+    {
+        documents.extend(saved.gifs.into_iter().map(|d| (d, "saved")));
+    }
+
+    let mut stickers: Vec<SavedSticker> = Vec::new();
+    for (document, source) in documents {
+        let tl::enums::Document::Document(doc) = &document else {
+            continue;
+        };
+        if stickers.iter().any(|s| s.id == doc.id) {
+            continue;
+        }
+        let mut emoji = None;
+        let mut file_name = None;
+        for attr in &doc.attributes {
+            match attr {
+                tl::enums::DocumentAttribute::Sticker(s) => emoji = Some(s.alt.clone()),
+                tl::enums::DocumentAttribute::Filename(f) => file_name = Some(f.file_name.clone()),
+                _ => {}
+            }
+        }
+        let label = match (emoji, file_name) {
+            (Some(emoji), _) => format!("{} ({})", sticker_label(&emoji), source),
+            (None, Some(name)) => format!("[GIF: {}]", name),
+            (None, None) => "[GIF]".to_string(),
+        };
+        stickers.push(SavedSticker { id: doc.id, label, document });
+    }
+    Ok(stickers)
+}
+
+/// Send a saved sticker or GIF as it is, without re-uploading it
+pub async fn send_saved_sticker(
+    client: &Client,
+    chat: &Chat,
+    document: &tl::enums::Document,
+    reply_to: Option<i32>,
+) -> Result<Message, InvocationError> {
+    let media = tl::types::MessageMediaDocument {
+        nopremium: false,
+        spoiler: false,
+        video: false,
+        round: false,
+        voice: false,
+        document: Some(document.clone()),
+        alt_document: None,
+        ttl_seconds: None,
+    };
+    let document = Document::from_media(media, client.clone());
+    let message = grammers_client::InputMessage::text("")
+        .copy_media(&Media::Document(document))
+        .reply_to(reply_to);
+    client.send_message(chat, message).await
+}
+
+/// Reply quoting part of the replied-to message, which `send_message` can't
+/// do. `quote` is the quoted text and where it starts in that message, in
+/// UTF-16 units; `topic` is the forum topic the reply goes to. Returns the
+/// sent message's id and date, if the server said them
+pub async fn send_quote_reply(
+    client: &Client,
+    chat: &Chat,
+    text: &str,
+    entities: Vec<tl::enums::MessageEntity>,
+    reply_to: i32,
+    quote: (&str, i32),
+    topic: Option<i32>,
+) -> Result<Option<(i32, i64)>, InvocationError> {
+    use ring::rand::{SecureRandom, SystemRandom};
+
+    // Telegram drops a message whose random_id repeats a recent one
+    let mut bytes = [0u8; 8];
+    let random_id = match SystemRandom::new().fill(&mut bytes) {
+        Ok(()) => i64::from_le_bytes(bytes),
+        Err(_) => std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as i64)
+            .unwrap_or_default(),
+    };
+    let updates = client
+        .invoke(&tl::functions::messages::SendMessage {
+            no_webpage: true,
+            silent: false,
+            background: false,
+            clear_draft: false,
+            noforwards: false,
+            update_stickersets_order: false,
+            invert_media: false,
+            peer: chat.pack().to_input_peer(),
+            reply_to: Some(
+                tl::types::InputReplyToMessage {
+                    reply_to_msg_id: reply_to,
+                    top_msg_id: topic.filter(|&topic| topic != reply_to),
+                    reply_to_peer_id: None,
+                    quote_text: Some(quote.0.to_string()),
+                    quote_entities: None,
+                    quote_offset: Some(quote.1),
+                }
+                .into(),
+            ),
+            message: text.to_string(),
+            random_id,
+            reply_markup: None,
+            entities: (!entities.is_empty()).then_some(entities),
+            schedule_date: None,
+            send_as: None,
+            quick_reply_shortcut: None,
+        })
+        .await?;
+    let updates = match updates {
+        tl::enums::Updates::UpdateShortSentMessage(sent) => {
+            return Ok(Some((sent.id, sent.date as i64)))
+        }
+        tl::enums::Updates::Updates(tl::types::Updates { updates, .. })
+        | tl::enums::Updates::Combined(tl::types::UpdatesCombined { updates, .. }) => updates,
+        _ => return Ok(None),
+    };
+    let id = updates.iter().find_map(|update| match update {
+        tl::enums::Update::MessageId(update) if update.random_id == random_id => Some(update.id),
+        _ => None,
+    });
+    let date = |id: i32| {
+        updates.iter().find_map(|update| {
+            let message = match update {
+                tl::enums::Update::NewMessage(update) => &update.message,
+                tl::enums::Update::NewChannelMessage(update) => &update.message,
+                _ => return None,
+            };
+            match message {
+                tl::enums::Message::Message(m) if m.id == id => Some(m.date as i64),
+                _ => None,
+            }
+        })
+    };
+    Ok(id.map(|id| (id, date(id).unwrap_or_else(crate::app::unix_now))))
+}
+
+/// Whether the server turned down a reply's quote (text not found in the
+/// message, or quotes not allowed there), so a plain reply is worth a try
+pub fn is_quote_error(err: &InvocationError) -> bool {
+    matches!(err, InvocationError::Rpc(rpc) if rpc.name.starts_with("QUOTE_"))
+}
+
+/// Whether a chat is a private chat with a bot
+pub fn chat_is_bot(chat: &Chat) -> bool {
+    matches!(chat, Chat::User(user) if user.is_bot())
+}
+
 /// Delete session for a specific account
 pub fn delete_session_for_account(account_id: &str) -> Result<bool, Box<dyn std::error::Error>> {
     use super::accounts::get_session_path_for_account;
@@ -1,4 +1,6 @@
 pub mod accounts;
 pub mod auth;
 pub mod client;
+pub mod session_crypto;
+#[allow(dead_code)] // Console listener, not used by the TUI
 pub mod updates;
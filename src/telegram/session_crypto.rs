@@ -0,0 +1,212 @@
+use grammers_session::Session;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use std::fs;
+use std::io::{self, Write};
+use std::num::NonZeroU32;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Env var holding the session passphrase, so it isn't prompted for
+const PASSPHRASE_VAR: &str = "VIMGRAM_SESSION_PASSPHRASE";
+/// Marks an encrypted session file; plaintext sessions never start with it
+const MAGIC: &[u8] = b"VGSE1\n";
+const SALT_LEN: usize = 16;
+const PBKDF2_ROUNDS: u32 = 100_000;
+
+/// Passphrase for this run, asked for at most once
+static PASSPHRASE: OnceLock<String> = OnceLock::new();
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+/// Whether the session file at `path` is encrypted
+pub fn is_encrypted(path: &Path) -> bool {
+    fs::read(path).is_ok_and(|data| data.starts_with(MAGIC))
+}
+
+/// Load a session file, decrypting it first if it's encrypted
+pub fn load_session(path: &Path) -> io::Result<Session> {
+    let data = fs::read(path)?;
+    let data = if data.starts_with(MAGIC) {
+        decrypt(&data, passphrase(false)?)?
+    } else {
+        data
+    };
+    Session::load(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Write session data to `path`, encrypted with the passphrase when `encrypt` is set.
+/// A passphrase typed in to encrypt a session for the first time is asked for twice
+pub fn save_session(path: &Path, data: &[u8], encrypt: bool) -> io::Result<()> {
+    if encrypt {
+        let passphrase = passphrase(!is_encrypted(path))?;
+        fs::write(path, encrypt_data(data, passphrase)?)
+    } else {
+        fs::write(path, data)
+    }
+}
+
+/// The passphrase this run already has, without prompting: None when sessions
+/// aren't encrypted and VIMGRAM_SESSION_PASSPHRASE isn't set
+fn known_passphrase() -> Option<String> {
+    match PASSPHRASE.get() {
+        Some(passphrase) => Some(passphrase.clone()),
+        None => std::env::var(PASSPHRASE_VAR).ok().filter(|p| !p.is_empty()),
+    }
+}
+
+/// Whether there's a session passphrase to check a typed one against
+pub fn knows_passphrase() -> bool {
+    known_passphrase().is_some()
+}
+
+/// Whether `typed` is the session passphrase
+pub fn check_passphrase(typed: &str) -> bool {
+    known_passphrase().is_some_and(|known| typed == known)
+}
+
+/// The session passphrase from VIMGRAM_SESSION_PASSPHRASE, or typed in (hidden) once per run.
+/// With `confirm`, a typed passphrase must be typed again the same, so a typo
+/// can't lock the session away behind a passphrase nobody knows
+fn passphrase(confirm: bool) -> io::Result<&'static str> {
+    if let Some(passphrase) = PASSPHRASE.get() {
+        return Ok(passphrase);
+    }
+    let passphrase = match std::env::var(PASSPHRASE_VAR) {
+        Ok(passphrase) if !passphrase.is_empty() => passphrase,
+        _ if confirm => loop {
+            let first = prompt_hidden("🔐 New session passphrase: ")?;
+            if prompt_hidden("🔐 Type it again: ")? == first {
+                break first;
+            }
+            println!("Passphrases don't match, try again");
+        },
+        _ => prompt_hidden("🔐 Session passphrase: ")?,
+    };
+    Ok(PASSPHRASE.get_or_init(|| passphrase))
+}
+
+/// Read a line from the terminal without echoing it
+fn prompt_hidden(prompt: &str) -> io::Result<String> {
+    use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+
+    print!("{}", prompt);
+    io::stdout().flush()?;
+    crossterm::terminal::enable_raw_mode()?;
+    let mut input = String::new();
+    let result = loop {
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Enter => break Ok(()),
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    break Err(io::Error::new(
+                        io::ErrorKind::Interrupted,
+                        "passphrase prompt cancelled",
+                    ));
+                }
+                KeyCode::Char(c) => input.push(c),
+                _ => {}
+            },
+            Ok(_) => {}
+            Err(e) => break Err(e),
+        }
+    };
+    crossterm::terminal::disable_raw_mode()?;
+    println!();
+    result.map(|()| input)
+}
+
+/// ChaCha20-Poly1305 key derived from the passphrase and a per-file salt
+fn derive_key(passphrase: &str, salt: &[u8]) -> LessSafeKey {
+    let mut key = [0u8; 32];
+    let rounds = NonZeroU32::new(PBKDF2_ROUNDS).expect("nonzero rounds");
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        rounds,
+        salt,
+        passphrase.as_bytes(),
+        &mut key,
+    );
+    LessSafeKey::new(UnboundKey::new(&CHACHA20_POLY1305, &key).expect("32-byte key"))
+}
+
+/// Encrypted file layout: MAGIC, salt, nonce, then ciphertext with its tag
+fn encrypt_data(data: &[u8], passphrase: &str) -> io::Result<Vec<u8>> {
+    let rng = SystemRandom::new();
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rng.fill(&mut salt)
+        .and_then(|()| rng.fill(&mut nonce))
+        .map_err(|_| invalid_data("no randomness available to encrypt the session"))?;
+
+    let mut sealed = data.to_vec();
+    derive_key(passphrase, &salt)
+        .seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(MAGIC),
+            &mut sealed,
+        )
+        .map_err(|_| invalid_data("failed to encrypt the session"))?;
+
+    let mut out = Vec::with_capacity(MAGIC.len() + SALT_LEN + NONCE_LEN + sealed.len());
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&salt);
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(&sealed);
+    Ok(out)
+}
+
+fn decrypt(data: &[u8], passphrase: &str) -> io::Result<Vec<u8>> {
+    let body = &data[MAGIC.len()..];
+    if body.len() < SALT_LEN + NONCE_LEN + CHACHA20_POLY1305.tag_len() {
+        return Err(invalid_data("encrypted session file is truncated"));
+    }
+    let (salt, rest) = body.split_at(SALT_LEN);
+    let (nonce, sealed) = rest.split_at(NONCE_LEN);
+    let nonce =
+        Nonce::try_assume_unique_for_key(nonce).map_err(|_| invalid_data("bad session nonce"))?;
+
+    let mut sealed = sealed.to_vec();
+    let plain = derive_key(passphrase, salt)
+        .open_in_place(nonce, Aad::from(MAGIC), &mut sealed)
+        .map_err(|_| invalid_data("wrong session passphrase, or the session file is damaged"))?;
+    Ok(plain.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let data = b"session bytes";
+        let sealed = encrypt_data(data, "hunter2").unwrap();
+        assert!(sealed.starts_with(MAGIC));
+        assert!(!sealed.windows(data.len()).any(|w| w == data));
+        assert_eq!(decrypt(&sealed, "hunter2").unwrap(), data);
+    }
+
+    #[test]
+    fn wrong_passphrase_is_rejected() {
+        let sealed = encrypt_data(b"session bytes", "hunter2").unwrap();
+        let err = decrypt(&sealed, "hunter3").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn truncated_file_is_rejected() {
+        let sealed = encrypt_data(b"session bytes", "hunter2").unwrap();
+        // Too short to hold the salt, nonce and tag
+        let err = decrypt(&sealed[..MAGIC.len() + SALT_LEN], "hunter2").unwrap_err();
+        assert_eq!(err.to_string(), "encrypted session file is truncated");
+        // Long enough, but the ciphertext is cut off
+        let err = decrypt(&sealed[..sealed.len() - 1], "hunter2").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -1,60 +1,228 @@
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
-    widgets::{Block, Borders, List, ListItem, Paragraph},
+    symbols::border,
+    widgets::{Block, Borders, List, ListItem, Paragraph, Tabs},
     Frame,
 };
 
-use crate::app::{App, Mode, Panel};
+use std::ops::Range;
+use std::time::Duration;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
-/// Wrap text into lines that fit within max_width
-fn wrap_text(text: &str, max_width: usize) -> Vec<String> {
+use crate::app::{
+    unix_now, App, ConnectionState, Message, MessageKind, Mode, Panel, MUTE_FOREVER,
+    WELCOME_CHAT_ID,
+};
+use crate::config::{Config, SendKey, TimeZoneSetting, UnreadStyle};
+use crate::format::TextFormat;
+use crate::ui::glyphs::{glyphs, UNICODE};
+use crate::ui::keymap::{wrap_bindings, KEYMAP};
+
+/// Style of text under a formatting entity
+fn format_style(style: Style, format: TextFormat) -> Style {
+    match format {
+        TextFormat::Bold => style.add_modifier(Modifier::BOLD),
+        TextFormat::Italic => style.add_modifier(Modifier::ITALIC),
+        TextFormat::Strike => style.add_modifier(Modifier::CROSSED_OUT),
+        TextFormat::Code => style
+            .fg(Color::Rgb(230, 170, 120))
+            .bg(Color::Rgb(45, 45, 58)),
+    }
+}
+
+/// Highlight of text matching the last :search
+fn search_match_style() -> Style {
+    Style::default()
+        .fg(Color::Rgb(20, 20, 20))
+        .bg(Color::Rgb(230, 190, 80))
+}
+
+/// Spans of one wrapped line: its words joined by spaces, styled with the formatting
+/// covering them (byte ranges into `text`) on top of `base`, search matches highlighted
+/// and URLs underlined (byte ranges too, found in the whole text so a URL wrapped
+/// over several lines is still one)
+fn styled_line(
+    text: &str,
+    words: &[Range<usize>],
+    formats: &[(Range<usize>, TextFormat)],
+    matches: &[Range<usize>],
+    base: Style,
+    links: &[Range<usize>],
+) -> Vec<ratatui::text::Span<'static>> {
+    use ratatui::text::Span;
+
+    let link_style = Style::default()
+        .fg(Color::Rgb(100, 160, 255))
+        .add_modifier(Modifier::UNDERLINED);
+    let style_at = |at: usize| {
+        let mut style = formats
+            .iter()
+            .filter(|(range, _)| range.contains(&at))
+            .fold(base, |style, (_, format)| format_style(style, *format));
+        if matches.iter().any(|range| range.contains(&at)) {
+            style = style.patch(search_match_style());
+        }
+        if links.iter().any(|range| range.contains(&at)) {
+            style = style.patch(link_style);
+        }
+        style
+    };
+
+    // Style every character, then merge runs that share a style
+    let mut chars: Vec<(char, Style)> = Vec::new();
+    for (n, word) in words.iter().enumerate() {
+        if n > 0 {
+            // The space keeps a format that spans both words (e.g. a code snippet)
+            chars.push((' ', style_at(words[n - 1].end)));
+        }
+        for (i, c) in text[word.clone()].char_indices() {
+            chars.push((c, style_at(word.start + i)));
+        }
+    }
+
+    let mut spans = Vec::new();
+    let mut run = String::new();
+    let mut run_style = base;
+    for (c, style) in chars {
+        if style != run_style && !run.is_empty() {
+            spans.push(Span::styled(std::mem::take(&mut run), run_style));
+        }
+        run_style = style;
+        run.push(c);
+    }
+    if !run.is_empty() || spans.is_empty() {
+        spans.push(Span::styled(run, run_style));
+    }
+    spans
+}
+
+/// "🔇 until 15:00" style label for a mute expiry (in the configured time format)
+fn mute_label(until: i64, config: &Config) -> String {
+    let muted = glyphs(config).muted;
+    if until >= MUTE_FOREVER {
+        return muted.to_string();
+    }
+    let clock = config.format_time(until);
+    let same_day =
+        config.format_time_with(until, "%F") == config.format_time_with(unix_now(), "%F");
+    if same_day {
+        format!("{} until {}", muted, clock)
+    } else {
+        format!(
+            "{} until {} {}",
+            muted,
+            config.format_time_with(until, "%b %-d"),
+            clock
+        )
+    }
+}
+
+/// Byte ranges of `query` in `text`, ignoring case, not overlapping
+fn find_matches(text: &str, query: &str) -> Vec<Range<usize>> {
+    let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let mut matches = Vec::new();
+    let mut from = 0;
+    for (start, _) in text.char_indices() {
+        if start < from {
+            continue;
+        }
+        // Compared char by char so byte offsets stay those of `text`
+        let mut wanted = query.iter();
+        let mut end = None;
+        for (i, c) in text[start..].char_indices() {
+            if !c.to_lowercase().all(|c| wanted.next() == Some(&c)) {
+                break;
+            }
+            if wanted.len() == 0 {
+                end = Some(start + i + c.len_utf8());
+                break;
+            }
+        }
+        if let Some(end) = end {
+            matches.push(start..end);
+            from = end;
+        }
+    }
+    matches
+}
+
+/// Whether a line from `wrap_words` ends partway through a word
+fn is_cut(text: &str, words: &[Range<usize>]) -> bool {
+    words
+        .last()
+        .is_some_and(|word| text[word.end..].starts_with(|c: char| !c.is_whitespace()))
+}
+
+/// A wrapped line's words joined by single spaces
+fn join_words(text: &str, words: &[Range<usize>]) -> String {
+    words
+        .iter()
+        .map(|word| &text[word.clone()])
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Wrap text into lines that fit within max_width, each line given as the byte
+/// ranges of its words in `text` (so formatting offsets still apply). Words
+/// longer than a line are split over several, or with `truncate` kept to one
+/// line with room for a "…" (see `is_cut`)
+fn wrap_words(text: &str, max_width: usize, truncate: bool) -> Vec<Vec<Range<usize>>> {
     if max_width == 0 {
-        return vec![text.to_string()];
+        return vec![vec![0..text.len()]];
     }
 
     let mut lines = Vec::new();
-    let mut current_line = String::new();
-
-    for word in text.split_whitespace() {
+    let mut current_line: Vec<Range<usize>> = Vec::new();
+    let mut current_len = 0;
+    let mut offset = 0;
+
+    for piece in text.split_inclusive(char::is_whitespace) {
+        let start = offset;
+        offset += piece.len();
+        let word = piece.strip_suffix(char::is_whitespace).unwrap_or(piece);
+        if word.is_empty() {
+            continue;
+        }
         let word_len = word.chars().count();
-        let current_len = current_line.chars().count();
-
-        if current_len == 0 {
-            // First word on line
-            if word_len > max_width {
-                // Word too long, split it
-                let mut chars = word.chars();
-                while chars.clone().count() > 0 {
-                    let chunk: String = chars.by_ref().take(max_width).collect();
-                    if chunk.is_empty() {
-                        break;
-                    }
-                    lines.push(chunk);
-                }
-            } else {
-                current_line = word.to_string();
-            }
-        } else if current_len + 1 + word_len <= max_width {
+
+        if current_len > 0 && current_len + 1 + word_len <= max_width {
             // Word fits on current line
-            current_line.push(' ');
-            current_line.push_str(word);
-        } else {
+            current_line.push(start..start + word.len());
+            current_len += 1 + word_len;
+            continue;
+        }
+        if current_len > 0 {
             // Word doesn't fit, start new line
-            lines.push(current_line);
-            if word_len > max_width {
-                let mut chars = word.chars();
-                while chars.clone().count() > 0 {
-                    let chunk: String = chars.by_ref().take(max_width).collect();
-                    if chunk.is_empty() {
-                        break;
-                    }
-                    lines.push(chunk);
+            lines.push(std::mem::take(&mut current_line));
+            current_len = 0;
+        }
+        if word_len > max_width && truncate {
+            // Word too long, keep what fits before the "…"
+            let end = word
+                .char_indices()
+                .nth(max_width - 1)
+                .map_or(word.len(), |(i, _)| i);
+            let chunk = start..start + end;
+            lines.push(vec![chunk]);
+        } else if word_len > max_width {
+            // Word too long, split it into lines of max_width characters
+            let mut chunk_start = start;
+            for (n, (i, _)) in word.char_indices().enumerate() {
+                if n > 0 && n % max_width == 0 {
+                    let chunk = chunk_start..start + i;
+                    lines.push(vec![chunk]);
+                    chunk_start = start + i;
                 }
-                current_line = String::new();
-            } else {
-                current_line = word.to_string();
             }
+            let chunk = chunk_start..start + word.len();
+            lines.push(vec![chunk]);
+        } else {
+            current_line.push(start..start + word.len());
+            current_len = word_len;
         }
     }
 
@@ -63,45 +231,119 @@ fn wrap_text(text: &str, max_width: usize) -> Vec<String> {
     }
 
     if lines.is_empty() {
-        lines.push(String::new());
+        lines.push(Vec::new());
     }
 
     lines
 }
 
+/// Border symbols for the configured border style (plain ASCII with `ascii_only`)
+fn border_set(config: &Config) -> border::Set {
+    glyphs(config).border_set(config.border_style)
+}
+
 /// Main UI drawing function
 pub fn draw(frame: &mut Frame, app: &App) {
-    // Main container with outer border
-    let outer = Block::default()
-        .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Rgb(70, 130, 180))) // Steel blue
-        .title(" Bifrost ");
-
-    let inner_area = outer.inner(frame.area());
-    frame.render_widget(outer, frame.area());
-
-    // Split into friends (left) and right side (chats + input)
-    let horizontal = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Percentage(30), // Friends panel (full height)
-            Constraint::Percentage(70), // Right side: Chats + Input
-        ])
-        .split(inner_area);
-
-    // Split right side into chats and input box
-    let right_vertical = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Min(5),    // Chats panel
-            Constraint::Length(3), // Input box (under chats only)
-        ])
-        .split(horizontal[1]);
+    // Locked: nothing of the chats is drawn at all
+    if app.locked {
+        draw_lock_screen(frame, app, frame.area());
+        return;
+    }
+
+    // Main container with outer border (can be turned off to save space)
+    let inner_area = if app.config.outer_frame {
+        // Tinted with the active account's accent, named too when there are several
+        let (r, g, b) = app.account_accent(&app.current_account_id);
+        let accent = Color::Rgb(r, g, b);
+        let mut outer = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(accent))
+            .border_set(border_set(&app.config))
+            .title(" Bifrost ");
+        let active = app
+            .account_names
+            .iter()
+            .find(|(id, _)| *id == app.current_account_id);
+        if let Some((_, name)) = active.filter(|_| app.account_names.len() > 1) {
+            outer = outer.title(
+                ratatui::text::Line::styled(
+                    format!(" {} {} ", glyphs(&app.config).active, name),
+                    Style::default().fg(accent).add_modifier(Modifier::BOLD),
+                )
+                .right_aligned(),
+            );
+        }
+        let inner_area = outer.inner(frame.area());
+        frame.render_widget(outer, frame.area());
+        inner_area
+    } else {
+        frame.area()
+    };
 
-    // Draw panels
-    draw_friends_panel(frame, app, horizontal[0]);
-    draw_chats_panel(frame, app, right_vertical[0]);
-    draw_input_box(frame, app, right_vertical[1]);
+    // Input box height grows with multi-line input, up to 5 lines
+    let input_lines = app.input.split('\n').count().min(5) as u16;
+
+    if inner_area.width < app.config.compact_width {
+        // Narrow terminal: one panel at a time, full width, with the input box below
+        let vertical = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(5),                  // Friends or chats panel
+                Constraint::Length(input_lines + 2), // Input box
+            ])
+            .split(inner_area);
+        let show_friends =
+            app.mode == Mode::Search || (app.panel == Panel::Friends && app.mode != Mode::Insert);
+        if show_friends {
+            draw_friends_panel(frame, app, vertical[0]);
+        } else {
+            draw_chats_panel(frame, app, vertical[0]);
+        }
+        draw_input_box(frame, app, vertical[1]);
+    } else if app.config.full_width_input {
+        // Input box along the whole bottom, under both panels
+        let vertical = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(5),                  // Friends and chats panels
+                Constraint::Length(input_lines + 2), // Input box (full width)
+            ])
+            .split(inner_area);
+        let horizontal = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Percentage(30), // Friends panel
+                Constraint::Percentage(70), // Chats panel
+            ])
+            .split(vertical[0]);
+
+        draw_friends_panel(frame, app, horizontal[0]);
+        draw_chats_panel(frame, app, horizontal[1]);
+        draw_input_box(frame, app, vertical[1]);
+    } else {
+        // Split into friends (left) and right side (chats + input)
+        let horizontal = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Percentage(30), // Friends panel (full height)
+                Constraint::Percentage(70), // Right side: Chats + Input
+            ])
+            .split(inner_area);
+
+        // Split right side into chats and input box
+        let right_vertical = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(5),                  // Chats panel
+                Constraint::Length(input_lines + 2), // Input box (under chats only)
+            ])
+            .split(horizontal[1]);
+
+        // Draw panels
+        draw_friends_panel(frame, app, horizontal[0]);
+        draw_chats_panel(frame, app, right_vertical[0]);
+        draw_input_box(frame, app, right_vertical[1]);
+    }
 
     // Draw account picker overlay if in that mode
     if app.mode == Mode::AccountPicker {
@@ -122,14 +364,126 @@ pub fn draw(frame: &mut Frame, app: &App) {
     if app.mode == Mode::Code {
         draw_code_overlay(frame, app, frame.area());
     }
+
+    // Draw account details overlay if in that mode
+    if app.mode == Mode::WhoAmI {
+        draw_whoami(frame, app, frame.area());
+    }
+
+    // Draw session stats overlay if in that mode
+    if app.mode == Mode::Stats {
+        draw_stats(frame, app, frame.area());
+    }
+
+    // Draw link picker overlay if in that mode
+    if app.mode == Mode::LinkPicker {
+        draw_link_picker(frame, app, frame.area());
+    }
+
+    // Draw forum topic picker if in that mode
+    if app.mode == Mode::TopicPicker {
+        draw_topic_picker(frame, app, frame.area());
+    }
+
+    // Draw sticker picker if in that mode
+    if app.mode == Mode::StickerPicker {
+        draw_sticker_picker(frame, app, frame.area());
+    }
+
+    // Draw full message view if in that mode
+    if app.mode == Mode::MessageView {
+        draw_message_view(frame, app, frame.area());
+    }
+
+    // Draw the message being quoted if picking its words
+    if app.mode == Mode::Quote {
+        draw_quote_picker(frame, app, frame.area());
+    }
+
+    // Draw pinned message list if in that mode
+    if app.mode == Mode::PinList {
+        draw_pin_list(frame, app, frame.area());
+    }
+
+    // Draw confirmation prompt if in that mode
+    if app.mode == Mode::Confirm {
+        draw_confirm(frame, app, frame.area());
+    }
+
+    // Draw the scratchpad if it's open
+    if app.mode == Mode::Scratchpad {
+        draw_scratchpad(frame, app, frame.area());
+    }
+
+    // Draw the command palette if it's open
+    if app.mode == Mode::CommandPalette {
+        draw_palette(frame, app, frame.area());
+    }
+}
+
+/// Cut text to at most `max_width` terminal columns, ending in `ellipsis` (one
+/// column) when cut (wide characters like CJK count as two columns)
+fn truncate_to_width(text: &str, max_width: usize, ellipsis: &str) -> String {
+    if text.width() <= max_width {
+        return text.to_string();
+    }
+    let mut out = String::new();
+    let mut width = 0;
+    for c in text.chars() {
+        let char_width = c.width().unwrap_or(0);
+        if width + char_width + 1 > max_width {
+            break;
+        }
+        out.push(c);
+        width += char_width;
+    }
+    if max_width > 0 {
+        out.push_str(ellipsis);
+    }
+    out
+}
+
+/// Unread indicator after a chat name, in the configured style
+fn unread_spans(
+    unread: u32,
+    style: UnreadStyle,
+    bullet: &str,
+) -> Vec<ratatui::text::Span<'static>> {
+    use ratatui::text::Span;
+
+    match style {
+        UnreadStyle::Count => vec![Span::raw(format!(" ({})", unread))],
+        UnreadStyle::Badge => vec![
+            Span::raw(" "),
+            Span::styled(
+                format!(" {} ", unread),
+                Style::default()
+                    .fg(Color::Rgb(20, 20, 30))
+                    .bg(Color::Rgb(100, 180, 255))
+                    .add_modifier(Modifier::BOLD),
+            ),
+        ],
+        UnreadStyle::Bullet => vec![Span::styled(
+            format!(" {}", bullet),
+            Style::default().fg(Color::Rgb(100, 180, 255)),
+        )],
+        UnreadStyle::Hidden => Vec::new(),
+    }
 }
 
 /// Draw the friends/contacts list panel
 fn draw_friends_panel(frame: &mut Frame, app: &App, area: Rect) {
+    use ratatui::text::{Line, Span};
+
+    let g = glyphs(&app.config);
     let is_focused = app.panel == Panel::Friends;
     let is_search_mode = app.mode == Mode::Search;
 
-    let border_color = if is_search_mode {
+    let no_matches = is_search_mode && app.filtered_chat_indices.is_empty();
+
+    let border_color = if no_matches {
+        Color::Rgb(220, 80, 80) // Red when the search matches nothing
+    } else if is_search_mode {
         Color::Rgb(255, 180, 50) // Orange/yellow when in search mode
     } else if is_focused {
         Color::Rgb(70, 130, 180) // Bright blue when focused
@@ -137,13 +491,25 @@ fn draw_friends_panel(frame: &mut Frame, app: &App, area: Rect) {
         Color::Rgb(50, 50, 60) // Dim when not focused
     };
 
+    let now = unix_now();
+    let inner_width = area.width.saturating_sub(2) as usize; // Inside the borders
+
     // Determine which chats to display
     let (display_indices, highlight_idx): (Vec<usize>, usize) = if is_search_mode {
         (app.filtered_chat_indices.clone(), app.search_selected)
     } else {
-        ((0..app.chats.len()).collect(), app.selected_chat)
+        let visible = app.folder_chat_indices();
+        let highlight = visible
+            .iter()
+            .position(|&i| i == app.selected_chat)
+            .unwrap_or(usize::MAX);
+        (visible, highlight)
     };
 
+    // Numbers to type in NumberJump mode, right-aligned in place of the "> " prefix
+    let number_jump = app.mode == Mode::NumberJump;
+    let number_width = display_indices.len().to_string().len();
+
     let items: Vec<ListItem> = display_indices
         .iter()
         .enumerate()
@@ -162,165 +528,599 @@ fn draw_friends_panel(frame: &mut Frame, app: &App, area: Rect) {
                     Style::default().fg(Color::Rgb(180, 180, 180))
                 };
 
-                let prefix = if is_selected && (is_focused || is_search_mode) {
-                    "> "
+                let jump_label =
+                    number_jump.then(|| format!("{:>w$} ", display_idx + 1, w = number_width));
+                let prefix = match &jump_label {
+                    Some(label) => label.as_str(),
+                    None if is_selected && (is_focused || is_search_mode) => "> ",
+                    None => "  ",
+                };
+                let style = if chat.unread > 0 && app.config.unread_bold {
+                    style.add_modifier(Modifier::BOLD)
                 } else {
-                    "  "
+                    style
+                };
+
+                let marker = |on: bool, glyph: &str| {
+                    if on {
+                        format!("{} ", glyph)
+                    } else {
+                        String::new()
+                    }
                 };
-                let unread = if chat.unread > 0 {
-                    format!(" ({})", chat.unread)
+                let muted = if chat.is_muted(now) {
+                    format!(" {}", g.muted)
                 } else {
                     String::new()
                 };
+                let notify = g.notify(app.notify_level(chat.id));
+                let bot = marker(chat.is_bot, g.bot);
+                let blocked = marker(chat.is_blocked, g.blocked);
+
+                let mut unread = if chat.unread > 0 {
+                    unread_spans(chat.unread, app.config.unread_style, g.bullet)
+                } else {
+                    Vec::new()
+                };
+                if chat.mentions > 0 {
+                    unread.insert(
+                        0,
+                        Span::styled(
+                            format!(" @{}", chat.mentions),
+                            Style::default()
+                                .fg(Color::Rgb(255, 160, 60))
+                                .add_modifier(Modifier::BOLD),
+                        ),
+                    );
+                }
+
+                // The name gets what's left after the prefix, markers and unread count
+                let reserved = prefix.width()
+                    + bot.width()
+                    + blocked.width()
+                    + muted.width()
+                    + notify.width()
+                    + unread.iter().map(|s| s.width()).sum::<usize>();
+                let mut name_width = inner_width.saturating_sub(reserved);
+                if app.config.max_name_width > 0 {
+                    name_width = name_width.min(app.config.max_name_width as usize);
+                }
+                let name = truncate_to_width(&chat.name, name_width, g.ellipsis);
+
+                let mut spans = Vec::new();
+                if let Some(label) = &jump_label {
+                    // Numbers that no longer fit what's typed fade out
+                    let matching = (display_idx + 1)
+                        .to_string()
+                        .starts_with(&app.number_jump_input);
+                    let label_style = if matching {
+                        Style::default()
+                            .fg(Color::Rgb(255, 200, 80))
+                            .add_modifier(Modifier::BOLD)
+                    } else {
+                        Style::default().fg(Color::Rgb(80, 80, 90))
+                    };
+                    spans.push(Span::styled(label.clone(), label_style));
+                }
+                spans.push(Span::raw(format!(
+                    "{}{}{}{}{}{}",
+                    if jump_label.is_some() { "" } else { prefix },
+                    bot,
+                    blocked,
+                    name,
+                    muted,
+                    notify
+                )));
+                spans.extend(unread);
+                let mut lines = vec![Line::from(spans)];
+
+                // Dim last message under the name, indented past the prefix
+                if app.config.chat_preview {
+                    let text = chat
+                        .last_message
+                        .as_deref()
+                        .unwrap_or("")
+                        .replace(['\n', '\r'], " ");
+                    let from_you = if chat.last_outgoing && !text.trim().is_empty() {
+                        "You: "
+                    } else {
+                        ""
+                    };
+                    let preview = truncate_to_width(
+                        &format!("{}{}", from_you, text.trim()),
+                        inner_width.saturating_sub(prefix.width()),
+                        g.ellipsis,
+                    );
+                    lines.push(Line::from(Span::styled(
+                        format!("{}{}", " ".repeat(prefix.width()), preview),
+                        Style::default()
+                            .fg(Color::Rgb(110, 110, 120))
+                            .remove_modifier(Modifier::BOLD),
+                    )));
+                }
 
-                ListItem::new(format!("{}{}{}", prefix, chat.name, unread)).style(style)
+                ListItem::new(lines).style(style)
             })
         })
         .collect();
 
-    // Build title with search input if in search mode
-    let title = if is_search_mode {
-        format!(" /{}▏", app.search_input)
+    // Build title with search input if in search mode, otherwise the inbox total
+    let total_unread: u32 = app.chats.iter().map(|c| c.unread).sum();
+    let title = if no_matches {
+        format!(" /{}{} no matches ", app.search_input, g.caret)
+    } else if is_search_mode {
+        let count = app.filtered_chat_indices.len();
+        let noun = if count == 1 { "match" } else { "matches" };
+        format!(" /{}{} {} {} ", app.search_input, g.caret, count, noun)
+    } else if total_unread > 0 {
+        format!(" friends ({}) ", total_unread)
     } else {
         " friends ".to_string()
     };
+    // The chat filter in force, F to change it
+    let title = match app.chat_filter {
+        Some(filter) => format!("{}[{}] ", title, filter.label()),
+        None => title,
+    };
+    let title = match app.connection {
+        ConnectionState::Online => title,
+        ConnectionState::Reconnecting => format!("{}{} reconnecting ", title, g.reconnecting),
+        ConnectionState::Offline => format!("{}{} offline ", title, g.offline),
+        ConnectionState::Lost => format!("{}{} connection lost, r retries ", title, g.offline),
+    };
+
+    // Folder tabs above the list ([ and ] to cycle)
+    let area = if app.folders.is_empty() {
+        area
+    } else {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(3)])
+            .split(area);
+        let titles = std::iter::once("All chats")
+            .chain(app.folders.iter().map(|f| f.title.as_str()))
+            .map(|t| format!(" {} ", t));
+        let tabs = Tabs::new(titles)
+            .select(app.active_folder)
+            .style(Style::default().fg(Color::Rgb(120, 120, 130)))
+            .highlight_style(
+                Style::default()
+                    .fg(Color::Rgb(70, 130, 180))
+                    .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            )
+            .divider(g.divider)
+            .padding("", "");
+        frame.render_widget(tabs, chunks[0]);
+        chunks[1]
+    };
 
     let list = List::new(items).block(
         Block::default()
             .borders(Borders::ALL)
             .border_style(Style::default().fg(border_color))
-            .border_type(ratatui::widgets::BorderType::Rounded)
+            .border_set(border_set(&app.config))
             .title(title),
     );
 
     frame.render_widget(list, area);
 }
 
-/// Draw the messages/chats panel
-fn draw_chats_panel(frame: &mut Frame, app: &App, area: Rect) {
-    use ratatui::layout::Alignment;
-    use ratatui::text::{Line, Span};
-
-    let is_focused = app.panel == Panel::Chats;
-    let border_color = if is_focused {
-        Color::Rgb(70, 130, 180)
-    } else {
-        Color::Rgb(50, 50, 60)
-    };
-
-    // Check if this is the Welcome chat (id=1) - show centered welcome box
-    let is_welcome_chat = app.current_chat_id() == Some(1);
-
-    if is_welcome_chat {
-        // Draw centered welcome box
-        draw_welcome_box(frame, area, border_color);
-        return;
-    }
+/// Inputs the chat panel layout depends on; any change forces a rebuild
+#[derive(PartialEq)]
+struct LayoutKey {
+    chat_id: Option<i64>,
+    chat_name: String,
+    topic: Option<i32>,
+    panel_width: usize,
+    bubble_width_pct: u16,
+    group_window_secs: u64,
+    compact: bool,
+    truncate_long_words: bool,
+    ascii_only: Option<bool>,
+    search: Option<String>,
+    time_format: String,
+    time_zone: TimeZoneSetting,
+    revision: u64,
+}
 
-    // Max bubble width = 60% of panel width
-    let panel_width = area.width.saturating_sub(4) as usize;
-    let max_bubble_width = (panel_width * 60) / 100;
+/// Laid-out message lines of the open chat, reused across frames
+#[derive(Default)]
+pub struct LayoutCache {
+    key: Option<LayoutKey>,
+    items: Vec<ListItem<'static>>,
+    rows: Vec<Range<usize>>, // Item range of each shown message, oldest first
+    matches: Vec<(usize, Range<usize>)>, // (message index, byte range) of each search match
+}
 
-    let messages = app.current_messages();
-    let mut items: Vec<ListItem> = Vec::new();
+/// Laid-out lines of the open chat: the list items, the item range of each shown
+/// message and where the :search query matches
+type MessageLayout = (
+    Vec<ListItem<'static>>,
+    Vec<Range<usize>>,
+    Vec<(usize, Range<usize>)>,
+);
+
+/// Lay out the shown messages of the current chat (those of the active
+/// topic, if one is picked) as list lines
+fn build_message_items(app: &App, panel_width: usize) -> MessageLayout {
+    use ratatui::text::{Line, Span};
 
-    for msg in messages.iter() {
-        let text = msg.text.trim();
+    let g = glyphs(&app.config);
+
+    // Max bubble width as a configurable share of the panel width
+    let max_bubble_width = (panel_width * app.config.bubble_width_pct as usize) / 100;
+
+    let pinned_ids: Vec<i32> = app
+        .chats
+        .get(app.selected_chat)
+        .map(|c| c.pinned.iter().map(|(id, _)| *id).collect())
+        .unwrap_or_default();
+
+    let messages = app.shown_messages();
+    let mut items: Vec<ListItem<'static>> = Vec::new();
+    let mut rows: Vec<Range<usize>> = Vec::new();
+    let mut matches: Vec<(usize, Range<usize>)> = Vec::new();
+    let mut previous: Option<&Message> = None; // Last message that got a bubble
+    let spaced = !app.config.compact; // Blank line between messages
+
+    for (index, msg) in messages.iter().enumerate() {
+        // Media shows as its label, ahead of the caption if there is one
+        let text = match (msg.media_text(g), msg.text.trim()) {
+            _ if msg.deleted => "this message was deleted".to_string(),
+            (Some(label), "") => label,
+            (Some(label), caption) => format!("{} {}", label, caption),
+            (None, text) if msg.kind == MessageKind::Service => g.with_media(msg.media_kind, text),
+            (None, text) => text.to_string(),
+        };
+
+        // Where the :search query matches, found once per layout so every line
+        // of a wrapped message highlights the same text however it's scrolled
+        let first_match = matches.len();
+        if let Some(query) = app.message_search_query.as_deref().filter(|_| !msg.deleted) {
+            matches.extend(
+                find_matches(&text, query)
+                    .into_iter()
+                    .map(|range| (index, range)),
+            );
+        }
+        let message_matches: Vec<Range<usize>> = matches[first_match..]
+            .iter()
+            .map(|(_, range)| range.clone())
+            .collect();
+
+        // Formatting offsets, moved past the trimmed whitespace and media label
+        let formats: Vec<(Range<usize>, TextFormat)> = if msg.deleted {
+            Vec::new()
+        } else {
+            let trimmed = msg.text.len() - msg.text.trim_start().len();
+            let shift = text.len() - msg.text.trim().len();
+            msg.formats
+                .iter()
+                .map(|(range, format)| {
+                    let start = range.start.saturating_sub(trimmed) + shift;
+                    let end = range.end.saturating_sub(trimmed) + shift;
+                    (start..end.min(text.len()), *format)
+                })
+                .collect()
+        };
 
         // Skip empty messages
         if text.is_empty() {
             continue;
         }
 
+        // Service messages are one dimmed, centered line, and end any sender group
+        if msg.kind == MessageKind::Service && !msg.deleted {
+            previous = None;
+            let first_row = items.len();
+            let label = format!("{} {} {}", g.dash, text, g.dash);
+            let padding = panel_width.saturating_sub(label.chars().count()) / 2;
+            items.push(ListItem::new(Line::from(vec![
+                Span::raw(" ".repeat(padding)),
+                Span::styled(
+                    label,
+                    Style::default()
+                        .fg(Color::Rgb(110, 110, 130))
+                        .add_modifier(Modifier::ITALIC),
+                ),
+            ])));
+            if spaced {
+                items.push(ListItem::new(Line::from("")));
+            }
+            rows.push(first_row..items.len());
+            continue;
+        }
+
+        // A named message right after one from the same sender joins its group:
+        // no name, and no blank line between the two
+        let grouped = app.config.group_window_secs > 0
+            && msg.display_name.is_some()
+            && msg.forwarded_from.is_none()
+            && previous.is_some_and(|prev| {
+                prev.display_name == msg.display_name
+                    && msg.date.saturating_sub(prev.date) <= app.config.group_window_secs as i64
+            });
+        previous = Some(msg);
+        if grouped && spaced {
+            items.pop();
+            if let Some(prev_rows) = rows.last_mut() {
+                prev_rows.end -= 1;
+            }
+        }
+        let first_row = items.len();
+
+        // "Forwarded from" line above the bubble, on the bubble's side
+        if let Some(origin) = msg.forwarded_from.as_deref().filter(|_| !msg.deleted) {
+            let label = if origin.is_empty() {
+                format!("{} Forwarded", g.forwarded)
+            } else {
+                format!("{} Forwarded from {}", g.forwarded, origin)
+            };
+            let padding = if msg.outgoing {
+                panel_width.saturating_sub(label.chars().count())
+            } else {
+                2
+            };
+            items.push(ListItem::new(Line::from(vec![
+                Span::raw(" ".repeat(padding)),
+                Span::styled(
+                    label,
+                    Style::default()
+                        .fg(Color::Rgb(130, 130, 150))
+                        .add_modifier(Modifier::ITALIC),
+                ),
+            ])));
+        }
+
+        // Deleted placeholders are dimmed regardless of direction
+        let deleted_style = Style::default()
+            .fg(Color::Rgb(90, 90, 100))
+            .add_modifier(Modifier::ITALIC);
+
         // Wrap text into lines that fit the bubble
         let wrap_width = max_bubble_width.saturating_sub(4);
-        let wrapped_lines = wrap_text(text, wrap_width);
+        let truncate = app.config.truncate_long_words;
+        let wrapped_words = wrap_words(&text, wrap_width, truncate);
+        // Lines ending in a truncated word get an ellipsis (K shows the whole message)
+        let cut: Vec<bool> = wrapped_words
+            .iter()
+            .map(|words| truncate && is_cut(&text, words))
+            .collect();
+        let wrapped_lines: Vec<String> = wrapped_words
+            .iter()
+            .zip(&cut)
+            .map(|(words, &cut)| {
+                let line = join_words(&text, words);
+                if cut {
+                    line + g.ellipsis
+                } else {
+                    line
+                }
+            })
+            .collect();
+        let last_line = wrapped_lines.len() - 1;
+
+        // Dim "(edited)" tag and send time appended to the last line
+        let edited_tag = format!(
+            "{}  {}",
+            if msg.edited && !msg.deleted {
+                " (edited)"
+            } else {
+                ""
+            },
+            app.config.format_time(msg.date)
+        );
+        let edited_style = Style::default().fg(Color::Rgb(90, 90, 100));
+
+        // Pin before the first line of pinned messages (📌 is 2 columns wide, 1 char)
+        let pin_marker = if pinned_ids.contains(&msg.id) {
+            format!("{} ", g.pin)
+        } else {
+            String::new()
+        };
+
+        // Links are only highlighted in live messages
+        let links = if msg.deleted {
+            Vec::new()
+        } else {
+            crate::links::find_urls(&text)
+        };
+        let body_spans = |i: usize, style: Style| {
+            let mut spans = styled_line(
+                &text,
+                &wrapped_words[i],
+                &formats,
+                &message_matches,
+                style,
+                &links,
+            );
+            if cut[i] {
+                spans.push(Span::styled(g.ellipsis, style));
+            }
+            spans
+        };
 
         if msg.outgoing {
             // Outgoing: right-aligned green text
-            let style = Style::default().fg(Color::Rgb(100, 200, 100));
+            let style = if msg.deleted {
+                deleted_style
+            } else {
+                Style::default().fg(Color::Rgb(100, 200, 100))
+            };
             let prefix_style = Style::default().fg(Color::Rgb(60, 140, 60));
 
             for (i, line_text) in wrapped_lines.iter().enumerate() {
-                let prefix = if i == 0 { "▸ " } else { "  " };
-                let content = format!("{}{}", prefix, line_text);
-                let padding = panel_width.saturating_sub(content.chars().count());
+                let prefix = if i == 0 {
+                    format!("{} ", g.outgoing)
+                } else {
+                    " ".repeat(g.outgoing.width() + 1)
+                };
+                let marker = if i == 0 { pin_marker.as_str() } else { "" };
+                let tag = if i == last_line {
+                    edited_tag.as_str()
+                } else {
+                    ""
+                };
+                let content = format!("{}{}{}{}", prefix, marker, line_text, tag);
+                let marker_extra = marker.width() - marker.chars().count();
+                let padding = panel_width.saturating_sub(content.chars().count() + marker_extra);
 
-                items.push(ListItem::new(Line::from(vec![
+                let mut spans = vec![
                     Span::raw(" ".repeat(padding)),
                     Span::styled(prefix, prefix_style),
-                    Span::styled(line_text.clone(), style),
-                ])));
+                    Span::raw(marker.to_string()),
+                ];
+                spans.extend(body_spans(i, style));
+                spans.push(Span::styled(tag.to_string(), edited_style));
+                items.push(ListItem::new(Line::from(spans)));
             }
             // Blank line after message
-            items.push(ListItem::new(Line::from("")));
-        } else {
-            // Incoming: sender name then message
-            let sender_display: String = msg.sender.chars().take(20).collect();
-            if sender_display.trim().is_empty() {
-                // Don't force "Unknown", just leave it empty
-                // sender_display = "Unknown".to_string();
+            if spaced {
+                items.push(ListItem::new(Line::from("")));
             }
-
+        } else {
+            // Incoming: sender name (groups only, resolved at load time) then message
             let sender_style = Style::default()
                 .fg(Color::Rgb(100, 180, 255))
                 .add_modifier(Modifier::BOLD);
-            let text_style = Style::default().fg(Color::Rgb(200, 200, 200));
+            let text_style = if msg.deleted {
+                deleted_style
+            } else {
+                Style::default().fg(Color::Rgb(200, 200, 200))
+            };
 
             // First line: sender + text
-
-            // Heuristic: If sender name matches the Chat Title (and it's not empty),
-            // it's likely a DM where the header already says who it is.
-            let mut current_chat_name = "Unknown";
-            if let Some(c) = app.chats.get(app.selected_chat) {
-                current_chat_name = &c.name;
-            }
-
-            if let Some(first_line) = wrapped_lines.first() {
-                // Hide if explicitly "Unknown", empty, or matches chat title (DM)
-                let should_hide_name = sender_display == "Unknown"
-                    || sender_display.trim().is_empty()
-                    || (sender_display == current_chat_name && current_chat_name != "Unknown");
-
-                if should_hide_name {
-                    // Hide sender name, just show text (padded to align with other lines if desirable,
-                    // or just flush left. Standard TUI chat usually aligns flush left if no name).
-                    items.push(ListItem::new(Line::from(vec![
-                        Span::raw("  "), // Left padding
-                        Span::styled(first_line.clone(), text_style),
-                    ])));
+            if !wrapped_lines.is_empty() {
+                let tag = if last_line == 0 {
+                    edited_tag.as_str()
                 } else {
-                    // Show sender name
-                    // Pad aggressively to 20 chars to wipe any "Unknown" ghosting or artifacts
-                    // format!("{:<20}", s) pads right with spaces to length 20.
-                    items.push(ListItem::new(Line::from(vec![
-                        Span::raw("  "), // Left padding
-                        Span::styled(format!("{:<20}", sender_display), sender_style),
-                        Span::raw(": "),
-                        Span::styled(first_line.clone(), text_style),
-                    ])));
+                    ""
+                };
+                let mut spans = vec![Span::raw("  ")]; // Left padding
+                match &msg.display_name {
+                    // Indented under the group's first message
+                    Some(name) if grouped => {
+                        spans.push(Span::raw(" ".repeat(name.chars().count() + 2)))
+                    }
+                    Some(name) => {
+                        spans.push(Span::styled(name.clone(), sender_style));
+                        spans.push(Span::raw(": "));
+                    }
+                    None => {}
                 }
+                spans.push(Span::raw(pin_marker.clone()));
+                spans.extend(body_spans(0, text_style));
+                spans.push(Span::styled(tag.to_string(), edited_style));
+                items.push(ListItem::new(Line::from(spans)));
             }
 
-            // Continuation lines with indent
-            let should_hide_name = sender_display == "Unknown"
-                || sender_display.trim().is_empty()
-                || (sender_display == current_chat_name && current_chat_name != "Unknown");
-
-            let indent_len = if should_hide_name {
-                2 // Just the left padding
-            } else {
-                sender_display.chars().count() + 4 + 2 // Name + ": " + left padding
+            // Continuation lines line up with the first line's text
+            let indent_len = match &msg.display_name {
+                Some(name) => 2 + name.chars().count() + 2, // Left padding + name + ": "
+                None => 2,                                  // Just the left padding
             };
 
-            for line_text in wrapped_lines.iter().skip(1) {
-                items.push(ListItem::new(Line::from(vec![
-                    Span::raw(" ".repeat(indent_len)),
-                    Span::styled(line_text.clone(), text_style),
-                ])));
+            for i in 1..wrapped_lines.len() {
+                let tag = if i == last_line {
+                    edited_tag.as_str()
+                } else {
+                    ""
+                };
+                let mut spans = vec![Span::raw(" ".repeat(indent_len))];
+                spans.extend(body_spans(i, text_style));
+                spans.push(Span::styled(tag.to_string(), edited_style));
+                items.push(ListItem::new(Line::from(spans)));
             }
             // Blank line after message
-            items.push(ListItem::new(Line::from("")));
+            if spaced {
+                items.push(ListItem::new(Line::from("")));
+            }
+        }
+        rows.push(first_row..items.len());
+    }
+
+    (items, rows, matches)
+}
+
+/// Draw the messages/chats panel
+fn draw_chats_panel(frame: &mut Frame, app: &App, area: Rect) {
+    let g = glyphs(&app.config);
+    let is_focused = app.panel == Panel::Chats;
+    let border_color = if is_focused {
+        Color::Rgb(70, 130, 180)
+    } else {
+        Color::Rgb(50, 50, 60)
+    };
+
+    // Check if this is the Welcome chat (id=1) - show centered welcome box
+    let is_welcome_chat = app.current_chat_id() == Some(WELCOME_CHAT_ID);
+
+    if is_welcome_chat {
+        // Draw centered welcome box
+        draw_welcome_box(frame, area, border_color, &app.config);
+        return;
+    }
+
+    // Pinned banner above the messages, cycled with P
+    let area = match app.chats.get(app.selected_chat) {
+        Some(chat) if !chat.pinned.is_empty() => {
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(3), Constraint::Min(3)])
+                .split(area);
+            draw_pinned_banner(
+                frame,
+                &chat.pinned,
+                app.pinned_index,
+                &app.config,
+                chunks[0],
+            );
+            chunks[1]
+        }
+        _ => area,
+    };
+
+    // Rebuild the laid-out messages only when something they depend on changed
+    let panel_width = area.width.saturating_sub(4) as usize;
+    let key = LayoutKey {
+        chat_id: app.current_chat_id(),
+        chat_name: app
+            .chats
+            .get(app.selected_chat)
+            .map(|c| c.name.clone())
+            .unwrap_or_default(),
+        topic: app.current_topic().map(|(id, _)| *id),
+        panel_width,
+        bubble_width_pct: app.config.bubble_width_pct,
+        group_window_secs: app.config.group_window_secs,
+        compact: app.config.compact,
+        truncate_long_words: app.config.truncate_long_words,
+        ascii_only: app.config.ascii_only,
+        search: app.message_search_query.clone(),
+        time_format: app.config.time_format.clone(),
+        time_zone: app.config.time_zone,
+        revision: app.messages_revision,
+    };
+    let mut cache = app.layout_cache.borrow_mut();
+    if cache.key.as_ref() != Some(&key) {
+        // Only new messages changed (same chat and layout settings)
+        let same_layout = cache.key.as_ref().is_some_and(|old| {
+            old.chat_id == key.chat_id
+                && old.topic == key.topic
+                && old.panel_width == key.panel_width
+                && old.bubble_width_pct == key.bubble_width_pct
+                && old.group_window_secs == key.group_window_secs
+                && old.compact == key.compact
+                && old.truncate_long_words == key.truncate_long_words
+        });
+        let old_total = cache.items.len();
+        (cache.items, cache.rows, cache.matches) = build_message_items(app, panel_width);
+        cache.key = Some(key);
+
+        // Not following new messages: shift by what was added so the view stays put
+        if same_layout && app.new_below > 0 {
+            let grown = cache.items.len().saturating_sub(old_total);
+            app.scroll_offset.set(app.scroll_offset.get() + grown);
         }
     }
 
@@ -328,25 +1128,106 @@ fn draw_chats_panel(frame: &mut Frame, app: &App, area: Rect) {
     let title = if let Some(status) = &app.loading_status {
         format!(" {} ", status)
     } else if let Some(chat) = app.chats.get(app.selected_chat) {
-        format!(" {} ", chat.name)
+        let topic = app
+            .current_topic()
+            .map(|(_, title)| format!(" # {}", title))
+            .unwrap_or_default();
+        match chat.muted_until.filter(|_| chat.is_muted(unix_now())) {
+            Some(until) => format!(
+                " {}{}  {} ",
+                chat.name,
+                topic,
+                mute_label(until, &app.config)
+            ),
+            None => format!(" {}{} ", chat.name, topic),
+        }
     } else {
         " chats ".to_string()
     };
 
+    // How often the :search query shows up in what's loaded of this chat
+    let title = match &app.message_search_query {
+        Some(query) if !cache.matches.is_empty() && app.loading_status.is_none() => {
+            format!(
+                "{} \"{}\" {}{} ",
+                title,
+                query,
+                g.times,
+                cache.matches.len()
+            )
+        }
+        _ => title,
+    };
+
     // Apply scroll offset - bottom aligned
     let visible_height = area.height.saturating_sub(2) as usize;
-    let total_items = items.len();
+    let total_items = cache.items.len();
+
+    // Item range of the selected message, highlighted while the panel is focused
+    let selected_rows = cache
+        .rows
+        .len()
+        .checked_sub(app.selected_message + 1)
+        .map(|i| cache.rows[i].clone())
+        .filter(|_| is_focused);
+
+    // Scroll just enough to keep the selected message in view
+    let mut scroll_offset = app.scroll_offset.get();
+    if let Some(rows) = &selected_rows {
+        let end_index = total_items.saturating_sub(scroll_offset);
+        if rows.end > end_index {
+            scroll_offset = total_items - rows.end;
+        } else if rows.start < end_index.saturating_sub(visible_height) {
+            scroll_offset = total_items.saturating_sub(rows.start + visible_height);
+        }
+        app.scroll_offset.set(scroll_offset);
+    }
 
     // Calculate range based on inverted scroll_offset (0 = bottom)
-    let end_index = total_items.saturating_sub(app.scroll_offset);
+    let end_index = total_items.saturating_sub(scroll_offset);
     let start_index = end_index.saturating_sub(visible_height);
 
-    // Get the slice of messages
-    let mut visible_items: Vec<ListItem> = items
-        .into_iter()
-        .skip(start_index)
-        .take(end_index - start_index)
-        .collect();
+    // Get the slice of messages (only these are cloned out of the cache)
+    let mut visible_items: Vec<ListItem> = cache.items[start_index..end_index].to_vec();
+
+    // Visual mode: the whole selected range, the cursor end highlighted below as usual
+    let visual_rows = app
+        .visual_range()
+        .and_then(|(first, last)| Some(cache.rows.get(first)?.start..cache.rows.get(last)?.end));
+    if let Some(rows) = visual_rows {
+        let highlight = Style::default().bg(Color::Rgb(60, 45, 75));
+        for index in rows.start.max(start_index)..rows.end.min(end_index) {
+            let item = &mut visible_items[index - start_index];
+            *item = item.clone().style(highlight);
+        }
+    }
+    if let Some(rows) = selected_rows {
+        let highlight = Style::default().bg(Color::Rgb(40, 45, 60));
+        for index in rows.start.max(start_index)..rows.end.min(end_index) {
+            let item = &mut visible_items[index - start_index];
+            *item = item.clone().style(highlight);
+        }
+    }
+
+    // A chat that finished loading with nothing in it gets a hint instead of a blank view
+    let loaded_empty = total_items == 0
+        && app.loading_status.is_none()
+        && app
+            .current_chat_id()
+            .is_some_and(|id| app.pending_load != Some(id) && app.messages.contains_key(&id));
+    if loaded_empty && visible_height > 0 {
+        visible_items = vec![ListItem::new(
+            ratatui::text::Line::styled(
+                format!("No messages yet {} say hi!", g.dash),
+                Style::default()
+                    .fg(Color::Rgb(130, 130, 150))
+                    .add_modifier(Modifier::ITALIC),
+            )
+            .centered(),
+        )];
+        // Blank lines below it; the padding above then centers it
+        visible_items.extend(vec![ListItem::new(""); visible_height / 2]);
+    }
 
     // If fewer items than height, pad with empty lines to force bottom alignment
     if visible_items.len() < visible_height {
@@ -356,27 +1237,84 @@ fn draw_chats_panel(frame: &mut Frame, app: &App, area: Rect) {
         visible_items = padded_items;
     }
 
-    let list = List::new(visible_items).block(
-        Block::default()
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(border_color))
-            .border_type(ratatui::widgets::BorderType::Rounded)
-            .title(title),
-    );
+    let mut block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(border_color))
+        .border_set(border_set(&app.config))
+        .title(title);
+    if app.new_below > 0 {
+        let noun = if app.new_below == 1 {
+            "message"
+        } else {
+            "messages"
+        };
+        block = block.title_bottom(
+            ratatui::text::Line::styled(
+                format!(
+                    " {} {} new {} (G to jump) ",
+                    g.new_below, app.new_below, noun
+                ),
+                Style::default()
+                    .fg(Color::Rgb(255, 200, 100))
+                    .add_modifier(Modifier::BOLD),
+            )
+            .right_aligned(),
+        );
+    }
+
+    let list = List::new(visible_items).block(block);
 
     frame.render_widget(list, area);
 }
 
+/// Draw the pinned-message banner (one pinned message at a time)
+fn draw_pinned_banner(
+    frame: &mut Frame,
+    pinned: &[(i32, String)],
+    index: usize,
+    config: &Config,
+    area: Rect,
+) {
+    let pin = glyphs(config).pin;
+    let index = index % pinned.len();
+    let text = pinned[index].1.replace('\n', " ");
+    let text = if text.trim().is_empty() {
+        "(media)".to_string()
+    } else {
+        text
+    };
+
+    let title = if pinned.len() > 1 {
+        format!(" {} Pinned {}/{} (P next) ", pin, index + 1, pinned.len())
+    } else {
+        format!(" {} Pinned ", pin)
+    };
+
+    let banner = Paragraph::new(text)
+        .style(Style::default().fg(Color::Rgb(200, 200, 200)))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Rgb(255, 200, 100)))
+                .border_set(border_set(config))
+                .title(title),
+        );
+
+    frame.render_widget(banner, area);
+}
+
 /// Draw a centered welcome box with keybindings
-fn draw_welcome_box(frame: &mut Frame, area: Rect, border_color: Color) {
+fn draw_welcome_box(frame: &mut Frame, area: Rect, border_color: Color, config: &Config) {
     use ratatui::layout::Alignment;
     use ratatui::text::{Line, Span};
 
+    let border = border_set(config);
+
     // Outer block for the chat panel
     let outer_block = Block::default()
         .borders(Borders::ALL)
         .border_style(Style::default().fg(border_color))
-        .border_type(ratatui::widgets::BorderType::Rounded)
+        .border_set(border)
         .title(" Welcome ");
 
     let inner_area = outer_block.inner(area);
@@ -392,9 +1330,41 @@ fn draw_welcome_box(frame: &mut Frame, area: Rect, border_color: Color) {
         return;
     }
 
+    // Welcome content, generated from the keymap so it can't drift from input.rs
+    let content_width: u16 = 50;
+    let text_width = content_width.saturating_sub(4) as usize;
+    let mut welcome_lines = vec![
+        Line::from(""),
+        Line::from(Span::styled(
+            format!("{0} Welcome to Vimgram! {0}", glyphs(config).welcome),
+            Style::default()
+                .fg(Color::Rgb(100, 180, 255))
+                .add_modifier(Modifier::BOLD),
+        )),
+    ];
+    for section in KEYMAP {
+        let heading_color = if section.mode == "COMMAND MODE" {
+            Color::Rgb(100, 200, 100)
+        } else {
+            Color::Rgb(255, 180, 50)
+        };
+        welcome_lines.push(Line::from(""));
+        welcome_lines.push(Line::from(Span::styled(
+            section.mode,
+            Style::default()
+                .fg(heading_color)
+                .add_modifier(Modifier::BOLD),
+        )));
+        for line in wrap_bindings(section.bindings, text_width) {
+            welcome_lines.push(Line::from(Span::styled(
+                line,
+                Style::default().fg(Color::Rgb(180, 180, 180)),
+            )));
+        }
+    }
+
     // Calculate centered box dimensions - ensure minimum viable size
-    let content_width = 50;
-    let content_height = 18; // 15 lines + 2 for borders + 1 buffer
+    let content_height = welcome_lines.len() as u16 + 3; // lines + 2 for borders + 1 buffer
 
     let box_width = content_width.min(inner_area.width.saturating_sub(2));
     let box_height = content_height.min(inner_area.height.saturating_sub(2));
@@ -409,145 +1379,771 @@ fn draw_welcome_box(frame: &mut Frame, area: Rect, border_color: Color) {
 
     let welcome_area = Rect::new(box_x, box_y, box_width, box_height);
 
-    // Welcome content
-    let welcome_lines = vec![
-        Line::from(""),
-        Line::from(Span::styled(
-            "⚡ Welcome to Vimgram! ⚡",
-            Style::default()
-                .fg(Color::Rgb(100, 180, 255))
-                .add_modifier(Modifier::BOLD),
-        )),
-        Line::from(""),
-        Line::from(Span::styled(
-            "NORMAL MODE",
-            Style::default()
-                .fg(Color::Rgb(255, 180, 50))
-                .add_modifier(Modifier::BOLD),
-        )),
-        Line::from(Span::styled(
-            "j/k scroll  h/l panels  / search  : cmd",
-            Style::default().fg(Color::Rgb(180, 180, 180)),
-        )),
-        Line::from(Span::styled(
-            "i insert  q quit  D disconnect",
-            Style::default().fg(Color::Rgb(180, 180, 180)),
-        )),
-        Line::from(""),
-        Line::from(Span::styled(
-            "COMMAND MODE",
-            Style::default()
-                .fg(Color::Rgb(100, 200, 100))
-                .add_modifier(Modifier::BOLD),
-        )),
-        Line::from(Span::styled(
-            ":find @user  search any Telegram user",
-            Style::default().fg(Color::Rgb(180, 180, 180)),
-        )),
+    let welcome_block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Rgb(70, 130, 180)))
+        .border_set(border);
+
+    let paragraph = Paragraph::new(welcome_lines)
+        .block(welcome_block)
+        .alignment(Alignment::Center);
+
+    frame.render_widget(paragraph, welcome_area);
+}
+
+/// Draw the input box at the bottom
+fn draw_input_box(frame: &mut Frame, app: &App, area: Rect) {
+    let g = glyphs(&app.config);
+    let keys = match app.config.send_on {
+        SendKey::Enter => "Enter send, Esc cancel",
+        SendKey::CtrlEnter => "Ctrl+Enter send, Enter newline, Esc cancel",
+    };
+    let slow_mode = app
+        .slow_mode_remaining()
+        .map(|secs| format!("slow mode: {}s, ", secs))
+        .unwrap_or_default();
+    let insert_title = match app.reply_target() {
+        Some(target) => {
+            let name = target.display_name.as_deref().unwrap_or(&target.sender);
+            let quoted = app
+                .reply_quote
+                .as_ref()
+                .and_then(|range| target.text.get(range.clone()));
+            let preview: String = quoted
+                .unwrap_or(&target.text)
+                .lines()
+                .next()
+                .unwrap_or("")
+                .chars()
+                .take(30)
+                .collect();
+            format!(
+                " INSERT {} {}: {} ({}{}) ",
+                g.reply, name, preview, slow_mode, keys
+            )
+        }
+        None => format!(" INSERT ({}{}) ", slow_mode, keys),
+    };
+    let visual_title = match app.visual_range() {
+        Some((first, last)) => {
+            let count = last - first + 1;
+            let noun = if count == 1 { "message" } else { "messages" };
+            format!(
+                " VISUAL {} {} (j/k extend, y copy, Esc cancel) ",
+                count, noun
+            )
+        }
+        None => String::new(),
+    };
+    let (title, style) = match app.mode {
+        Mode::Visual => (
+            visual_title.as_str(),
+            Style::default().fg(Color::Rgb(200, 120, 220)),
+        ),
+        Mode::Insert => (
+            insert_title.as_str(),
+            Style::default().fg(Color::Rgb(70, 130, 180)),
+        ),
+        Mode::Search => (
+            " / search (↑↓ navigate, Enter select, Esc cancel) ",
+            Style::default().fg(Color::Rgb(255, 180, 50)),
+        ),
+        Mode::AccountPicker => (
+            " A switch accounts (↑↓ navigate, Enter select, x log out, Esc cancel) ",
+            Style::default().fg(Color::Rgb(150, 100, 255)),
+        ),
+        Mode::Command => (" COMMAND ", Style::default().fg(Color::Rgb(100, 200, 100))),
+        Mode::FindUser => (
+            " FIND USER ",
+            Style::default().fg(Color::Rgb(100, 200, 255)),
+        ),
+        Mode::AICommand => (
+            " AI COMMAND (Enter submit, Esc cancel) ",
+            Style::default().fg(Color::Rgb(255, 100, 255)),
+        ),
+        Mode::Code => (
+            " CODE ASSISTANT (Ctrl+j/k scroll, Esc exit) ",
+            Style::default().fg(Color::Rgb(100, 255, 200)),
+        ),
+        Mode::WhoAmI => (
+            " WHOAMI (Esc close) ",
+            Style::default().fg(Color::Rgb(100, 200, 255)),
+        ),
+        Mode::Stats => (
+            " STATS (Esc close) ",
+            Style::default().fg(Color::Rgb(100, 200, 255)),
+        ),
+        Mode::Confirm => (
+            " CONFIRM (y yes, n/Esc cancel) ",
+            Style::default().fg(Color::Rgb(255, 100, 100)),
+        ),
+        Mode::LinkPicker => (
+            " OPEN LINK (↑↓ navigate, Enter open, Esc cancel) ",
+            Style::default().fg(Color::Rgb(100, 160, 255)),
+        ),
+        Mode::TopicPicker => (
+            " TOPICS (↑↓ navigate, Enter show, Esc cancel) ",
+            Style::default().fg(Color::Rgb(100, 200, 180)),
+        ),
+        Mode::StickerPicker => (
+            " STICKERS (↑↓ navigate, Enter send, Esc cancel) ",
+            Style::default().fg(Color::Rgb(255, 170, 120)),
+        ),
+        Mode::NumberJump => (
+            " JUMP (type the chat's number, Enter open, Esc cancel) ",
+            Style::default().fg(Color::Rgb(255, 200, 80)),
+        ),
+        Mode::Quote => (
+            " QUOTE (h/l last word, H/L first word, Enter reply, Esc back) ",
+            Style::default().fg(Color::Rgb(200, 120, 220)),
+        ),
+        Mode::MessageView => (
+            " MESSAGE (j/k scroll, Esc close) ",
+            Style::default().fg(Color::Rgb(200, 200, 200)),
+        ),
+        Mode::PinList => (
+            " PINNED (↑↓ navigate, Enter jump, Esc cancel) ",
+            Style::default().fg(Color::Rgb(255, 200, 100)),
+        ),
+        Mode::Scratchpad => (
+            " SCRATCHPAD (Esc save & close) ",
+            Style::default().fg(Color::Rgb(230, 200, 120)),
+        ),
+        Mode::CommandPalette => (
+            " PALETTE (type to filter, ↑↓ navigate, Enter run, Esc cancel) ",
+            Style::default().fg(Color::Rgb(200, 150, 255)),
+        ),
+        Mode::Normal => (
+            " type to send ",
+            Style::default().fg(Color::Rgb(80, 80, 90)),
+        ),
+    };
+    // Mode hints are written with the Unicode arrows
+    let title = title.replace(UNICODE.arrows, g.arrows);
+
+    // Content to display in input box
+    let content = match app.mode {
+        Mode::Command => format!(":{}", app.command_input),
+        Mode::AICommand => format!(":ai {}", app.ai_input),
+        Mode::Code => format!("> {}", app.code_input),
+        _ => app.input.clone(),
+    };
+
+    // Command feedback takes the empty input line in normal mode (like vim's message area)
+    let (content, content_style) = match &app.status_message {
+        Some(status) if app.mode == Mode::Normal && app.input.is_empty() => (
+            status.clone(),
+            Style::default().fg(Color::Rgb(255, 200, 100)),
+        ),
+        _ => (content, Style::default().fg(Color::White)),
+    };
+
+    // Keep the last lines of a long multi-line message in view
+    let hidden_lines = content
+        .split('\n')
+        .count()
+        .saturating_sub(area.height.saturating_sub(2) as usize) as u16;
+    let input = Paragraph::new(content.as_str())
+        .style(content_style)
+        .scroll((hidden_lines, 0))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(style)
+                .border_set(border_set(&app.config))
+                .title(title),
+        );
+
+    frame.render_widget(input, area);
+
+    // Show cursor in insert mode or command mode
+    let cursor = match app.mode {
+        // Earlier lines scroll out once the box is full
+        Mode::Insert => Some(input_cursor(&app.input, area.height.saturating_sub(3))),
+        Mode::Command => Some(input_cursor(&format!(":{}", app.command_input), 0)),
+        _ => None,
+    };
+    if let Some((col, row)) = cursor {
+        // Lines aren't wrapped: past the right border the cursor waits at the edge
+        let col = col.min(area.width.saturating_sub(3));
+        frame.set_cursor_position((area.x + col + 1, area.y + row + 1));
+    }
+}
+
+/// Cursor (column, row) at the end of `text` inside the input box border.
+/// Columns are terminal cells, so emoji and CJK characters count as two
+fn input_cursor(text: &str, max_row: u16) -> (u16, u16) {
+    let lines: Vec<&str> = text.split('\n').collect();
+    let row = (lines.len() - 1).min(max_row as usize) as u16;
+    let col = lines[lines.len() - 1].width() as u16;
+    (col, row)
+}
+
+/// Draw the account picker overlay
+fn draw_account_picker(frame: &mut Frame, app: &App, area: Rect) {
+    use ratatui::widgets::Clear;
+
+    // Calculate overlay dimensions
+    let box_width = 40.min(area.width.saturating_sub(10));
+    let box_height = (app.account_names.len() as u16 + 4).min(area.height.saturating_sub(6));
+
+    let box_x = (area.width.saturating_sub(box_width)) / 2;
+    let box_y = (area.height.saturating_sub(box_height)) / 2;
+
+    let overlay_area = Rect::new(box_x, box_y, box_width, box_height);
+
+    // Clear the area behind the overlay
+    frame.render_widget(Clear, overlay_area);
+
+    // Build account list items
+    let mut items: Vec<ListItem> = app
+        .account_names
+        .iter()
+        .enumerate()
+        .map(|(i, (id, name))| {
+            let is_selected = i == app.account_picker_selected;
+            let is_current = *id == app.current_account_id;
+
+            let prefix = if is_selected { "> " } else { "  " };
+            let suffix = if is_current {
+                format!(" {}", glyphs(&app.config).selected)
+            } else {
+                String::new()
+            };
+
+            // Each account in its accent, so the picker matches the frame after switching
+            let (r, g, b) = app.account_accent(id);
+            let mut style = Style::default().fg(Color::Rgb(r, g, b));
+            if is_selected {
+                style = style.add_modifier(Modifier::BOLD);
+            }
+
+            ListItem::new(format!("{}{}{}", prefix, name, suffix)).style(style)
+        })
+        .collect();
+
+    // Add "+ Add Account" option
+    let add_selected = app.account_picker_selected == app.account_names.len();
+    let add_style = if add_selected {
+        Style::default()
+            .fg(Color::Rgb(100, 200, 100))
+            .add_modifier(Modifier::BOLD)
+    } else {
+        Style::default().fg(Color::Rgb(100, 180, 100))
+    };
+    let add_prefix = if add_selected { "> " } else { "  " };
+    items.push(ListItem::new(format!("{}+ Add Account", add_prefix)).style(add_style));
+
+    let list = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Rgb(150, 100, 255)))
+            .border_set(border_set(&app.config))
+            .title(" Switch Account "),
+    );
+
+    frame.render_widget(list, overlay_area);
+}
+
+/// Draw the link picker overlay (message with several links)
+fn draw_link_picker(frame: &mut Frame, app: &App, area: Rect) {
+    use ratatui::widgets::Clear;
+
+    // Calculate overlay dimensions
+    let box_width = 70.min(area.width.saturating_sub(10));
+    let box_height = (app.link_choices.len() as u16 + 2).min(area.height.saturating_sub(6));
+
+    let box_x = (area.width.saturating_sub(box_width)) / 2;
+    let box_y = (area.height.saturating_sub(box_height)) / 2;
+
+    let overlay_area = Rect::new(box_x, box_y, box_width, box_height);
+
+    // Clear the area behind the overlay
+    frame.render_widget(Clear, overlay_area);
+
+    let items: Vec<ListItem> = app
+        .link_choices
+        .iter()
+        .enumerate()
+        .map(|(i, url)| {
+            let is_selected = i == app.link_picker_selected;
+            let prefix = if is_selected { "> " } else { "  " };
+
+            let style = if is_selected {
+                Style::default()
+                    .fg(Color::Rgb(100, 160, 255))
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::Rgb(180, 180, 180))
+            };
+
+            ListItem::new(format!("{}{}", prefix, url)).style(style)
+        })
+        .collect();
+
+    let list = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Rgb(100, 160, 255)))
+            .border_set(border_set(&app.config))
+            .title(" Open Link "),
+    );
+
+    frame.render_widget(list, overlay_area);
+}
+
+/// Draw the open forum's topics, "All topics" first (T)
+fn draw_topic_picker(frame: &mut Frame, app: &App, area: Rect) {
+    use ratatui::widgets::Clear;
+
+    let topics = app
+        .chats
+        .get(app.selected_chat)
+        .map(|c| c.topics.as_slice())
+        .unwrap_or_default();
+    let box_width = 50.min(area.width.saturating_sub(10));
+    let box_height = (topics.len() as u16 + 3).min(area.height.saturating_sub(6));
+
+    let box_x = (area.width.saturating_sub(box_width)) / 2;
+    let box_y = (area.height.saturating_sub(box_height)) / 2;
+
+    let overlay_area = Rect::new(box_x, box_y, box_width, box_height);
+
+    frame.render_widget(Clear, overlay_area);
+
+    let text_width = (box_width as usize).saturating_sub(4);
+    let names = std::iter::once("All topics").chain(topics.iter().map(|(_, t)| t.as_str()));
+    let items: Vec<ListItem> = names
+        .enumerate()
+        .map(|(i, name)| {
+            let is_selected = i == app.topics_selected;
+            let prefix = if is_selected { "> " } else { "  " };
+
+            let style = if is_selected {
+                Style::default()
+                    .fg(Color::Rgb(100, 200, 180))
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::Rgb(180, 180, 180))
+            };
+
+            let name = truncate_to_width(name, text_width, glyphs(&app.config).ellipsis);
+            ListItem::new(format!("{}{}", prefix, name)).style(style)
+        })
+        .collect();
+
+    // Keep the highlighted topic in view when there are more than fit
+    let mut state = ratatui::widgets::ListState::default().with_selected(Some(app.topics_selected));
+    let list = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Rgb(100, 200, 180)))
+            .border_set(border_set(&app.config))
+            .title(" Topics "),
+    );
+
+    frame.render_stateful_widget(list, overlay_area, &mut state);
+}
+
+/// Draw the saved stickers and GIFs matching a :sticker query
+fn draw_sticker_picker(frame: &mut Frame, app: &App, area: Rect) {
+    use ratatui::widgets::Clear;
+
+    let choices = &app.sticker_choices;
+    let box_width = 50.min(area.width.saturating_sub(10));
+    let box_height = (choices.len() as u16 + 2).min(area.height.saturating_sub(6));
+
+    let box_x = (area.width.saturating_sub(box_width)) / 2;
+    let box_y = (area.height.saturating_sub(box_height)) / 2;
+
+    let overlay_area = Rect::new(box_x, box_y, box_width, box_height);
+
+    frame.render_widget(Clear, overlay_area);
+
+    let text_width = (box_width as usize).saturating_sub(4);
+    let items: Vec<ListItem> = choices
+        .iter()
+        .enumerate()
+        .map(|(i, (_, label))| {
+            let is_selected = i == app.sticker_selected;
+            let prefix = if is_selected { "> " } else { "  " };
+
+            let style = if is_selected {
+                Style::default()
+                    .fg(Color::Rgb(255, 170, 120))
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::Rgb(180, 180, 180))
+            };
+
+            ListItem::new(format!(
+                "{}{}",
+                prefix,
+                truncate_to_width(label, text_width, glyphs(&app.config).ellipsis)
+            ))
+            .style(style)
+        })
+        .collect();
+
+    // Keep the highlighted sticker in view when there are more than fit
+    let mut state =
+        ratatui::widgets::ListState::default().with_selected(Some(app.sticker_selected));
+    let list = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Rgb(255, 170, 120)))
+            .border_set(border_set(&app.config))
+            .title(format!(" Stickers & GIFs ({}) ", choices.len())),
+    );
+
+    frame.render_stateful_widget(list, overlay_area, &mut state);
+}
+
+/// Draw the message picked with K in full, long words broken anywhere so
+/// nothing is cut off
+fn draw_message_view(frame: &mut Frame, app: &App, area: Rect) {
+    use ratatui::widgets::Clear;
+
+    let Some((sender, text)) = &app.message_view else {
+        return;
+    };
+    let box_width = 80.min(area.width.saturating_sub(10));
+    let box_height = 20.min(area.height.saturating_sub(6));
+
+    let box_x = (area.width.saturating_sub(box_width)) / 2;
+    let box_y = (area.height.saturating_sub(box_height)) / 2;
+
+    let overlay_area = Rect::new(box_x, box_y, box_width, box_height);
+
+    frame.render_widget(Clear, overlay_area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Rgb(200, 200, 200)))
+        .border_set(border_set(&app.config))
+        .title(format!(" {} ", sender));
+
+    let paragraph = Paragraph::new(text.as_str())
+        .style(Style::default().fg(Color::Rgb(220, 220, 220)))
+        .block(block)
+        .wrap(ratatui::widgets::Wrap { trim: false });
+
+    // The count includes the borders, as does the box height
+    let lines = paragraph.line_count(box_width.saturating_sub(2));
+    let max_scroll = u16::try_from(lines.saturating_sub(box_height as usize)).unwrap_or(u16::MAX);
+    app.message_view_max_scroll.set(max_scroll);
+    let paragraph = paragraph.scroll((app.message_view_scroll.min(max_scroll), 0));
+
+    frame.render_widget(paragraph, overlay_area);
+}
+
+/// Draw the message being quoted with its picked words highlighted
+fn draw_quote_picker(frame: &mut Frame, app: &App, area: Rect) {
+    use ratatui::text::{Line, Span};
+    use ratatui::widgets::Clear;
+
+    let Some((message, range)) = app.quote_selection() else {
+        return;
+    };
+    let box_width = 80.min(area.width.saturating_sub(10));
+    let box_height = 20.min(area.height.saturating_sub(6));
+
+    let box_x = (area.width.saturating_sub(box_width)) / 2;
+    let box_y = (area.height.saturating_sub(box_height)) / 2;
+
+    let overlay_area = Rect::new(box_x, box_y, box_width, box_height);
+
+    frame.render_widget(Clear, overlay_area);
+
+    let sender = message.display_name.as_deref().unwrap_or(&message.sender);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Rgb(200, 120, 220)))
+        .border_set(border_set(&app.config))
+        .title(format!(" Quote {} ", sender));
+
+    let normal = Style::default().fg(Color::Rgb(220, 220, 220));
+    let picked = Style::default()
+        .fg(Color::Rgb(20, 20, 30))
+        .bg(Color::Rgb(200, 120, 220));
+    let mut lines = Vec::new();
+    let mut line_start = 0;
+    for line in message.text.split('\n') {
+        let line_end = line_start + line.len();
+        // Cut points of the picked range that fall inside this line
+        let from = range.start.clamp(line_start, line_end) - line_start;
+        let to = range.end.clamp(line_start, line_end) - line_start;
+        lines.push(Line::from(vec![
+            Span::styled(&line[..from], normal),
+            Span::styled(&line[from..to], picked),
+            Span::styled(&line[to..], normal),
+        ]));
+        line_start = line_end + 1;
+    }
+
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .wrap(ratatui::widgets::Wrap { trim: false });
+
+    frame.render_widget(paragraph, overlay_area);
+}
+
+/// Draw the open chat's pinned messages (:pins), one line each
+fn draw_pin_list(frame: &mut Frame, app: &App, area: Rect) {
+    use ratatui::widgets::Clear;
+
+    let pins = app.current_pins();
+    let box_width = 70.min(area.width.saturating_sub(10));
+    let box_height = (pins.len() as u16 + 2).min(area.height.saturating_sub(6));
+
+    let box_x = (area.width.saturating_sub(box_width)) / 2;
+    let box_y = (area.height.saturating_sub(box_height)) / 2;
+
+    let overlay_area = Rect::new(box_x, box_y, box_width, box_height);
+
+    frame.render_widget(Clear, overlay_area);
+
+    let text_width = (box_width as usize).saturating_sub(4);
+    let items: Vec<ListItem> = pins
+        .iter()
+        .enumerate()
+        .map(|(i, (_, text))| {
+            let is_selected = i == app.pins_selected;
+            let prefix = if is_selected { "> " } else { "  " };
+
+            let style = if is_selected {
+                Style::default()
+                    .fg(Color::Rgb(255, 200, 100))
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::Rgb(180, 180, 180))
+            };
+
+            let first_line = text.lines().next().unwrap_or("");
+            let text = if first_line.is_empty() {
+                "(media)"
+            } else {
+                first_line
+            };
+            let text = truncate_to_width(text, text_width, glyphs(&app.config).ellipsis);
+            ListItem::new(format!("{}{}", prefix, text)).style(style)
+        })
+        .collect();
+
+    let list = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Rgb(255, 200, 100)))
+            .border_set(border_set(&app.config))
+            .title(format!(" Pinned Messages ({}) ", pins.len())),
+    );
+
+    frame.render_widget(list, overlay_area);
+}
+
+/// Draw the command palette: the filter on top, matching actions below
+fn draw_palette(frame: &mut Frame, app: &App, area: Rect) {
+    use ratatui::text::{Line, Span};
+    use ratatui::widgets::Clear;
+
+    let matches = app.palette_matches();
+    let accent = Color::Rgb(200, 150, 255);
+
+    // Calculate overlay dimensions (filter line, separator, then the matches)
+    let box_width = 60.min(area.width.saturating_sub(10));
+    let box_height = (matches.len().max(1) as u16 + 4).min(area.height.saturating_sub(4));
+
+    let box_x = (area.width.saturating_sub(box_width)) / 2;
+    let box_y = (area.height.saturating_sub(box_height)) / 3;
+
+    let overlay_area = Rect::new(box_x, box_y, box_width, box_height);
+
+    // Clear the area behind the overlay
+    frame.render_widget(Clear, overlay_area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(accent))
+        .border_set(border_set(&app.config))
+        .title(" Command Palette ");
+    let inner = block.inner(overlay_area);
+    frame.render_widget(block, overlay_area);
+    if inner.height < 3 {
+        return;
+    }
+
+    let filter = Line::from(vec![
+        Span::styled("> ", Style::default().fg(accent)),
+        Span::raw(app.palette_input.clone()),
+    ]);
+    frame.render_widget(Paragraph::new(filter), Rect { height: 1, ..inner });
+    let separator = glyphs(&app.config).rule.repeat(inner.width as usize);
+    frame.render_widget(
+        Paragraph::new(separator).style(Style::default().fg(Color::Rgb(80, 80, 90))),
+        Rect {
+            y: inner.y + 1,
+            height: 1,
+            ..inner
+        },
+    );
+    let cursor_x = inner.x + 2 + app.palette_input.width() as u16;
+    frame.set_cursor_position((cursor_x.min(inner.right().saturating_sub(1)), inner.y));
+
+    let list_area = Rect {
+        y: inner.y + 2,
+        height: inner.height - 2,
+        ..inner
+    };
+    if matches.is_empty() {
+        frame.render_widget(
+            Paragraph::new("  No matching actions")
+                .style(Style::default().fg(Color::Rgb(120, 120, 130))),
+            list_area,
+        );
+        return;
+    }
+
+    // Keep the selection in view when the list is taller than the box
+    let visible = list_area.height as usize;
+    let first = (app.palette_selected + 1).saturating_sub(visible);
+    let items: Vec<ListItem> = matches
+        .iter()
+        .enumerate()
+        .skip(first)
+        .take(visible)
+        .map(|(i, entry)| {
+            let is_selected = i == app.palette_selected;
+            let prefix = if is_selected { "> " } else { "  " };
+            let style = if is_selected {
+                Style::default().fg(accent).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::Rgb(180, 180, 180))
+            };
+
+            // Name on the left, its key or command right-aligned
+            let name = format!("{}{}", prefix, entry.name);
+            let gap = (list_area.width as usize)
+                .saturating_sub(name.width() + entry.keys.width() + 1)
+                .max(1);
+            ListItem::new(Line::from(vec![
+                Span::styled(name, style),
+                Span::raw(" ".repeat(gap)),
+                Span::styled(entry.keys, Style::default().fg(Color::Rgb(110, 110, 125))),
+            ]))
+        })
+        .collect();
+
+    frame.render_widget(List::new(items), list_area);
+}
+
+/// Draw the lock screen in place of everything else
+fn draw_lock_screen(frame: &mut Frame, app: &App, area: Rect) {
+    use ratatui::text::{Line, Span};
+
+    let g = glyphs(&app.config);
+    let box_width = 44.min(area.width);
+    let box_height = 7.min(area.height);
+    let box_x = (area.width.saturating_sub(box_width)) / 2;
+    let box_y = (area.height.saturating_sub(box_height)) / 2;
+    let overlay_area = Rect::new(box_x, box_y, box_width, box_height);
+
+    let hint = Style::default().fg(Color::Rgb(120, 120, 130));
+    let mut lines = vec![
         Line::from(""),
         Line::from(Span::styled(
-            "SEARCH MODE",
+            format!("{} Vimgram is locked", g.lock),
             Style::default()
-                .fg(Color::Rgb(255, 180, 50))
+                .fg(Color::Rgb(200, 200, 200))
                 .add_modifier(Modifier::BOLD),
         )),
-        Line::from(Span::styled(
-            "type filter  arrows nav  Enter jump",
-            Style::default().fg(Color::Rgb(180, 180, 180)),
-        )),
+    ];
+    if app.lock_needs_passphrase() {
+        lines.push(Line::from(vec![
+            Span::styled("Passphrase: ", hint),
+            Span::raw(g.bullet.repeat(app.lock_input.chars().count())),
+        ]));
+        if let Some(status) = &app.status_message {
+            lines.push(Line::from(Span::styled(
+                status.as_str(),
+                Style::default().fg(Color::Rgb(255, 100, 100)),
+            )));
+        }
+    } else {
+        lines.push(Line::from(Span::styled("Press any key to unlock", hint)));
+    }
+
+    let paragraph = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Rgb(120, 120, 130)))
+                .border_set(border_set(&app.config))
+                .title(" Locked "),
+        )
+        .alignment(ratatui::layout::Alignment::Center);
+
+    frame.render_widget(paragraph, overlay_area);
+}
+
+/// Draw the y/n confirmation prompt
+fn draw_confirm(frame: &mut Frame, app: &App, area: Rect) {
+    use ratatui::text::{Line, Span};
+    use ratatui::widgets::Clear;
+
+    let prompt = app
+        .pending_confirm
+        .as_ref()
+        .map(|action| action.prompt())
+        .unwrap_or_default();
+
+    // Calculate overlay dimensions
+    let box_width = (prompt.chars().count() as u16 + 8)
+        .max(30)
+        .min(area.width.saturating_sub(10));
+    let box_height = 6.min(area.height.saturating_sub(6));
+
+    let box_x = (area.width.saturating_sub(box_width)) / 2;
+    let box_y = (area.height.saturating_sub(box_height)) / 2;
+
+    let overlay_area = Rect::new(box_x, box_y, box_width, box_height);
+
+    // Clear the area behind the overlay
+    frame.render_widget(Clear, overlay_area);
+
+    let lines = vec![
         Line::from(""),
         Line::from(Span::styled(
-            "INSERT MODE",
+            prompt,
             Style::default()
-                .fg(Color::Rgb(255, 180, 50))
+                .fg(Color::Rgb(200, 200, 200))
                 .add_modifier(Modifier::BOLD),
         )),
         Line::from(Span::styled(
-            "type msg  Enter send  Esc cancel",
-            Style::default().fg(Color::Rgb(180, 180, 180)),
+            "y confirm  n/Esc cancel",
+            Style::default().fg(Color::Rgb(120, 120, 130)),
         )),
     ];
 
-    let welcome_block = Block::default()
-        .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Rgb(70, 130, 180)))
-        .border_type(ratatui::widgets::BorderType::Rounded);
-
-    let paragraph = Paragraph::new(welcome_lines)
-        .block(welcome_block)
-        .alignment(Alignment::Center);
-
-    frame.render_widget(paragraph, welcome_area);
-}
-
-/// Draw the input box at the bottom
-fn draw_input_box(frame: &mut Frame, app: &App, area: Rect) {
-    let (title, style) = match app.mode {
-        Mode::Insert => (" INSERT ", Style::default().fg(Color::Rgb(70, 130, 180))),
-        Mode::Search => (
-            " / search (↑↓ navigate, Enter select, Esc cancel) ",
-            Style::default().fg(Color::Rgb(255, 180, 50)),
-        ),
-        Mode::AccountPicker => (
-            " A switch accounts (↑↓ navigate, Enter select, Esc cancel) ",
-            Style::default().fg(Color::Rgb(150, 100, 255)),
-        ),
-        Mode::Command => (" COMMAND ", Style::default().fg(Color::Rgb(100, 200, 100))),
-        Mode::FindUser => (
-            " FIND USER ",
-            Style::default().fg(Color::Rgb(100, 200, 255)),
-        ),
-        Mode::AICommand => (
-            " AI COMMAND (Enter submit, Esc cancel) ",
-            Style::default().fg(Color::Rgb(255, 100, 255)),
-        ),
-        Mode::Code => (
-            " CODE ASSISTANT (Ctrl+j/k scroll, Esc exit) ",
-            Style::default().fg(Color::Rgb(100, 255, 200)),
-        ),
-        Mode::Normal => (
-            " type to send ",
-            Style::default().fg(Color::Rgb(80, 80, 90)),
-        ),
-    };
-
-    // Content to display in input box
-    let content = match app.mode {
-        Mode::Command => format!(":{}", app.command_input),
-        Mode::AICommand => format!(":ai {}", app.ai_input),
-        Mode::Code => format!("> {}", app.code_input),
-        _ => app.input.clone(),
-    };
-
-    let input = Paragraph::new(content.as_str())
-        .style(Style::default().fg(Color::White))
+    let paragraph = Paragraph::new(lines)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .border_style(style)
-                .border_type(ratatui::widgets::BorderType::Rounded)
-                .title(title),
-        );
-
-    frame.render_widget(input, area);
+                .border_style(Style::default().fg(Color::Rgb(255, 100, 100)))
+                .border_set(border_set(&app.config))
+                .title(" Confirm "),
+        )
+        .alignment(ratatui::layout::Alignment::Center);
 
-    // Show cursor in insert mode or command mode
-    if app.mode == Mode::Insert {
-        frame.set_cursor_position((area.x + app.input.len() as u16 + 1, area.y + 1));
-    } else if app.mode == Mode::Command {
-        // +2 for ": " prefix
-        frame.set_cursor_position((area.x + app.command_input.len() as u16 + 2, area.y + 1));
-    }
+    frame.render_widget(paragraph, overlay_area);
 }
 
-/// Draw the account picker overlay
-fn draw_account_picker(frame: &mut Frame, app: &App, area: Rect) {
-    use ratatui::text::{Line, Span};
-    use ratatui::widgets::Clear;
+/// Draw the scratchpad overlay: local notes, kept scrolled to the end being typed
+fn draw_scratchpad(frame: &mut Frame, app: &App, area: Rect) {
+    use ratatui::widgets::{Clear, Wrap};
 
     // Calculate overlay dimensions
-    let box_width = 40.min(area.width.saturating_sub(10));
-    let box_height = (app.account_names.len() as u16 + 4).min(area.height.saturating_sub(6));
+    let box_width = (area.width * 7 / 10).min(area.width.saturating_sub(4));
+    let box_height = (area.height * 6 / 10).min(area.height.saturating_sub(4));
 
     let box_x = (area.width.saturating_sub(box_width)) / 2;
     let box_y = (area.height.saturating_sub(box_height)) / 2;
@@ -557,53 +2153,36 @@ fn draw_account_picker(frame: &mut Frame, app: &App, area: Rect) {
     // Clear the area behind the overlay
     frame.render_widget(Clear, overlay_area);
 
-    // Build account list items
-    let mut items: Vec<ListItem> = app
-        .account_names
-        .iter()
-        .enumerate()
-        .map(|(i, (id, name))| {
-            let is_selected = i == app.account_picker_selected;
-            let is_current = *id == app.current_account_id;
-
-            let prefix = if is_selected { "> " } else { "  " };
-            let suffix = if is_current { " ✓" } else { "" };
-
-            let style = if is_selected {
-                Style::default()
-                    .fg(Color::Rgb(150, 100, 255))
-                    .add_modifier(Modifier::BOLD)
-            } else if is_current {
-                Style::default().fg(Color::Rgb(100, 200, 100))
-            } else {
-                Style::default().fg(Color::Rgb(180, 180, 180))
-            };
-
-            ListItem::new(format!("{}{}{}", prefix, name, suffix)).style(style)
-        })
-        .collect();
+    // Cursor at the end, where typing goes
+    let g = glyphs(&app.config);
+    let content = format!("{}{}", app.scratchpad, g.cursor);
 
-    // Add "+ Add Account" option
-    let add_selected = app.account_picker_selected == app.account_names.len();
-    let add_style = if add_selected {
-        Style::default()
-            .fg(Color::Rgb(100, 200, 100))
-            .add_modifier(Modifier::BOLD)
-    } else {
-        Style::default().fg(Color::Rgb(100, 180, 100))
-    };
-    let add_prefix = if add_selected { "> " } else { "  " };
-    items.push(ListItem::new(format!("{}+ Add Account", add_prefix)).style(add_style));
+    // Estimate the wrapped height to keep the last line in view
+    let inner_width = box_width.saturating_sub(2).max(1) as usize;
+    let visible = box_height.saturating_sub(2) as usize;
+    let total: usize = content
+        .split('\n')
+        .map(|line| line.width().div_ceil(inner_width).max(1))
+        .sum();
+    let scroll = total.saturating_sub(visible) as u16;
 
-    let list = List::new(items).block(
-        Block::default()
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(Color::Rgb(150, 100, 255)))
-            .border_type(ratatui::widgets::BorderType::Rounded)
-            .title(" Switch Account "),
-    );
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Rgb(230, 200, 120)))
+        .border_set(border_set(&app.config))
+        .title(" Scratchpad ")
+        .title_bottom(format!(
+            " local only, never sent {} Esc save & close ",
+            g.dot
+        ));
+
+    let paragraph = Paragraph::new(content)
+        .style(Style::default().fg(Color::Rgb(220, 220, 220)))
+        .wrap(Wrap { trim: false })
+        .scroll((scroll, 0))
+        .block(block);
 
-    frame.render_widget(list, overlay_area);
+    frame.render_widget(paragraph, overlay_area);
 }
 
 /// Draw the find user overlay
@@ -625,18 +2204,19 @@ fn draw_find_user(frame: &mut Frame, app: &App, area: Rect) {
     frame.render_widget(Clear, overlay_area);
 
     // Build content based on find result
+    let g = glyphs(&app.config);
     let lines: Vec<Line> = match &app.find_result {
         Some(FindResult::Searching) => vec![
             Line::from(""),
             Line::from(Span::styled(
-                format!("🔍 Searching for @{}...", app.find_input),
+                format!("{} Searching for @{}...", g.searching, app.find_input),
                 Style::default().fg(Color::Rgb(100, 200, 255)),
             )),
         ],
         Some(FindResult::Found { name, .. }) => vec![
             Line::from(""),
             Line::from(Span::styled(
-                format!("✅ Found: {}", name),
+                format!("{} Found: {}", g.found, name),
                 Style::default()
                     .fg(Color::Rgb(100, 200, 100))
                     .add_modifier(Modifier::BOLD),
@@ -649,7 +2229,7 @@ fn draw_find_user(frame: &mut Frame, app: &App, area: Rect) {
         Some(FindResult::NotFound(username)) => vec![
             Line::from(""),
             Line::from(Span::styled(
-                format!("❌ User @{} not found", username),
+                format!("{} User @{} not found", g.not_found, username),
                 Style::default().fg(Color::Rgb(255, 100, 100)),
             )),
             Line::from(Span::styled(
@@ -660,7 +2240,7 @@ fn draw_find_user(frame: &mut Frame, app: &App, area: Rect) {
         Some(FindResult::Error(msg)) => vec![
             Line::from(""),
             Line::from(Span::styled(
-                format!("⚠️ Error: {}", msg),
+                format!("{} Error: {}", g.warning, msg),
                 Style::default().fg(Color::Rgb(255, 180, 50)),
             )),
             Line::from(Span::styled(
@@ -677,7 +2257,7 @@ fn draw_find_user(frame: &mut Frame, app: &App, area: Rect) {
     let block = Block::default()
         .borders(Borders::ALL)
         .border_style(Style::default().fg(Color::Rgb(100, 200, 255)))
-        .border_type(ratatui::widgets::BorderType::Rounded)
+        .border_set(border_set(&app.config))
         .title(format!(" :find @{} ", app.find_input));
 
     let paragraph = Paragraph::new(lines)
@@ -687,11 +2267,153 @@ fn draw_find_user(frame: &mut Frame, app: &App, area: Rect) {
     frame.render_widget(paragraph, overlay_area);
 }
 
+/// Draw the :whoami account details overlay
+fn draw_whoami(frame: &mut Frame, app: &App, area: Rect) {
+    use ratatui::text::{Line, Span};
+    use ratatui::widgets::Clear;
+
+    // Calculate overlay dimensions
+    let box_width = 50.min(area.width.saturating_sub(10));
+    let box_height = 10.min(area.height.saturating_sub(6));
+
+    let box_x = (area.width.saturating_sub(box_width)) / 2;
+    let box_y = (area.height.saturating_sub(box_height)) / 2;
+
+    let overlay_area = Rect::new(box_x, box_y, box_width, box_height);
+
+    // Clear the area behind the overlay
+    frame.render_widget(Clear, overlay_area);
+
+    let label_style = Style::default().fg(Color::Rgb(120, 120, 130));
+    let value_style = Style::default().fg(Color::Rgb(200, 200, 200));
+
+    let account_id = if app.current_account_id.is_empty() {
+        "legacy session".to_string()
+    } else {
+        app.current_account_id.clone()
+    };
+
+    let lines: Vec<Line> = match &app.me {
+        Some(me) => vec![
+            Line::from(""),
+            Line::from(vec![
+                Span::styled("Name:     ", label_style),
+                Span::styled(me.name.clone(), value_style.add_modifier(Modifier::BOLD)),
+            ]),
+            Line::from(vec![
+                Span::styled("Username: ", label_style),
+                Span::styled(
+                    me.username
+                        .as_ref()
+                        .map(|u| format!("@{}", u))
+                        .unwrap_or_else(|| "(none)".to_string()),
+                    value_style,
+                ),
+            ]),
+            Line::from(vec![
+                Span::styled("Phone:    ", label_style),
+                Span::styled(
+                    me.phone
+                        .as_ref()
+                        .map(|p| format!("+{}", p.trim_start_matches('+')))
+                        .unwrap_or_else(|| "(hidden)".to_string()),
+                    value_style,
+                ),
+            ]),
+            Line::from(vec![
+                Span::styled("User ID:  ", label_style),
+                Span::styled(me.id.to_string(), value_style),
+            ]),
+            Line::from(vec![
+                Span::styled("Account:  ", label_style),
+                Span::styled(account_id, Style::default().fg(Color::Rgb(100, 200, 100))),
+            ]),
+        ],
+        None => vec![
+            Line::from(""),
+            Line::from(Span::styled(
+                "Account details unavailable",
+                Style::default().fg(Color::Rgb(255, 180, 50)),
+            )),
+        ],
+    };
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Rgb(100, 200, 255)))
+        .border_set(border_set(&app.config))
+        .title(" :whoami ");
+
+    let paragraph = Paragraph::new(lines).block(block);
+
+    frame.render_widget(paragraph, overlay_area);
+}
+
+/// Uptime as "42s", "5m 07s" or "3h 02m"
+fn format_uptime(uptime: Duration) -> String {
+    let secs = uptime.as_secs();
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m {:02}s", secs / 60, secs % 60),
+        _ => format!("{}h {:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+/// Draw the :stats session metrics overlay
+fn draw_stats(frame: &mut Frame, app: &App, area: Rect) {
+    use ratatui::text::{Line, Span};
+    use ratatui::widgets::Clear;
+
+    let box_width = 50.min(area.width.saturating_sub(10));
+    let box_height = 10.min(area.height.saturating_sub(6));
+
+    let box_x = (area.width.saturating_sub(box_width)) / 2;
+    let box_y = (area.height.saturating_sub(box_height)) / 2;
+
+    let overlay_area = Rect::new(box_x, box_y, box_width, box_height);
+
+    frame.render_widget(Clear, overlay_area);
+
+    let label_style = Style::default().fg(Color::Rgb(120, 120, 130));
+    let value_style = Style::default().fg(Color::Rgb(200, 200, 200));
+
+    let account = app
+        .current_account_name()
+        .unwrap_or("legacy session")
+        .to_string();
+    let stats = &app.stats;
+    let rows = [
+        ("Account:    ", account),
+        ("Uptime:     ", format_uptime(stats.started.elapsed())),
+        ("Chats:      ", app.chats.len().to_string()),
+        ("Messages:   ", app.cached_message_count().to_string()),
+        ("Sent:       ", stats.sent.to_string()),
+        ("Received:   ", stats.received.to_string()),
+        ("Reconnects: ", stats.reconnects.to_string()),
+    ];
+    let mut lines = vec![Line::from("")];
+    lines.extend(rows.into_iter().map(|(label, value)| {
+        Line::from(vec![
+            Span::styled(label, label_style),
+            Span::styled(value, value_style),
+        ])
+    }));
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Rgb(100, 200, 255)))
+        .border_set(border_set(&app.config))
+        .title(" :stats ");
+
+    frame.render_widget(Paragraph::new(lines).block(block), overlay_area);
+}
+
 /// Draw the AI command overlay
 fn draw_ai_overlay(frame: &mut Frame, app: &App, area: Rect) {
     use ratatui::text::{Line, Span};
     use ratatui::widgets::Clear;
 
+    let g = glyphs(&app.config);
     // Calculate overlay dimensions (60% width, 40% height)
     let overlay_width = (area.width * 60 / 100)
         .max(50)
@@ -738,35 +2460,35 @@ fn draw_ai_overlay(frame: &mut Frame, app: &App, area: Rect) {
             Style::default().fg(Color::Rgb(150, 150, 160)),
         )));
         lines.push(Line::from(Span::styled(
-            "  • mute this chat for 2 hours",
+            format!("  {} mute this chat for 2 hours", g.bullet),
             Style::default().fg(Color::Rgb(100, 180, 255)),
         )));
         lines.push(Line::from(Span::styled(
-            "  • find messages about meeting",
+            format!("  {} find messages about meeting", g.bullet),
             Style::default().fg(Color::Rgb(100, 180, 255)),
         )));
         lines.push(Line::from(Span::styled(
-            "  • send hello to @username",
+            format!("  {} send hello to @username", g.bullet),
             Style::default().fg(Color::Rgb(100, 180, 255)),
         )));
     }
 
     lines.push(Line::from(""));
     lines.push(Line::from(Span::styled(
-        "Enter submit • Esc cancel",
+        format!("Enter submit {} Esc cancel", g.separator),
         Style::default().fg(Color::Rgb(100, 100, 110)),
     )));
 
     let title = if app.ai_input.is_empty() {
-        " 🤖 AI Command ".to_string()
+        format!(" {} AI Command ", g.bot)
     } else {
-        format!(" 🤖 AI: {} ", app.ai_input)
+        format!(" {} AI: {} ", g.bot, app.ai_input)
     };
 
     let block = Block::default()
         .borders(Borders::ALL)
         .border_style(Style::default().fg(Color::Rgb(255, 100, 255)))
-        .border_type(ratatui::widgets::BorderType::Rounded)
+        .border_set(border_set(&app.config))
         .title(title);
 
     let paragraph = Paragraph::new(lines)
@@ -781,6 +2503,7 @@ fn draw_code_overlay(frame: &mut Frame, app: &App, area: Rect) {
     use ratatui::text::{Line, Span};
     use ratatui::widgets::Clear;
 
+    let g = glyphs(&app.config);
     // Calculate overlay dimensions (80% width, 70% height)
     let overlay_width = (area.width * 80 / 100)
         .max(60)
@@ -844,35 +2567,35 @@ fn draw_code_overlay(frame: &mut Frame, app: &App, area: Rect) {
             Style::default().fg(Color::Rgb(150, 150, 160)),
         )));
         lines.push(Line::from(Span::styled(
-            "  • explain rust ownership",
+            format!("  {} explain rust ownership", g.bullet),
             Style::default().fg(Color::Rgb(100, 255, 200)),
         )));
         lines.push(Line::from(Span::styled(
-            "  • write a python function to sort a list",
+            format!("  {} write a python function to sort a list", g.bullet),
             Style::default().fg(Color::Rgb(100, 255, 200)),
         )));
         lines.push(Line::from(Span::styled(
-            "  • debug: why does this segfault",
+            format!("  {} debug: why does this segfault", g.bullet),
             Style::default().fg(Color::Rgb(100, 255, 200)),
         )));
     }
 
     lines.push(Line::from(""));
     lines.push(Line::from(Span::styled(
-        "Enter submit • Ctrl+j/k scroll • Esc exit",
+        format!("Enter submit {0} Ctrl+j/k scroll {0} Esc exit", g.separator),
         Style::default().fg(Color::Rgb(100, 100, 110)),
     )));
 
     let title = if app.code_input.is_empty() {
-        " 💻 Code Assistant ".to_string()
+        format!(" {} Code Assistant ", g.code)
     } else {
-        format!(" 💻 > {} ", app.code_input)
+        format!(" {} > {} ", g.code, app.code_input)
     };
 
     let block = Block::default()
         .borders(Borders::ALL)
         .border_style(Style::default().fg(Color::Rgb(100, 255, 200)))
-        .border_type(ratatui::widgets::BorderType::Rounded)
+        .border_set(border_set(&app.config))
         .title(title);
 
     let paragraph = Paragraph::new(lines)
@@ -881,3 +2604,412 @@ fn draw_code_overlay(frame: &mut Frame, app: &App, area: Rect) {
 
     frame.render_widget(paragraph, overlay_area);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uptime_shows_the_two_largest_units() {
+        assert_eq!(format_uptime(Duration::from_secs(42)), "42s");
+        assert_eq!(format_uptime(Duration::from_secs(5 * 60 + 7)), "5m 07s");
+        assert_eq!(format_uptime(Duration::from_secs(3 * 3600 + 125)), "3h 02m");
+    }
+
+    #[test]
+    fn compact_drops_the_blank_line_between_messages() {
+        let mut app = App::new();
+        app.add_chat(10, "Alice".to_string());
+        app.add_message(10, 1, "Alice".to_string(), "hi".to_string(), false, 0);
+        app.add_message(10, 2, "You".to_string(), "hey".to_string(), true, 0);
+
+        let (items, rows, _) = build_message_items(&app, 60);
+        assert_eq!(items.len(), 4);
+        assert_eq!(rows, [0..2, 2..4]);
+
+        app.config.compact = true;
+        let (items, rows, _) = build_message_items(&app, 60);
+        assert_eq!(items.len(), 2);
+        assert_eq!(rows, [0..1, 1..2]);
+    }
+
+    #[test]
+    fn search_matches_stay_highlighted_across_wrapped_lines() {
+        use ratatui::{backend::TestBackend, Terminal};
+
+        let mut app = App::new();
+        app.add_chat(10, "Alice".to_string());
+        let text = "the cat sat on the mat next to another Cat";
+        app.add_message(10, 1, "Alice".to_string(), text.to_string(), false, 0);
+        app.message_search_query = Some("CAT".to_string());
+
+        // Highlighted runs of each rendered row, top to bottom
+        let highlighted = |app: &App| {
+            let mut terminal = Terminal::new(TestBackend::new(40, 12)).unwrap();
+            terminal
+                .draw(|frame| draw_chats_panel(frame, app, frame.area()))
+                .unwrap();
+            let buffer = terminal.backend().buffer().clone();
+            let mut runs = Vec::new();
+            for y in 0..buffer.area.height {
+                let mut run = String::new();
+                for x in 0..buffer.area.width {
+                    let cell = &buffer[(x, y)];
+                    if cell.bg == Color::Rgb(230, 190, 80) {
+                        run.push_str(cell.symbol());
+                    } else if !run.is_empty() {
+                        runs.push(std::mem::take(&mut run));
+                    }
+                }
+                if !run.is_empty() {
+                    runs.push(run);
+                }
+            }
+            runs
+        };
+
+        // Matches are stored as (message index, byte range) with the layout
+        let runs = highlighted(&app);
+        assert_eq!(runs, ["cat", "Cat"]);
+        assert_eq!(app.layout_cache.borrow().matches, [(0, 4..7), (0, 39..42)]);
+
+        // The message wraps over several rows; the second match is on a later one
+        let (_, rows, _) = build_message_items(&app, 36);
+        assert!(rows[0].len() > 2);
+
+        // Scrolling redraws from the same stored matches
+        app.scroll_offset.set(1);
+        assert_eq!(highlighted(&app), ["cat", "Cat"]);
+
+        app.message_search_query = None;
+        assert!(highlighted(&app).is_empty());
+    }
+
+    #[test]
+    fn active_topic_narrows_the_panel_to_its_messages() {
+        use ratatui::{backend::TestBackend, Terminal};
+
+        let mut app = App::new();
+        app.add_chat(10, "Forum".to_string());
+        for (id, text, topic) in [(1, "in rust", 5), (2, "in general", 1), (3, "also rust", 5)] {
+            app.add_message(10, id, "Alice".to_string(), text.to_string(), false, 0);
+            app.set_topic(10, id, Some(topic));
+        }
+        app.active_topic.insert(10, (5, "Rust".to_string()));
+        app.panel = Panel::Chats;
+        app.selected_message = 1;
+        assert_eq!(app.selected_message().map(|m| m.id), Some(1));
+
+        let (_, rows, _) = build_message_items(&app, 60);
+        assert_eq!(rows.len(), 2);
+
+        // The highlighted message is the one the cursor acts on
+        let mut terminal = Terminal::new(TestBackend::new(60, 12)).unwrap();
+        terminal
+            .draw(|frame| draw_chats_panel(frame, &app, frame.area()))
+            .unwrap();
+        let buffer = terminal.backend().buffer().clone();
+        let screen: Vec<String> = (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect()
+            })
+            .collect();
+        assert!(!screen.iter().any(|line| line.contains("in general")));
+        let highlighted: Vec<&String> = screen
+            .iter()
+            .enumerate()
+            .filter(|(y, _)| {
+                (0..buffer.area.width).any(|x| buffer[(x, *y as u16)].bg == Color::Rgb(40, 45, 60))
+            })
+            .map(|(_, line)| line)
+            .filter(|line| line.contains("rust") || line.contains("general"))
+            .collect();
+        assert_eq!(highlighted.len(), 1, "{:?}", screen);
+        assert!(highlighted[0].contains("in rust"), "{:?}", screen);
+    }
+
+    #[test]
+    fn ascii_only_draws_no_unicode() {
+        use ratatui::{backend::TestBackend, Terminal};
+
+        let mut app = App::new();
+        app.add_chat(10, "Alice".to_string());
+        app.chats[0].muted_until = Some(MUTE_FOREVER);
+        app.chats[0].pinned = vec![(1, "pinned".to_string())];
+        app.chats[0].unread = 3;
+        app.add_message(10, 1, "You".to_string(), "hello there".to_string(), true, 0);
+        app.add_message(10, 2, "Alice".to_string(), String::new(), false, 0);
+        app.set_media(10, 2, "Voice (0:14)".to_string());
+        app.set_media_kind(10, 2, crate::app::MediaKind::Voice);
+        app.config.unread_style = UnreadStyle::Bullet;
+
+        let rendered = |app: &App| {
+            let mut terminal = Terminal::new(TestBackend::new(100, 24)).unwrap();
+            terminal.draw(|frame| draw(frame, app)).unwrap();
+            let buffer = terminal.backend().buffer().clone();
+            buffer
+                .content()
+                .iter()
+                .map(|cell| cell.symbol())
+                .collect::<String>()
+        };
+
+        app.config.ascii_only = Some(false);
+        assert!(!rendered(&app).is_ascii());
+
+        app.config.ascii_only = Some(true);
+        let screen = rendered(&app);
+        assert!(screen.is_ascii(), "{}", screen);
+        assert!(screen.contains("Alice (muted) *"));
+        assert!(screen.contains("* Pinned"));
+        assert!(screen.contains("> * hello there"));
+        assert!(screen.contains("[mic] Voice (0:14)"));
+    }
+
+    #[test]
+    fn full_width_input_runs_under_both_panels() {
+        use ratatui::{backend::TestBackend, Terminal};
+
+        // Column where the input box title starts
+        let title_x = |app: &App| {
+            let mut terminal = Terminal::new(TestBackend::new(100, 24)).unwrap();
+            terminal.draw(|frame| draw(frame, app)).unwrap();
+            let buffer = terminal.backend().buffer().clone();
+            (0..buffer.area.height).find_map(|y| {
+                let row: String = (0..buffer.area.width)
+                    .map(|x| buffer[(x, y)].symbol().chars().next().unwrap_or(' '))
+                    .collect();
+                row.find("type to send").map(|i| row[..i].chars().count())
+            })
+        };
+
+        let mut app = App::new();
+        assert!(title_x(&app).is_some_and(|x| x > 30));
+        app.config.full_width_input = true;
+        assert!(title_x(&app).is_some_and(|x| x < 10));
+    }
+
+    #[test]
+    fn find_matches_ignores_case_and_keeps_byte_offsets() {
+        assert_eq!(find_matches("Cat cAT", "cat"), [0..3, 4..7]);
+        assert_eq!(find_matches("héllo HÉLLO", "héllo"), [0..6, 7..13]);
+        assert_eq!(find_matches("aaaa", "aa"), [0..2, 2..4]);
+        assert!(find_matches("dog", "cat").is_empty());
+        assert!(find_matches("cat", "").is_empty());
+    }
+
+    /// Wrapped lines of `text` as strings
+    fn wrapped(text: &str, width: usize) -> Vec<String> {
+        wrap_words(text, width, false)
+            .iter()
+            .map(|words| join_words(text, words))
+            .collect()
+    }
+
+    /// (text, is bold, is italic, is code) of each span of a wrapped line
+    fn runs(
+        text: &str,
+        width: usize,
+        line: usize,
+        formats: &[(Range<usize>, TextFormat)],
+    ) -> Vec<(String, bool, bool, bool)> {
+        let words = &wrap_words(text, width, false)[line];
+        styled_line(text, words, formats, &[], Style::default(), &[])
+            .into_iter()
+            .map(|span| {
+                let m = span.style.add_modifier;
+                (
+                    span.content.to_string(),
+                    m.contains(Modifier::BOLD),
+                    m.contains(Modifier::ITALIC),
+                    span.style.bg.is_some(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn message_view_scrolls_no_further_than_its_last_line() {
+        use ratatui::{backend::TestBackend, Terminal};
+
+        let mut app = App::new();
+        app.add_chat(10, "Alice".to_string());
+        let text = (1..=40)
+            .map(|n| format!("line {}", n))
+            .collect::<Vec<_>>()
+            .join("\n");
+        app.add_message(10, 1, "Alice".to_string(), text, false, 0);
+        app.panel = crate::app::Panel::Chats;
+        app.view_message();
+        assert_eq!(app.mode, Mode::MessageView);
+
+        // 40 lines in a 20-row box (18 inside the borders)
+        let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+        terminal.draw(|frame| draw(frame, &app)).unwrap();
+        for _ in 0..100 {
+            app.scroll_message_view(true);
+        }
+        assert_eq!(app.message_view_scroll, 22);
+        app.scroll_message_view(false);
+        assert_eq!(app.message_view_scroll, 21);
+    }
+
+    #[test]
+    fn url_wrapped_over_lines_is_underlined_on_each() {
+        let text = "see https://example.com/a/long/path ok";
+        let links = crate::links::find_urls(text);
+        let underlined = |line: usize| -> Vec<String> {
+            let words = &wrap_words(text, 12, false)[line];
+            styled_line(text, words, &[], &[], Style::default(), &links)
+                .into_iter()
+                .filter(|span| span.style.add_modifier.contains(Modifier::UNDERLINED))
+                .map(|span| span.content.to_string())
+                .collect()
+        };
+        assert_eq!(
+            wrapped(text, 12),
+            ["see", "https://exam", "ple.com/a/lo", "ng/path", "ok"]
+        );
+        assert!(underlined(0).is_empty());
+        assert_eq!(underlined(1), ["https://exam"]);
+        assert_eq!(underlined(2), ["ple.com/a/lo"]);
+        assert_eq!(underlined(3), ["ng/path"]);
+    }
+
+    #[test]
+    fn input_cursor_counts_display_width() {
+        assert_eq!(input_cursor("hello", 3), (5, 0));
+        // é is one column, 🦀 and each CJK character two
+        assert_eq!(input_cursor("héllo 🦀", 3), (8, 0));
+        assert_eq!(input_cursor(":goto 你好", 3), (10, 0));
+        assert_eq!(input_cursor("first\n日本語", 3), (6, 1));
+        // Rows stop at the last visible line of the box
+        assert_eq!(input_cursor("a\nb\nc\nd", 1), (1, 1));
+    }
+
+    #[test]
+    fn truncating_keeps_a_long_word_to_one_cut_line() {
+        let text = "see https://example.com/a/very/long/path ok";
+        let lines = wrap_words(text, 12, true);
+        let joined: Vec<String> = lines.iter().map(|w| join_words(text, w)).collect();
+        assert_eq!(joined, ["see", "https://exa", "ok"]);
+        let cut: Vec<bool> = lines.iter().map(|w| is_cut(text, w)).collect();
+        assert_eq!(cut, [false, true, false]);
+    }
+
+    #[test]
+    fn wraps_on_words_and_splits_long_ones() {
+        assert_eq!(wrapped("hello bold world", 10), ["hello bold", "world"]);
+        assert_eq!(wrapped("a  b\nc", 10), ["a b c"]);
+        assert_eq!(wrapped("abcdefghij xy", 4), ["abcd", "efgh", "ij", "xy"]);
+        assert_eq!(wrapped("", 4), [""]);
+    }
+
+    #[test]
+    fn bold_word_keeps_its_style_on_a_later_line() {
+        let formats = [(6..10, TextFormat::Bold)];
+        assert_eq!(
+            runs("hello bold world", 8, 1, &formats),
+            [("bold".to_string(), true, false, false)]
+        );
+        assert_eq!(
+            runs("hello bold world", 8, 2, &formats),
+            [("world".to_string(), false, false, false)]
+        );
+    }
+
+    #[test]
+    fn format_across_a_wrap_covers_both_lines() {
+        // Italic from "two" to the end, wrapped after "two three"
+        let text = "one two three four";
+        let formats = [(4..18, TextFormat::Italic)];
+        assert_eq!(
+            runs(text, 13, 0, &formats),
+            [
+                ("one ".to_string(), false, false, false),
+                ("two three".to_string(), false, true, false)
+            ]
+        );
+        assert_eq!(
+            runs(text, 13, 1, &formats),
+            [("four".to_string(), false, true, false)]
+        );
+    }
+
+    #[test]
+    fn code_in_a_split_word_and_multibyte_text() {
+        let formats = [(2..6, TextFormat::Code)];
+        assert_eq!(
+            runs("abcdefghij", 4, 0, &formats),
+            [
+                ("ab".to_string(), false, false, false),
+                ("cd".to_string(), false, false, true)
+            ]
+        );
+        assert_eq!(
+            runs("abcdefghij", 4, 1, &formats),
+            [
+                ("ef".to_string(), false, false, true),
+                ("gh".to_string(), false, false, false)
+            ]
+        );
+
+        // "wörld" starts at byte 7 since é takes two bytes
+        let formats = [(7..13, TextFormat::Bold)];
+        assert_eq!(
+            runs("héllo wörld", 20, 0, &formats),
+            [
+                ("héllo ".to_string(), false, false, false),
+                ("wörld".to_string(), true, false, false)
+            ]
+        );
+    }
+
+    /// Frame time over a 1000-message chat with and without the layout cache.
+    /// Run with `cargo test --release -- --ignored --nocapture layout_cache_benchmark`
+    #[test]
+    #[ignore]
+    fn layout_cache_benchmark() {
+        use ratatui::{backend::TestBackend, Terminal};
+        use std::time::Instant;
+
+        let mut app = App::new();
+        app.add_chat(10, "Alice".to_string());
+        for id in 1..=1000 {
+            let text = format!(
+                "message {} with enough words in it to wrap across a line or two of the bubble",
+                id
+            );
+            app.add_message(
+                10,
+                id,
+                "Alice".to_string(),
+                text,
+                id % 3 == 0,
+                i64::from(id) * 60,
+            );
+        }
+        let mut terminal = Terminal::new(TestBackend::new(200, 60)).unwrap();
+        const FRAMES: u32 = 300;
+
+        let mut time = |app: &mut App, rebuild: bool| {
+            let start = Instant::now();
+            for _ in 0..FRAMES {
+                if rebuild {
+                    app.messages_revision += 1;
+                }
+                terminal.draw(|frame| draw(frame, app)).unwrap();
+            }
+            start.elapsed() / FRAMES
+        };
+        let rebuilt = time(&mut app, true);
+        let cached = time(&mut app, false);
+        println!(
+            "rebuilt every frame: {:?}/frame, cached: {:?}/frame",
+            rebuilt, cached
+        );
+        assert!(cached < rebuilt);
+    }
+}
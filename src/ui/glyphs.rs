@@ -0,0 +1,254 @@
+use std::sync::OnceLock;
+
+use ratatui::symbols::border;
+use ratatui::widgets::BorderType;
+
+use crate::app::MediaKind;
+use crate::config::{BorderStyle, Config, NotifyLevel};
+
+/// Every decorative character the UI draws, so terminals without Unicode or
+/// emoji support can get plain ASCII ones by swapping this one set
+pub struct Glyphs {
+    pub border: Option<border::Set>, // Replaces the configured border style when set
+    pub divider: &'static str,       // Between folder tabs
+    pub rule: &'static str,          // Repeated into a horizontal line
+    pub ellipsis: &'static str,      // End of cut text, one column wide
+    pub caret: &'static str,         // After the text being typed in a title
+    pub cursor: &'static str,        // End of the scratchpad text
+    pub selected: &'static str,      // Current account in the picker
+    pub active: &'static str,        // Active account in the outer frame
+    pub bullet: &'static str,        // Unread dot, list items, hidden passphrase characters
+    pub separator: &'static str,     // Between hints in overlay footers
+    pub dot: &'static str,           // Between hints in the scratchpad footer
+    pub dash: &'static str,          // Around service messages and in hints
+    pub times: &'static str,         // Search match count
+    pub arrows: &'static str,        // Up/down keys in mode hints
+    pub new_below: &'static str,     // New messages below the view
+    pub outgoing: &'static str,      // First line of an outgoing message
+    pub forwarded: &'static str,     // "Forwarded from" line
+    pub reply: &'static str,         // Replying to, in the INSERT title
+    pub pin: &'static str,           // Pinned messages and the pinned banner
+    pub muted: &'static str,         // Muted chats
+    pub lock: &'static str,          // Lock screen
+    pub bot: &'static str,           // Bots, and the AI overlay
+    pub blocked: &'static str,       // Blocked users
+    pub notify_mentions: &'static str,
+    pub notify_none: &'static str,
+    pub reconnecting: &'static str,
+    pub offline: &'static str,
+    pub welcome: &'static str,
+    pub searching: &'static str,
+    pub found: &'static str,
+    pub not_found: &'static str,
+    pub warning: &'static str,
+    pub code: &'static str,
+    pub photo: &'static str, // Media and calls, before their label
+    pub voice: &'static str,
+    pub file: &'static str,
+    pub call: &'static str,
+    pub video_call: &'static str,
+}
+
+pub const UNICODE: Glyphs = Glyphs {
+    border: None,
+    divider: "│",
+    rule: "─",
+    ellipsis: "…",
+    caret: "▏",
+    cursor: "█",
+    selected: "✓",
+    active: "●",
+    bullet: "•",
+    separator: "•",
+    dot: "·",
+    dash: "—",
+    times: "×",
+    arrows: "↑↓",
+    new_below: "↓",
+    outgoing: "▸",
+    forwarded: "↪",
+    reply: "↩",
+    pin: "📌",
+    muted: "🔇",
+    lock: "🔒",
+    bot: "🤖",
+    blocked: "⛔",
+    notify_mentions: "🔔@",
+    notify_none: "🔕",
+    reconnecting: "⟳",
+    offline: "✗",
+    welcome: "⚡",
+    searching: "🔍",
+    found: "✅",
+    not_found: "❌",
+    warning: "⚠️",
+    code: "💻",
+    photo: "🖼",
+    voice: "🎤",
+    file: "📎",
+    call: "📞",
+    video_call: "📹",
+};
+
+pub const ASCII: Glyphs = Glyphs {
+    border: Some(border::Set {
+        top_left: "+",
+        top_right: "+",
+        bottom_left: "+",
+        bottom_right: "+",
+        vertical_left: "|",
+        vertical_right: "|",
+        horizontal_top: "-",
+        horizontal_bottom: "-",
+    }),
+    divider: "|",
+    rule: "-",
+    ellipsis: "~",
+    caret: "_",
+    cursor: "_",
+    selected: "[v]",
+    active: "*",
+    bullet: "*",
+    separator: "|",
+    dot: "-",
+    dash: "-",
+    times: "x",
+    arrows: "Up/Down",
+    new_below: "v",
+    outgoing: ">",
+    forwarded: "->",
+    reply: "<-",
+    pin: "*",
+    muted: "(muted)",
+    lock: "[lock]",
+    bot: "[bot]",
+    blocked: "[x]",
+    notify_mentions: "[@]",
+    notify_none: "[off]",
+    reconnecting: "~",
+    offline: "x",
+    welcome: "*",
+    searching: "[?]",
+    found: "[v]",
+    not_found: "[x]",
+    warning: "[!]",
+    code: "[code]",
+    photo: "[img]",
+    voice: "[mic]",
+    file: "[file]",
+    call: "[call]",
+    video_call: "[video]",
+};
+
+/// The glyph set for the configured `ascii_only`, detected when unset
+pub fn glyphs(config: &Config) -> &'static Glyphs {
+    static DETECTED: OnceLock<bool> = OnceLock::new();
+    let ascii = config
+        .ascii_only
+        .unwrap_or_else(|| *DETECTED.get_or_init(|| detect_ascii(|name| std::env::var(name).ok())));
+    if ascii {
+        &ASCII
+    } else {
+        &UNICODE
+    }
+}
+
+/// Whether the environment looks like a terminal that can't show Unicode: a
+/// non-UTF-8 locale (the first of LC_ALL, LC_CTYPE and LANG that's set wins),
+/// or the Linux console and old VT terminals. No locale at all counts as
+/// Unicode, as most terminal emulators on macOS and Windows don't set one
+fn detect_ascii(var: impl Fn(&str) -> Option<String>) -> bool {
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .into_iter()
+        .find_map(|name| var(name).filter(|value| !value.is_empty()));
+    if let Some(locale) = locale {
+        let locale = locale.to_lowercase();
+        if !locale.contains("utf-8") && !locale.contains("utf8") {
+            return true;
+        }
+    }
+    matches!(
+        var("TERM").as_deref(),
+        Some("linux" | "vt100" | "vt102" | "vt220" | "dumb")
+    )
+}
+
+impl Glyphs {
+    /// Border symbols for the configured style, unless this set replaces them
+    pub fn border_set(&self, style: BorderStyle) -> border::Set {
+        self.border.unwrap_or_else(|| {
+            match style {
+                BorderStyle::Rounded => BorderType::Rounded,
+                BorderStyle::Plain => BorderType::Plain,
+                BorderStyle::Double => BorderType::Double,
+                BorderStyle::Thick => BorderType::Thick,
+            }
+            .to_border_set()
+        })
+    }
+
+    /// A media label or service text behind the glyph of its kind, if it has one
+    pub fn with_media(&self, kind: Option<MediaKind>, label: &str) -> String {
+        let glyph = match kind {
+            Some(MediaKind::Photo) => self.photo,
+            Some(MediaKind::Voice) => self.voice,
+            Some(MediaKind::File) => self.file,
+            Some(MediaKind::Call) => self.call,
+            Some(MediaKind::VideoCall) => self.video_call,
+            // Stickers and GIFs are labelled in brackets already
+            Some(MediaKind::Sticker | MediaKind::Gif) | None => return label.to_string(),
+        };
+        format!("{} {}", glyph, label)
+    }
+
+    /// Marker after the chat name in the friends list (none for the default)
+    pub fn notify(&self, level: NotifyLevel) -> String {
+        match level {
+            NotifyLevel::All => String::new(),
+            NotifyLevel::Mentions => format!(" {}", self.notify_mentions),
+            NotifyLevel::None => format!(" {}", self.notify_none),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(vars: &[(&str, &str)]) -> bool {
+        detect_ascii(|name| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value.to_string())
+        })
+    }
+
+    #[test]
+    fn utf8_locale_or_none_keeps_unicode() {
+        assert!(!detect(&[("LANG", "en_US.UTF-8")]));
+        assert!(!detect(&[
+            ("LC_CTYPE", "de_DE.utf8"),
+            ("TERM", "xterm-256color")
+        ]));
+        assert!(!detect(&[]));
+    }
+
+    #[test]
+    fn non_utf8_locale_falls_back_to_ascii() {
+        assert!(detect(&[("LANG", "C")]));
+        assert!(detect(&[("LC_ALL", "POSIX")]));
+    }
+
+    #[test]
+    fn first_set_locale_variable_wins() {
+        assert!(detect(&[("LC_ALL", "C"), ("LANG", "en_US.UTF-8")]));
+        assert!(!detect(&[("LC_ALL", "en_US.UTF-8"), ("LANG", "C")]));
+        assert!(!detect(&[("LC_ALL", ""), ("LANG", "en_US.UTF-8")]));
+    }
+
+    #[test]
+    fn linux_console_falls_back_to_ascii() {
+        assert!(detect(&[("LANG", "en_US.UTF-8"), ("TERM", "linux")]));
+        assert!(detect(&[("TERM", "vt100")]));
+    }
+}
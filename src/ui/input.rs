@@ -1,4 +1,5 @@
-use crate::app::{App, Mode};
+use crate::app::{App, Mode, Panel};
+use crate::config::SendKey;
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
 /// Handle keyboard input based on current mode
@@ -9,6 +10,17 @@ pub fn handle_key(app: &mut App, key: KeyEvent) -> Option<String> {
         return None;
     }
 
+    app.note_input();
+    if app.locked {
+        handle_locked(app, key);
+        return None;
+    }
+
+    // Any key in normal mode dismisses the last status message
+    if app.mode == Mode::Normal {
+        app.status_message = None;
+    }
+
     match app.mode {
         Mode::Normal => handle_normal_mode(app, key),
         Mode::Insert => handle_insert_mode(app, key),
@@ -18,11 +30,64 @@ pub fn handle_key(app: &mut App, key: KeyEvent) -> Option<String> {
         Mode::FindUser => handle_find_user_mode(app, key),
         Mode::AICommand => handle_ai_command_mode(app, key),
         Mode::Code => handle_code_mode(app, key),
+        Mode::WhoAmI | Mode::Stats => handle_whoami_mode(app, key),
+        Mode::LinkPicker => handle_link_picker_mode(app, key),
+        Mode::PinList => handle_pin_list_mode(app, key),
+        Mode::Visual => handle_visual_mode(app, key),
+        Mode::Quote => handle_quote_mode(app, key),
+        Mode::TopicPicker => handle_topic_picker_mode(app, key),
+        Mode::StickerPicker => handle_sticker_picker_mode(app, key),
+        Mode::MessageView => handle_message_view_mode(app, key),
+        Mode::NumberJump => handle_number_jump_mode(app, key),
+        Mode::Confirm => handle_confirm_mode(app, key),
+        Mode::Scratchpad => handle_scratchpad_mode(app, key),
+        Mode::CommandPalette => handle_palette_mode(app, key),
+    }
+}
+
+/// Handle a bracketed paste: the text goes into the focused input as typed,
+/// newlines included, so it never sends on its own
+pub fn handle_paste(app: &mut App, text: &str) {
+    app.note_input();
+    if app.locked {
+        return;
+    }
+    match app.mode {
+        Mode::Normal => {
+            app.enter_insert();
+            if app.mode == Mode::Insert {
+                app.insert_pasted(text);
+            }
+        }
+        Mode::Insert => app.insert_pasted(text),
+        Mode::Scratchpad => app.scratchpad.push_str(&text.replace("\r\n", "\n")),
+        // Single-line inputs get the lines joined
+        Mode::Search => {
+            app.search_input.push_str(&single_line(text));
+            app.update_search_filter();
+        }
+        Mode::Command => app.command_input.push_str(&single_line(text)),
+        _ => {}
     }
 }
 
+fn single_line(text: &str) -> String {
+    text.lines().map(str::trim).collect::<Vec<_>>().join(" ")
+}
+
 /// Handle keys in normal mode (vim navigation)
 fn handle_normal_mode(app: &mut App, key: KeyEvent) -> Option<String> {
+    // Second key of a ' letter jump
+    if app.letter_jump_pending {
+        app.letter_jump_pending = false;
+        if let KeyCode::Char(c) = key.code {
+            if c.is_alphanumeric() {
+                app.jump_to_letter(c);
+            }
+        }
+        return None;
+    }
+
     match key.code {
         // Navigation
         KeyCode::Char('j') | KeyCode::Down => app.move_down(),
@@ -33,29 +98,92 @@ fn handle_normal_mode(app: &mut App, key: KeyEvent) -> Option<String> {
         // Mode switching
         KeyCode::Char('i') => app.enter_insert(),
 
+        // Paste the clipboard into the input
+        KeyCode::Char('v') if key.modifiers.contains(KeyModifiers::CONTROL) => app.request_paste(),
+
+        // Command palette
+        KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => app.enter_palette(),
+
+        // Reply to the latest incoming message
+        KeyCode::Char('R') => app.reply_to_latest(),
+
         // Search mode
         KeyCode::Char('/') => app.enter_search(),
 
+        // Open link from the latest message that has one
+        KeyCode::Char('o') => app.open_link(),
+
+        // Pin/unpin the latest message, cycle the pinned banner
+        KeyCode::Char('p') => app.toggle_pin(),
+        KeyCode::Char('P') => app.cycle_pinned(),
+
+        // Pick a forum topic
+        KeyCode::Char('T') => app.request_topics(),
+
+        // Notification level of the chat: all, mentions only, none
+        KeyCode::Char('b') => app.cycle_notify_level(),
+
+        // Show the selected message in full
+        KeyCode::Char('K') => app.view_message(),
+
+        // Copy the chat's t.me or invite link
+        KeyCode::Char('Y') => app.copy_chat_link(),
+
+        // Recent chats like tabs: Tab / Shift-Tab cycle, Alt+1..9 jump
+        KeyCode::Tab => app.cycle_recent(true),
+        KeyCode::BackTab => app.cycle_recent(false),
+        KeyCode::Char(c @ '1'..='9') if key.modifiers.contains(KeyModifiers::ALT) => {
+            app.jump_to_recent(c as usize - '1' as usize);
+        }
+
         // Reload current chat
-        KeyCode::Char('r') => app.reload_requested = true,
+        KeyCode::Char('r') => app.request_reload(),
 
         // Quit
         KeyCode::Char('q') => app.should_quit = true,
 
         // Disconnect (delete session and quit)
-        KeyCode::Char('D') => app.disconnect_requested = true,
+        KeyCode::Char('D') => app.request_disconnect(),
 
         // Account picker
         KeyCode::Char('A') => app.enter_account_picker(),
 
+        // Scratchpad notes
+        KeyCode::Char('S') => app.enter_scratchpad(),
+
+        // Select a range of messages to copy
+        KeyCode::Char('v') if app.panel == Panel::Chats => app.enter_visual(),
+
         // Command mode
         KeyCode::Char(':') => app.enter_command(),
 
+        // Jump to next chat starting with a letter (' then letter)
+        KeyCode::Char('\'') => app.letter_jump_pending = true,
+
+        // Number the chats and jump to one by typing its number
+        KeyCode::Char('f') => app.enter_number_jump(),
+
+        // Send the last failed message again
+        KeyCode::Char('.') => app.request_retry_send(),
+
+        // List only users, groups, channels or bots
+        KeyCode::Char('F') => app.cycle_chat_filter(),
+
         // Jump to top/bottom
-        KeyCode::Char('g') => app.selected_chat = 0,
-        KeyCode::Char('G') => {
-            app.selected_chat = app.chats.len().saturating_sub(1);
-        }
+        // (oldest/newest message in the chat panel)
+        KeyCode::Char('g') if app.panel == Panel::Chats => app.jump_to_oldest(),
+        KeyCode::Char('L') if app.panel == Panel::Chats => app.request_load_more(),
+        KeyCode::Char('G') if app.panel == Panel::Chats => app.jump_to_latest(),
+        KeyCode::Char('g') => app.jump_to_edge(false),
+        KeyCode::Char('G') => app.jump_to_edge(true),
+
+        // Step through :search results
+        KeyCode::Char('n') => app.step_search_result(true),
+        KeyCode::Char('N') => app.step_search_result(false),
+
+        // Cycle chat folders
+        KeyCode::Char(']') => app.cycle_folder(true),
+        KeyCode::Char('[') => app.cycle_folder(false),
 
         _ => {}
     }
@@ -70,23 +198,44 @@ fn handle_insert_mode(app: &mut App, key: KeyEvent) -> Option<String> {
             app.exit_insert();
         }
 
-        // Send message
-        KeyCode::Enter => {
-            if !app.input.is_empty() {
-                let message = app.input.clone();
-                app.input.clear();
-                return Some(message);
+        // Send message (terminals without Ctrl+Enter support report it as Ctrl+J)
+        KeyCode::Enter | KeyCode::Char('j')
+            if key.code == KeyCode::Enter || key.modifiers.contains(KeyModifiers::CONTROL) =>
+        {
+            let sends = app.config.send_on == SendKey::Enter
+                || key.modifiers.contains(KeyModifiers::CONTROL);
+            if !sends {
+                app.input.push('\n');
+            } else if let Some(secs) = app.slow_mode_remaining() {
+                app.status_message = Some(format!("Slow mode: wait {}s", secs));
+            } else if !app.input.trim().is_empty() {
+                // Large groups and channels ask first (y sends from the prompt)
+                match app.send_confirmation() {
+                    Some(action) => app.ask_confirm(action),
+                    None => return Some(app.take_outgoing()),
+                }
             }
         }
 
+        // Paste the clipboard (newlines stay in the message)
+        KeyCode::Char('v') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            app.paste_requested = true;
+        }
+
+        // Recall sent messages (starting with what's typed so far)
+        KeyCode::Up => app.history_up(),
+        KeyCode::Down => app.history_down(),
+
         // Delete character
         KeyCode::Backspace => {
             app.input.pop();
+            app.stop_history_recall();
         }
 
         // Type character
         KeyCode::Char(c) => {
             app.input.push(c);
+            app.stop_history_recall();
         }
 
         _ => {}
@@ -152,6 +301,11 @@ fn handle_account_picker_mode(app: &mut App, key: KeyEvent) -> Option<String> {
             app.select_account();
         }
 
+        // Log out highlighted account (session only)
+        KeyCode::Char('x') => {
+            app.log_out_selected_account();
+        }
+
         // Navigate accounts
         KeyCode::Down | KeyCode::Char('j') => {
             app.account_picker_move_down();
@@ -165,6 +319,201 @@ fn handle_account_picker_mode(app: &mut App, key: KeyEvent) -> Option<String> {
     None
 }
 
+/// Lock screen: any key unlocks, or Enter checks the typed passphrase when one is needed
+fn handle_locked(app: &mut App, key: KeyEvent) {
+    if !app.lock_needs_passphrase() {
+        app.submit_unlock();
+        return;
+    }
+    match key.code {
+        KeyCode::Enter => app.submit_unlock(),
+        KeyCode::Esc => app.lock_input.clear(),
+        KeyCode::Backspace => {
+            app.lock_input.pop();
+        }
+        KeyCode::Char(c) => app.lock_input.push(c),
+        _ => {}
+    }
+}
+
+/// Handle keys in the forum topic picker
+fn handle_topic_picker_mode(app: &mut App, key: KeyEvent) -> Option<String> {
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q') => app.exit_topics(),
+        KeyCode::Enter => app.select_topic(),
+        KeyCode::Down | KeyCode::Char('j') => app.topics_move_down(),
+        KeyCode::Up | KeyCode::Char('k') => app.topics_move_up(),
+        _ => {}
+    }
+    None
+}
+
+/// Handle keys in the saved sticker picker
+fn handle_sticker_picker_mode(app: &mut App, key: KeyEvent) -> Option<String> {
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q') => app.exit_stickers(),
+        KeyCode::Enter => app.select_sticker(),
+        KeyCode::Down | KeyCode::Char('j') => app.stickers_move_down(),
+        KeyCode::Up | KeyCode::Char('k') => app.stickers_move_up(),
+        _ => {}
+    }
+    None
+}
+
+/// Handle keys while typing a message number to jump to
+fn handle_number_jump_mode(app: &mut App, key: KeyEvent) -> Option<String> {
+    match key.code {
+        KeyCode::Esc => app.exit_number_jump(),
+        KeyCode::Enter => app.confirm_number_jump(),
+        KeyCode::Backspace => app.number_jump_backspace(),
+        KeyCode::Char(c) if c.is_ascii_digit() => app.number_jump_push(c),
+        _ => {}
+    }
+    None
+}
+
+/// Handle keys in the full message view (scroll or close)
+fn handle_message_view_mode(app: &mut App, key: KeyEvent) -> Option<String> {
+    match key.code {
+        KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') | KeyCode::Char('K') => {
+            app.exit_message_view()
+        }
+        KeyCode::Down | KeyCode::Char('j') => app.scroll_message_view(true),
+        KeyCode::Up | KeyCode::Char('k') => app.scroll_message_view(false),
+        _ => {}
+    }
+    None
+}
+
+/// Handle keys in the pinned messages list
+fn handle_pin_list_mode(app: &mut App, key: KeyEvent) -> Option<String> {
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q') => app.exit_pins(),
+        KeyCode::Enter => app.select_pin(),
+        KeyCode::Down | KeyCode::Char('j') => app.pins_move_down(),
+        KeyCode::Up | KeyCode::Char('k') => app.pins_move_up(),
+        _ => {}
+    }
+    None
+}
+
+/// Handle keys in visual mode (selecting messages)
+fn handle_visual_mode(app: &mut App, key: KeyEvent) -> Option<String> {
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('v') => app.exit_visual(),
+        KeyCode::Char('y') => app.yank_visual(),
+        KeyCode::Char('q') => app.start_quote(),
+        KeyCode::Down | KeyCode::Char('j') => app.move_down(),
+        KeyCode::Up | KeyCode::Char('k') => app.move_up(),
+        KeyCode::Char('g') => app.jump_to_oldest(),
+        KeyCode::Char('G') => app.jump_to_latest(),
+        _ => {}
+    }
+    None
+}
+
+/// Handle keys while picking the words to quote in a reply
+fn handle_quote_mode(app: &mut App, key: KeyEvent) -> Option<String> {
+    match key.code {
+        KeyCode::Esc => app.cancel_quote(),
+        KeyCode::Enter => app.confirm_quote(),
+        // Last word
+        KeyCode::Right | KeyCode::Char('l') => app.move_quote(false, true),
+        KeyCode::Left | KeyCode::Char('h') => app.move_quote(false, false),
+        // First word
+        KeyCode::Char('L') => app.move_quote(true, true),
+        KeyCode::Char('H') => app.move_quote(true, false),
+        _ => {}
+    }
+    None
+}
+
+/// Handle keys in link picker mode
+fn handle_link_picker_mode(app: &mut App, key: KeyEvent) -> Option<String> {
+    match key.code {
+        // Close picker
+        KeyCode::Esc => {
+            app.exit_link_picker();
+        }
+
+        // Open link
+        KeyCode::Enter => {
+            app.select_link();
+        }
+
+        // Navigate links
+        KeyCode::Down | KeyCode::Char('j') => {
+            app.link_picker_move_down();
+        }
+        KeyCode::Up | KeyCode::Char('k') => {
+            app.link_picker_move_up();
+        }
+
+        _ => {}
+    }
+    None
+}
+
+/// Handle keys in the y/n confirmation prompt
+fn handle_confirm_mode(app: &mut App, key: KeyEvent) -> Option<String> {
+    match key.code {
+        KeyCode::Char('y') | KeyCode::Char('Y') => return app.confirm(),
+        KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => app.cancel_confirm(),
+        _ => {}
+    }
+    None
+}
+
+/// Handle keys in the scratchpad (a plain multi-line editor)
+fn handle_scratchpad_mode(app: &mut App, key: KeyEvent) -> Option<String> {
+    match key.code {
+        // Close and save
+        KeyCode::Esc => app.exit_scratchpad(),
+
+        KeyCode::Enter => app.scratchpad.push('\n'),
+        KeyCode::Backspace => {
+            app.scratchpad.pop();
+        }
+        KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+            app.scratchpad.push(c);
+        }
+
+        _ => {}
+    }
+    None
+}
+
+/// Handle keys in the command palette (fuzzy-find an action)
+fn handle_palette_mode(app: &mut App, key: KeyEvent) -> Option<String> {
+    match key.code {
+        KeyCode::Esc => app.exit_palette(),
+        KeyCode::Enter => app.run_palette_selection(),
+
+        // Navigate matches
+        KeyCode::Down | KeyCode::Char('j') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            app.palette_move_down();
+        }
+        KeyCode::Up | KeyCode::Char('k') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            app.palette_move_up();
+        }
+        KeyCode::Down => app.palette_move_down(),
+        KeyCode::Up => app.palette_move_up(),
+
+        // Type to filter, back on the best match
+        KeyCode::Backspace => {
+            app.palette_input.pop();
+            app.palette_selected = 0;
+        }
+        KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+            app.palette_input.push(c);
+            app.palette_selected = 0;
+        }
+
+        _ => {}
+    }
+    None
+}
+
 /// Handle keys in command mode (: commands)
 fn handle_command_mode(app: &mut App, key: KeyEvent) -> Option<String> {
     match key.code {
@@ -291,3 +640,16 @@ fn handle_code_mode(app: &mut App, key: KeyEvent) -> Option<String> {
     }
     None
 }
+
+/// Handle keys in the :whoami and :stats overlays
+fn handle_whoami_mode(app: &mut App, key: KeyEvent) -> Option<String> {
+    match key.code {
+        // Close overlay
+        KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => {
+            app.exit_whoami();
+        }
+
+        _ => {}
+    }
+    None
+}
@@ -0,0 +1,121 @@
+/// A single keybinding as shown in help text
+pub struct KeyHelp {
+    pub keys: &'static str,
+    pub action: &'static str,
+}
+
+/// Bindings for one mode, in the order they're listed
+pub struct ModeHelp {
+    pub mode: &'static str,
+    pub bindings: &'static [KeyHelp],
+}
+
+const fn key(keys: &'static str, action: &'static str) -> KeyHelp {
+    KeyHelp { keys, action }
+}
+
+/// The active keymap, mirroring the handlers in input.rs
+pub const KEYMAP: &[ModeHelp] = &[
+    ModeHelp {
+        mode: "NORMAL MODE",
+        bindings: &[
+            key("j/k", "move/select msg"),
+            key("h/l", "panels"),
+            key("/", "search"),
+            key(":", "cmd"),
+            key("i", "insert"),
+            key("R", "reply to latest"),
+            key("Ctrl+P", "command palette"),
+            key("Ctrl+V", "paste & type"),
+            key("o", "open link"),
+            key("p", "pin/unpin"),
+            key("P", "next pinned"),
+            key("T", "forum topics"),
+            key("Y", "copy chat link"),
+            key("b", "notify level"),
+            key("v", "select & copy msgs"),
+            key("v q", "quote in reply"),
+            key("K", "show full message"),
+            key("'x", "jump to x"),
+            key("f", "jump to chat #"),
+            key("Tab/S-Tab", "recent chats"),
+            key("Alt+1-9", "recent N"),
+            key("g/G", "top/bottom"),
+            key("L", "load older msgs"),
+            key(".", "retry failed send"),
+            key("[/]", "folders"),
+            key("F", "filter by chat type"),
+            key("r", "reload"),
+            key("A", "accounts"),
+            key("q", "quit"),
+            key("D", "disconnect"),
+            key("S", "scratchpad notes"),
+        ],
+    },
+    ModeHelp {
+        mode: "COMMAND MODE",
+        bindings: &[
+            key(":find @user", "search any user"),
+            key(":goto @user", "open known chat"),
+            key(":msg id text", "send by chat id"),
+            key(":jump id", "go to message"),
+            key(":pins", "list pinned"),
+            key(":sticker q", "send sticker/GIF"),
+            key(":lock", "lock screen"),
+            key(":refresh", "reload chat list"),
+            key(":reconnect", "new connection"),
+            key(":search text", "search all chats"),
+            key(":filter kind", "only users/groups/..."),
+            key(":next / :prev", "step results (n/N)"),
+            key(":whoami", "current account"),
+            key(":stats", "session metrics"),
+            key(":set k=v", "change option"),
+            key(":mute [8h]", "mute chat"),
+            key(":unmute", "unmute"),
+            key(":block / :unblock", "block user"),
+            key(":export path", "save chat"),
+            key(":download [path]", "save media"),
+            key(":forget-credentials", "reset API id/hash"),
+        ],
+    },
+    ModeHelp {
+        mode: "SEARCH MODE",
+        bindings: &[
+            key("type", "filter"),
+            key("arrows", "nav"),
+            key("Enter", "jump"),
+        ],
+    },
+    ModeHelp {
+        mode: "INSERT MODE",
+        bindings: &[
+            key("type", "msg"),
+            key("Enter", "send (see send_on)"),
+            key("Up/Down", "recall sent"),
+            key("Ctrl+V", "paste clipboard"),
+            key("Esc", "cancel"),
+        ],
+    },
+];
+
+/// Pack a mode's bindings into lines no wider than `width`
+pub fn wrap_bindings(bindings: &[KeyHelp], width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+
+    for binding in bindings {
+        let entry = format!("{} {}", binding.keys, binding.action);
+        if !current.is_empty() && current.chars().count() + 2 + entry.chars().count() > width {
+            lines.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push_str("  ");
+        }
+        current.push_str(&entry);
+    }
+    if !current.is_empty() {
+        lines.push(current);
+    }
+
+    lines
+}
@@ -1,2 +1,4 @@
 pub mod draw;
+pub mod glyphs;
 pub mod input;
+pub mod keymap;